@@ -1,9 +1,15 @@
+pub mod asm;
+mod block_cache;
+#[cfg(test)]
+mod conformance;
 mod decode;
-mod decode_prefix_cb;
+pub mod disasm;
 mod execute;
 mod inst;
+mod table;
+pub mod trace;
 
-use crate::memory::{MemoryIF, IE, IF};
+use crate::memory::{MemoryIF, Model, IE, IF};
 use inst::{FlagReg, Inst, Reg16, Reg8};
 use std::fmt;
 
@@ -19,6 +25,12 @@ pub struct Cpu {
     ime: bool,
     flags: Flags,
     //halt: bool,
+    // LD B,B was the last instruction
+    breakpoint: bool,
+    // M-cycles since power on
+    cycles: u64,
+    tracer: Option<trace::Tracer>,
+    block_cache: Option<block_cache::BlockCache>,
 }
 
 impl Cpu {
@@ -33,6 +45,33 @@ impl Cpu {
                 halt: false,
                 stop: false,
             },
+            breakpoint: false,
+            cycles: 0,
+            tracer: None,
+            block_cache: None,
+        }
+    }
+    pub fn set_tracer(&mut self, tracer: trace::Tracer) {
+        self.tracer = Some(tracer);
+    }
+    /// Executes through a cache of decoded basic blocks, see `block_cache`.
+    pub fn enable_block_cache(&mut self) {
+        self.block_cache = Some(block_cache::BlockCache::new());
+    }
+    pub fn tracer_mut(&mut self) -> Option<&mut trace::Tracer> {
+        self.tracer.as_mut()
+    }
+    /// Registers as left by the DMG boot ROM.
+    pub fn skip_boot_rom(&mut self) {
+        let r = &mut self.reg;
+        (r.a, r.f, r.b, r.c) = (0x01, 0xb0, 0x00, 0x13);
+        (r.d, r.e, r.h, r.l) = (0x00, 0xd8, 0x01, 0x4d);
+        (r.sp, r.pc) = (0xfffe, 0x0100);
+    }
+    /// The CGB boot ROM leaves 0x11 in A, games check it to use CGB features.
+    pub fn set_model(&mut self, model: Model) {
+        if model == Model::Cgb {
+            self.reg.a = 0x11;
         }
     }
     pub fn run(
@@ -40,19 +79,32 @@ impl Cpu {
         memory: &mut impl MemoryIF,
         key_pressed: bool,
     ) -> Result<(u16, bool), String> {
+        self.cycles += 1;
+        if memory.cpu_paused() {
+            return Ok((self.reg.pc, self.flags.stop));
+        }
         self.clock_m += 1;
 
         if (self.clock_m >= self.m) || self.flags.halt || self.flags.stop {
             self.clock_m = 0;
             if !self.flags.halt && !self.flags.stop {
-                let (inst, addvance) = decode::decode(self.reg.pc, memory)?;
-                self.reg.pc += addvance;
-                (self.m, self.flags) = self.reg.execute(inst, memory, &mut self.ime)?;
+                if let Some(tracer) = &mut self.tracer {
+                    tracer.log((self.cycles - 1) * 4, &self.reg, memory)?;
+                }
+                // LD B,B
+                self.breakpoint = memory.read_byte(self.reg.pc) == 0x40;
+                (self.m, self.flags) = match &mut self.block_cache {
+                    Some(cache) => cache.step(&mut self.reg, memory, &mut self.ime)?,
+                    None => self.reg.step(memory, &mut self.ime)?,
+                };
                 //////////
                 // for cpu_instrs
                 // 0xc2fe ld a, 01
                 // 0xc300 ld (ff00+4d) a
                 // 0xc300 stop
+                if self.flags.stop {
+                    memory.speed_switch();
+                }
                 self.flags.stop = false;
                 //////////
             }
@@ -69,6 +121,11 @@ impl Cpu {
                     _ = self
                         .reg
                         .execute(Inst::Push16(Reg16::PC), memory, &mut self.ime)?;
+                    // the return address may overwrite cached code
+                    if let Some(cache) = &mut self.block_cache {
+                        cache.written(self.reg.sp);
+                        cache.written(self.reg.sp.wrapping_add(1));
+                    }
                     if masked & 0x01 != 0 {
                         // VBlank
                         memory.write_byte(IF, i_flag & !0x01);
@@ -101,6 +158,18 @@ impl Cpu {
         }
         Ok((self.reg.pc, self.flags.stop))
     }
+    /// The next `run` executes an instruction, unless the CPU is paused.
+    pub fn at_instruction(&self) -> bool {
+        self.clock_m + 1 >= self.m && !self.flags.halt && !self.flags.stop
+    }
+    /// `LD B,B` is used as a software breakpoint by test ROMs (and by BGB).
+    pub fn at_breakpoint(&self) -> bool {
+        self.breakpoint
+    }
+    pub fn regs_bcdehl(&self) -> [u8; 6] {
+        let r = &self.reg;
+        [r.b, r.c, r.d, r.e, r.h, r.l]
+    }
 }
 
 impl fmt::Display for Cpu {
@@ -113,7 +182,7 @@ impl fmt::Display for Cpu {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Registers {
     a: u8,
     b: u8,
@@ -0,0 +1,792 @@
+//! Small SM83 assembler.
+//!
+//! It accepts the syntax printed by `Display` for `Inst` (RGBDS style), plus
+//! labels (`name:`), `@` for the address of the current instruction,
+//! `db` / `dw` / `ds` and `SECTION "name", ROM0[$addr]` or
+//! `SECTION "name", ROMX[$addr], BANK[n]`.
+//! Code before the first `SECTION` goes to ROM0 at $0000.
+//!
+//! Opcodes are found by decoding every candidate with `decode::decode`, so the
+//! assembler cannot disagree with the decoder.
+
+use super::decode;
+use super::inst::{Arg16, Arg8, Inst, JpFlag, Reg16, Reg8};
+use crate::memory::MemoryIF;
+use std::collections::HashMap;
+use std::fs;
+
+const BANK_SIZE: usize = 0x4000;
+const NINTENDO_LOGO: [u8; 48] = [
+    0xce, 0xed, 0x66, 0x66, 0xcc, 0x0d, 0x00, 0x0b, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0c, 0x00, 0x0d,
+    0x00, 0x08, 0x11, 0x1f, 0x88, 0x89, 0x00, 0x0e, 0xdc, 0xcc, 0x6e, 0xe6, 0xdd, 0xdd, 0xd9, 0x99,
+    0xbb, 0xbb, 0x67, 0x63, 0x6e, 0x0e, 0xec, 0xcc, 0xdd, 0xdc, 0x99, 0x9f, 0xbb, 0xb9, 0x33, 0x3e,
+];
+
+/// `rs-gb asm <source> <rom> [title]`
+pub fn main(args: &[String]) -> i32 {
+    let (src, out) = match args {
+        [src, out, ..] => (src, out),
+        _ => {
+            println!("usage: rs-gb asm <source> <rom> [title]");
+            return 2;
+        }
+    };
+    let title = args.get(2).map_or("RS-GB", |s| s.as_str());
+    let result = fs::read_to_string(src)
+        .map_err(|e| e.to_string())
+        .and_then(|src| assemble(&src))
+        .and_then(|sections| build_rom(&sections, title))
+        .and_then(|rom| fs::write(out, rom).map_err(|e| e.to_string()));
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("error {}", e);
+            1
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Section {
+    pub name: String,
+    pub bank: usize,
+    pub addr: u16,
+    pub bytes: Vec<u8>,
+}
+
+enum Item<'a> {
+    Section(&'a str, usize, u16),
+    Label(&'a str),
+    Inst(&'a str, Vec<&'a str>),
+    Db(Vec<&'a str>),
+    Dw(Vec<&'a str>),
+    Ds(Vec<&'a str>),
+}
+
+pub fn assemble(src: &str) -> Result<Vec<Section>, String> {
+    let mut items = Vec::new();
+    for (n, line) in src.lines().enumerate() {
+        parse_line(line, n + 1, &mut items).map_err(|e| format!("line {}: {}", n + 1, e))?;
+    }
+
+    // pass 1: addresses of the labels
+    let mut labels = HashMap::new();
+    emit(&items, &mut labels, false)?;
+    // pass 2: code
+    emit(&items, &mut labels, true)
+}
+
+fn emit<'a>(
+    items: &[(usize, Item<'a>)],
+    labels: &mut HashMap<&'a str, i32>,
+    final_pass: bool,
+) -> Result<Vec<Section>, String> {
+    let mut sections = vec![Section {
+        name: String::new(),
+        bank: 0,
+        addr: 0,
+        bytes: Vec::new(),
+    }];
+    for (n, item) in items {
+        match item {
+            Item::Section(name, bank, addr) => {
+                sections.push(Section {
+                    name: name.to_string(),
+                    bank: *bank,
+                    addr: *addr,
+                    bytes: Vec::new(),
+                });
+                continue;
+            }
+            Item::Label(name) => {
+                let section = sections.last().unwrap();
+                let pc = section.addr as i32 + section.bytes.len() as i32;
+                if !final_pass && labels.insert(name, pc).is_some() {
+                    return Err(format!("line {}: label defined twice: {}", n, name));
+                }
+                continue;
+            }
+            _ => (),
+        }
+        let section = sections.last_mut().unwrap();
+        let pc = section.addr as i32 + section.bytes.len() as i32;
+        let labels = &*labels;
+        // labels defined later are not known in the first pass
+        let eval = |expr: &str| match eval(expr, labels, pc) {
+            Err(_) if !final_pass => Ok(0),
+            v => v,
+        };
+        let result = match item {
+            Item::Inst(mnemonic, operands) => build_inst(mnemonic, operands, pc, final_pass, &eval)
+                .and_then(|inst| {
+                    let bytes = encode(&inst).ok_or(format!("invalid instruction: {}", inst))?;
+                    section.bytes.extend(bytes);
+                    Ok(())
+                }),
+            Item::Db(values) => values.iter().try_for_each(|v| {
+                if let Some(s) = v.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+                    section.bytes.extend(s.bytes());
+                    Ok(())
+                } else {
+                    let v = check_range(eval(v)?, -0x80, 0xff)?;
+                    section.bytes.push(v as u8);
+                    Ok(())
+                }
+            }),
+            Item::Dw(values) => values.iter().try_for_each(|v| {
+                let v = check_range(eval(v)?, -0x8000, 0xffff)?;
+                section.bytes.extend((v as u16).to_le_bytes());
+                Ok(())
+            }),
+            Item::Ds(values) => match values.as_slice() {
+                [len] | [len, _] => {
+                    let len = check_range(eval(len)?, 0, 0x8000)?;
+                    let fill = values.get(1).map_or(Ok(0), |v| eval(v))?;
+                    let fill = check_range(fill, -0x80, 0xff)?;
+                    section.bytes.extend(vec![fill as u8; len as usize]);
+                    Ok(())
+                }
+                _ => Err("ds: invalid operands".to_string()),
+            },
+            Item::Section(..) | Item::Label(_) => Ok(()),
+        };
+        result.map_err(|e| format!("line {}: {}", n, e))?;
+    }
+    sections.retain(|s| !s.name.is_empty() || !s.bytes.is_empty());
+    Ok(sections)
+}
+
+fn parse_line<'a>(
+    line: &'a str,
+    line_no: usize,
+    items: &mut Vec<(usize, Item<'a>)>,
+) -> Result<(), String> {
+    let line = strip_comment(line).trim();
+    let mut rest = line;
+    // label
+    if let Some(i) = rest.find(':') {
+        let name = &rest[..i];
+        if is_identifier(name) {
+            items.push((line_no, Item::Label(name)));
+            rest = rest[i..].trim_start_matches(':').trim();
+        }
+    }
+    if rest.is_empty() {
+        return Ok(());
+    }
+    let (mnemonic, operands) = match rest.find(char::is_whitespace) {
+        Some(i) => (&rest[..i], rest[i..].trim()),
+        None => (rest, ""),
+    };
+    let operands = split_operands(operands);
+    let item = match mnemonic.to_lowercase().as_str() {
+        "section" => parse_section(&operands)?,
+        "db" => Item::Db(operands),
+        "dw" => Item::Dw(operands),
+        "ds" => Item::Ds(operands),
+        _ => Item::Inst(mnemonic, operands),
+    };
+    items.push((line_no, item));
+    Ok(())
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            ';' if !in_string => return &line[..i],
+            _ => (),
+        }
+    }
+    line
+}
+
+fn split_operands(s: &str) -> Vec<&str> {
+    let mut operands = Vec::new();
+    let mut in_string = false;
+    let mut depth = 0;
+    let mut begin = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '[' if !in_string => depth += 1,
+            ']' if !in_string => depth -= 1,
+            ',' if !in_string && depth == 0 => {
+                operands.push(s[begin..i].trim());
+                begin = i + 1;
+            }
+            _ => (),
+        }
+    }
+    if !s[begin..].trim().is_empty() {
+        operands.push(s[begin..].trim());
+    }
+    operands
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '.')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+fn parse_section<'a>(operands: &[&'a str]) -> Result<Item<'a>, String> {
+    let name = operands
+        .first()
+        .and_then(|s| s.strip_prefix('"'))
+        .and_then(|s| s.strip_suffix('"'))
+        .ok_or("section: name is missing")?;
+    let bracket = |s: &str, key: &str| -> Result<Option<u32>, String> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case(key) {
+            return Ok(None);
+        }
+        let inner = s
+            .get(key.len()..)
+            .filter(|_| s[..key.len()].eq_ignore_ascii_case(key))
+            .and_then(|s| s.trim().strip_prefix('['))
+            .and_then(|s| s.strip_suffix(']'))
+            .ok_or(format!("section: invalid {}", key))?;
+        parse_number(inner.trim())
+            .map(Some)
+            .ok_or(format!("section: invalid address {}", inner))
+    };
+    let kind = operands.get(1).ok_or("section: type is missing")?;
+    let (bank, addr) = if kind.to_uppercase().starts_with("ROM0") {
+        (0, bracket(kind, "ROM0")?.unwrap_or(0))
+    } else if kind.to_uppercase().starts_with("ROMX") {
+        let addr = bracket(kind, "ROMX")?.unwrap_or(BANK_SIZE as u32);
+        let bank = match operands.get(2) {
+            Some(b) => bracket(b, "BANK")?.ok_or("section: invalid BANK")?,
+            None => 1,
+        };
+        (bank, addr)
+    } else {
+        return Err(format!("section: unsupported type {}", kind));
+    };
+    let (min, max) = if bank == 0 {
+        (0, 0x3fff)
+    } else {
+        (0x4000, 0x7fff)
+    };
+    if addr < min || addr > max || (kind.to_uppercase().starts_with("ROMX") && bank == 0) {
+        return Err(format!("section: invalid address ${:x}", addr));
+    }
+    Ok(Item::Section(name, bank as usize, addr as u16))
+}
+
+fn parse_number(s: &str) -> Option<u32> {
+    if let Some(hex) = s.strip_prefix('$').or_else(|| s.strip_prefix("0x")) {
+        u32::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = s.strip_prefix('%') {
+        u32::from_str_radix(bin, 2).ok()
+    } else {
+        s.parse().ok()
+    }
+}
+
+/// Evaluates `term (+|- term)*`, where a term is a number, a label or `@`.
+fn eval(expr: &str, labels: &HashMap<&str, i32>, pc: i32) -> Result<i32, String> {
+    let expr = expr.trim();
+    let mut value = 0;
+    let mut sign = 1;
+    let mut term = String::new();
+    let mut terms = Vec::new();
+    for c in expr.chars().chain(std::iter::once('+')) {
+        match c {
+            '+' | '-' => {
+                let t = term.trim().to_string();
+                // a sign without a term: `-1`, `@+-1`
+                if !t.is_empty() {
+                    terms.push((sign, t));
+                    sign = 1;
+                }
+                if c == '-' {
+                    sign = -sign;
+                }
+                term.clear();
+            }
+            _ => term.push(c),
+        }
+    }
+    if terms.is_empty() {
+        return Err(format!("invalid expression: {}", expr));
+    }
+    for (sign, t) in terms {
+        let v = if t == "@" {
+            pc
+        } else if let Some(n) = parse_number(&t) {
+            n as i32
+        } else if let Some(v) = labels.get(t.as_str()) {
+            *v
+        } else {
+            return Err(format!("unknown label: {}", t));
+        };
+        value += sign * v;
+    }
+    Ok(value)
+}
+
+fn check_range(v: i32, min: i32, max: i32) -> Result<i32, String> {
+    if v < min || v > max {
+        Err(format!("value out of range: {}", v))
+    } else {
+        Ok(v)
+    }
+}
+
+enum Operand<'a> {
+    R8(Reg8),
+    R16(Reg16),
+    Cond(JpFlag),
+    IndR16(Reg16),
+    IndIncHL,
+    IndDecHL,
+    IndC,
+    Ind(&'a str),
+    Imm(&'a str),
+    SpOffset(&'a str),
+}
+
+fn parse_operand(s: &str, cond: bool) -> Operand<'_> {
+    let lower = s.to_lowercase().replace(' ', "");
+    if cond {
+        match lower.as_str() {
+            "nz" => return Operand::Cond(JpFlag::Nz),
+            "z" => return Operand::Cond(JpFlag::Z),
+            "nc" => return Operand::Cond(JpFlag::Nc),
+            "c" => return Operand::Cond(JpFlag::C),
+            _ => (),
+        }
+    }
+    match lower.as_str() {
+        "a" => Operand::R8(Reg8::A),
+        "b" => Operand::R8(Reg8::B),
+        "c" => Operand::R8(Reg8::C),
+        "d" => Operand::R8(Reg8::D),
+        "e" => Operand::R8(Reg8::E),
+        "h" => Operand::R8(Reg8::H),
+        "l" => Operand::R8(Reg8::L),
+        "af" => Operand::R16(Reg16::AF),
+        "bc" => Operand::R16(Reg16::BC),
+        "de" => Operand::R16(Reg16::DE),
+        "hl" => Operand::R16(Reg16::HL),
+        "sp" => Operand::R16(Reg16::SP),
+        "[bc]" => Operand::IndR16(Reg16::BC),
+        "[de]" => Operand::IndR16(Reg16::DE),
+        "[hl]" => Operand::IndR16(Reg16::HL),
+        "[hl+]" | "[hli]" => Operand::IndIncHL,
+        "[hl-]" | "[hld]" => Operand::IndDecHL,
+        "[c]" | "[$ff00+c]" | "[0xff00+c]" => Operand::IndC,
+        _ if lower.starts_with('[') && lower.ends_with(']') => {
+            Operand::Ind(s.trim()[1..s.trim().len() - 1].trim())
+        }
+        _ if lower.starts_with("sp+") || lower.starts_with("sp-") => {
+            let s = s.trim();
+            Operand::SpOffset(s[2..].trim())
+        }
+        _ => Operand::Imm(s.trim()),
+    }
+}
+
+fn build_inst(
+    mnemonic: &str,
+    operands: &[&str],
+    pc: i32,
+    final_pass: bool,
+    eval: &dyn Fn(&str) -> Result<i32, String>,
+) -> Result<Inst, String> {
+    let mnemonic = mnemonic.to_lowercase();
+    let cond = matches!(mnemonic.as_str(), "jp" | "jr" | "call" | "ret");
+    let ops: Vec<Operand> = operands
+        .iter()
+        .enumerate()
+        .map(|(i, s)| parse_operand(s, cond && i == 0))
+        .collect();
+    let u8_ = |e: &str| {
+        eval(e)
+            .and_then(|v| check_range(v, -0x80, 0xff))
+            .map(|v| v as u8)
+    };
+    let u16_ = |e: &str| {
+        eval(e)
+            .and_then(|v| check_range(v, -0x8000, 0xffff))
+            .map(|v| v as u16)
+    };
+    let i8_ = |e: &str| {
+        eval(e)
+            .and_then(|v| check_range(v, -0x80, 0x7f))
+            .map(|v| v as i8)
+    };
+    let arg8 = |op: &Operand| -> Result<Arg8, String> {
+        Ok(match op {
+            Operand::R8(r) => Arg8::Reg(r.clone()),
+            Operand::IndR16(rr) => Arg8::IndReg(rr.clone()),
+            Operand::IndIncHL => Arg8::IndIncHL,
+            Operand::IndDecHL => Arg8::IndDecHL,
+            Operand::IndC => Arg8::IndIoC,
+            Operand::Ind(e) => Arg8::Ind(u16_(e)?),
+            Operand::Imm(e) => Arg8::Immed(u8_(e)?),
+            _ => return Err("invalid operand".to_string()),
+        })
+    };
+    let jr_offset = |e: &str| -> Result<i8, String> {
+        let target = eval(e)?;
+        if final_pass {
+            check_range(target - (pc + 2), -0x80, 0x7f).map(|v| v as i8)
+        } else {
+            Ok(0)
+        }
+    };
+    let a = Arg8::Reg(Reg8::A);
+
+    let inst = match (mnemonic.as_str(), ops.as_slice()) {
+        ("nop", []) => Inst::Nop,
+        ("halt", []) => Inst::Halt,
+        ("stop", []) => Inst::Stop,
+        ("di", []) => Inst::Di,
+        ("ei", []) => Inst::Ei,
+        ("daa", []) => Inst::Daa,
+        ("cpl", []) => Inst::Cpl,
+        ("ccf", []) => Inst::Ccf,
+        ("scf", []) => Inst::Scf,
+        ("rlca", []) => Inst::Rlca,
+        ("rla", []) => Inst::Rla,
+        ("rrca", []) => Inst::Rrca,
+        ("rra", []) => Inst::Rra,
+        ("reti", []) => Inst::Reti,
+        ("ret", []) => Inst::Ret,
+        ("ret", [Operand::Cond(cc)]) => Inst::Retf(cc.clone()),
+        ("ld", [Operand::R16(rr), Operand::Imm(e)]) => {
+            Inst::Ld16(Arg16::Reg(rr.clone()), Arg16::Immed(u16_(e)?))
+        }
+        ("ld", [Operand::Ind(e), Operand::R16(Reg16::SP)]) => {
+            Inst::Ld16(Arg16::Ind(u16_(e)?), Arg16::Reg(Reg16::SP))
+        }
+        ("ld", [Operand::R16(Reg16::SP), Operand::R16(Reg16::HL)]) => {
+            Inst::Ld16(Arg16::Reg(Reg16::SP), Arg16::Reg(Reg16::HL))
+        }
+        ("ld", [Operand::R16(Reg16::HL), Operand::SpOffset(e)]) => Inst::Ld16HLSP(i8_(e)?),
+        ("ld", [d, s]) => Inst::Ld8(arg8(d)?, arg8(s)?),
+        ("ldh", [d, s]) => {
+            let io = |op: &Operand| -> Result<Arg8, String> {
+                match op {
+                    Operand::Ind(e) => {
+                        let v = u16_(e)?;
+                        match v {
+                            0xff00..=0xffff | 0x0000..=0x00ff => Ok(Arg8::IndIo(v as u8)),
+                            _ => Err(format!("ldh: invalid address ${:04x}", v)),
+                        }
+                    }
+                    op => arg8(op),
+                }
+            };
+            Inst::Ld8(io(d)?, io(s)?)
+        }
+        ("push", [Operand::R16(rr)]) => Inst::Push16(rr.clone()),
+        ("pop", [Operand::R16(rr)]) => Inst::Pop16(rr.clone()),
+        ("add", [Operand::R16(Reg16::HL), Operand::R16(rr)]) => {
+            Inst::Add16(Arg16::Reg(Reg16::HL), Arg16::Reg(rr.clone()))
+        }
+        ("add", [Operand::R16(Reg16::SP), Operand::Imm(e)]) => Inst::Add16SP(i8_(e)?),
+        ("add" | "adc" | "sub" | "sbc" | "and" | "xor" | "or" | "cp", [x])
+        | (
+            "add" | "adc" | "sub" | "sbc" | "and" | "xor" | "or" | "cp",
+            [Operand::R8(Reg8::A), x],
+        ) => {
+            let x = arg8(x)?;
+            match mnemonic.as_str() {
+                "add" => Inst::Add(a, x),
+                "adc" => Inst::Adc(a, x),
+                "sub" => Inst::Sub(a, x),
+                "sbc" => Inst::Sbc(a, x),
+                "and" => Inst::And(a, x),
+                "xor" => Inst::Xor(a, x),
+                "or" => Inst::Or(a, x),
+                _ => Inst::Cp(a, x),
+            }
+        }
+        ("inc", [Operand::R16(rr)]) => Inst::Inc16(Arg16::Reg(rr.clone())),
+        ("dec", [Operand::R16(rr)]) => Inst::Dec16(Arg16::Reg(rr.clone())),
+        ("inc", [x]) => Inst::Inc(arg8(x)?),
+        ("dec", [x]) => Inst::Dec(arg8(x)?),
+        ("rlc" | "rl" | "rrc" | "rr" | "sla" | "swap" | "sra" | "srl", [x]) => {
+            let x = arg8(x)?;
+            match mnemonic.as_str() {
+                "rlc" => Inst::Rlc(x),
+                "rl" => Inst::Rl(x),
+                "rrc" => Inst::Rrc(x),
+                "rr" => Inst::Rr(x),
+                "sla" => Inst::Sla(x),
+                "swap" => Inst::Swap(x),
+                "sra" => Inst::Sra(x),
+                _ => Inst::Srl(x),
+            }
+        }
+        ("bit" | "set" | "res", [Operand::Imm(n), x]) => {
+            let n = eval(n).and_then(|v| check_range(v, 0, 7))? as u8;
+            let x = arg8(x)?;
+            match mnemonic.as_str() {
+                "bit" => Inst::Bit(n, x),
+                "set" => Inst::Set(n, x),
+                _ => Inst::Res(n, x),
+            }
+        }
+        ("jp", [Operand::R16(Reg16::HL)]) => Inst::JpHL,
+        ("jp", [Operand::Imm(e)]) => Inst::Jp(u16_(e)?),
+        ("jp", [Operand::Cond(cc), Operand::Imm(e)]) => Inst::Jpf(cc.clone(), u16_(e)?),
+        ("jr", [Operand::Imm(e)]) => Inst::Jr(jr_offset(e)?),
+        ("jr", [Operand::Cond(cc), Operand::Imm(e)]) => Inst::Jrf(cc.clone(), jr_offset(e)?),
+        ("call", [Operand::Imm(e)]) => Inst::Call(u16_(e)?),
+        ("call", [Operand::Cond(cc), Operand::Imm(e)]) => Inst::Callf(cc.clone(), u16_(e)?),
+        ("rst", [Operand::Imm(e)]) => Inst::Rst(u8_(e)?),
+        _ => return Err(format!("invalid instruction: {}", operands.join(", "))),
+    };
+    Ok(inst)
+}
+
+struct Bytes([u8; 3]);
+
+impl MemoryIF for Bytes {
+    fn read_byte(&self, addr: u16) -> u8 {
+        self.0.get(addr as usize).copied().unwrap_or(0)
+    }
+    fn write_byte(&mut self, _addr: u16, _val: u8) {}
+}
+
+/// Encodes `inst` by searching the opcode that decodes to it.
+pub fn encode(inst: &Inst) -> Option<Vec<u8>> {
+    if *inst == Inst::Stop {
+        // `stop` is followed by a byte that is skipped (RGBDS emits 10 00)
+        return Some(vec![0x10, 0x00]);
+    }
+    let [lo, hi] = immediate(inst).to_le_bytes();
+    let candidates = (0..=0xffu8)
+        .filter(|&op| op != 0xcb)
+        .map(|op| [op, lo, hi])
+        .chain((0..=0xffu8).map(|op| [0xcb, op, 0]));
+    for bytes in candidates {
+        if let Ok((decoded, len)) = decode::decode(0, &Bytes(bytes)) {
+            if decoded == *inst {
+                return Some(bytes[..len as usize].to_vec());
+            }
+        }
+    }
+    None
+}
+
+fn immediate(inst: &Inst) -> u16 {
+    let arg8 = |x: &Arg8| match x {
+        Arg8::Immed(n) | Arg8::IndIo(n) => Some(*n as u16),
+        Arg8::Ind(nn) => Some(*nn),
+        _ => None,
+    };
+    let arg16 = |x: &Arg16| match x {
+        Arg16::Immed(nn) | Arg16::Ind(nn) => Some(*nn),
+        Arg16::Reg(_) => None,
+    };
+    let v = match inst {
+        Inst::Ld8(d, s)
+        | Inst::Add(d, s)
+        | Inst::Adc(d, s)
+        | Inst::Sub(d, s)
+        | Inst::Sbc(d, s)
+        | Inst::And(d, s)
+        | Inst::Xor(d, s)
+        | Inst::Or(d, s)
+        | Inst::Cp(d, s) => arg8(d).or(arg8(s)),
+        Inst::Ld16(d, s) => arg16(d).or(arg16(s)),
+        Inst::Add16SP(dd) | Inst::Ld16HLSP(dd) | Inst::Jr(dd) | Inst::Jrf(_, dd) => {
+            Some(*dd as u8 as u16)
+        }
+        Inst::Jp(nn) | Inst::Jpf(_, nn) | Inst::Call(nn) | Inst::Callf(_, nn) => Some(*nn),
+        _ => None,
+    };
+    v.unwrap_or(0)
+}
+
+/// Builds a ROM image with a valid header: the Nintendo logo, the title,
+/// the cartridge type (ROM only, or MBC1 for more than two banks) and both
+/// checksums. If no section covers 0x0100, the entry point jumps to 0x0150.
+pub fn build_rom(sections: &[Section], title: &str) -> Result<Vec<u8>, String> {
+    let banks = sections.iter().map(|s| s.bank + 1).max().unwrap_or(1);
+    let banks = banks.max(2).next_power_of_two();
+    let mut rom = vec![0xff; banks * BANK_SIZE];
+    let mut entry_point = false;
+    for s in sections {
+        let begin = s.bank * BANK_SIZE + (s.addr as usize % BANK_SIZE);
+        let end = begin + s.bytes.len();
+        if end > (s.bank + 1) * BANK_SIZE {
+            return Err(format!("section {} exceeds its bank", s.name));
+        }
+        if s.bank == 0 && begin < 0x150 && end > 0x104 {
+            return Err(format!("section {} overlaps the cartridge header", s.name));
+        }
+        if s.bank == 0 && begin <= 0x100 && end > 0x100 {
+            entry_point = true;
+        }
+        rom[begin..end].copy_from_slice(&s.bytes);
+    }
+    if !entry_point {
+        // nop; jp $0150
+        rom[0x100..0x104].copy_from_slice(&[0x00, 0xc3, 0x50, 0x01]);
+    }
+    rom[0x104..0x134].copy_from_slice(&NINTENDO_LOGO);
+    let title = title.as_bytes();
+    for i in 0..16 {
+        rom[0x134 + i] = title.get(i).copied().unwrap_or(0);
+    }
+    rom[0x147] = if banks > 2 { 0x01 } else { 0x00 }; // cartridge type
+    rom[0x148] = banks.trailing_zeros() as u8 - 1; // ROM size
+    rom[0x149] = 0x00; // RAM size
+    rom[0x14a] = 0x01; // destination code
+    rom[0x14b] = 0x00; // old licensee code
+    rom[0x14c] = 0x00; // version
+    rom[0x14d] = rom[0x134..0x14d]
+        .iter()
+        .fold(0u8, |x, b| x.wrapping_sub(*b).wrapping_sub(1));
+    let global = rom
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != 0x14e && *i != 0x14f)
+        .fold(0u16, |x, (_, b)| x.wrapping_add(*b as u16));
+    rom[0x14e..0x150].copy_from_slice(&global.to_be_bytes());
+    Ok(rom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rom::{run_image, TestResult};
+
+    #[test]
+    fn assemble_bytes() {
+        let src = "
+            ld a, $12       ; comment
+            ld [hl+], a
+            ldh [$ff40], a
+            ld [c], a
+            bit 7, [hl]
+            add sp, -2
+            ld hl, sp+3
+            stop
+            db $01, \"ab\"
+            dw $1234
+            ds 2, $ff
+        ";
+        let sections = assemble(src).unwrap();
+        assert_eq!(1, sections.len());
+        assert_eq!(
+            vec![
+                0x3e, 0x12, 0x22, 0xe0, 0x40, 0xe2, 0xcb, 0x7e, 0xe8, 0xfe, 0xf8, 0x03, 0x10, 0x00,
+                0x01, 0x61, 0x62, 0x34, 0x12, 0xff, 0xff
+            ],
+            sections[0].bytes
+        );
+    }
+    #[test]
+    fn assemble_labels() {
+        let src = "
+            SECTION \"main\", ROM0[$0150]
+        main:
+            jr nz, .skip
+            call func
+        .skip:
+            jr @
+            SECTION \"func\", ROMX[$4000], BANK[2]
+        func::
+            jp main
+        ";
+        let sections = assemble(src).unwrap();
+        assert_eq!(
+            vec![
+                Section {
+                    name: "main".to_string(),
+                    bank: 0,
+                    addr: 0x150,
+                    bytes: vec![0x20, 0x03, 0xcd, 0x00, 0x40, 0x18, 0xfe],
+                },
+                Section {
+                    name: "func".to_string(),
+                    bank: 2,
+                    addr: 0x4000,
+                    bytes: vec![0xc3, 0x50, 0x01],
+                },
+            ],
+            sections
+        );
+        assert!(assemble("jr far\nds 200\nfar:").is_err());
+        assert!(assemble("ld a, unknown").is_err());
+        assert!(assemble("ld [hl], [hl]").is_err());
+    }
+    #[test]
+    fn round_trip() {
+        // every opcode: decode -> Display -> assemble gives the same bytes
+        let candidates = (0..=0xffu8)
+            .filter(|&op| op != 0xcb)
+            .map(|op| [op, 0x34, 0x12])
+            .chain((0..=0xffu8).map(|op| [0xcb, op, 0]));
+        for bytes in candidates {
+            let (inst, len) = match decode::decode(0, &Bytes(bytes)) {
+                Ok(r) => r,
+                Err(_) => continue,
+            };
+            let len = if inst == Inst::Stop { 2 } else { len as usize };
+            let expected = if inst == Inst::Stop {
+                vec![0x10, 0x00]
+            } else {
+                bytes[..len].to_vec()
+            };
+            let src = inst.to_string();
+            let sections = assemble(&src).unwrap_or_else(|e| panic!("{}: {}", src, e));
+            assert_eq!(expected, sections[0].bytes, "{}", src);
+        }
+    }
+    #[test]
+    fn rom_header() {
+        let rom = build_rom(
+            &assemble("SECTION \"a\", ROM0[$150]\nhalt").unwrap(),
+            "TEST",
+        )
+        .unwrap();
+        assert_eq!(0x8000, rom.len());
+        assert_eq!([0x00, 0xc3, 0x50, 0x01], rom[0x100..0x104]);
+        assert_eq!(NINTENDO_LOGO, rom[0x104..0x134]);
+        assert_eq!(b"TEST", &rom[0x134..0x138]);
+        let checksum = rom[0x134..0x14d]
+            .iter()
+            .fold(0u8, |x, b| x.wrapping_sub(*b).wrapping_sub(1));
+        assert_eq!(checksum, rom[0x14d]);
+        assert_eq!(0x76, rom[0x150]);
+
+        let rom = build_rom(&assemble("SECTION \"a\", ROMX, BANK[5]").unwrap(), "").unwrap();
+        assert_eq!(0x20000, rom.len());
+        assert_eq!(0x01, rom[0x147]);
+        assert_eq!(0x02, rom[0x148]);
+        assert!(build_rom(&assemble("SECTION \"a\", ROM0[$140]\nnop").unwrap(), "").is_err());
+    }
+    #[test]
+    fn run_mooneye_style_rom() {
+        let src = "
+            SECTION \"main\", ROM0[$0150]
+            ld sp, $fffe
+            call fib_regs
+            ld b, b
+        halt_loop:
+            jr halt_loop
+        fib_regs:
+            ld b, 3
+            ld c, 5
+            ld d, 8
+            ld e, 13
+            ld h, 21
+            ld l, 34
+            ret
+        ";
+        let rom = build_rom(&assemble(src).unwrap(), "FIB").unwrap();
+        assert!(matches!(run_image(&rom, 1), TestResult::Pass));
+    }
+}
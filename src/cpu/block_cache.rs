@@ -0,0 +1,351 @@
+//! Cache of pre-decoded basic blocks, an optional execution mode for long
+//! headless runs.
+//!
+//! Blocks are keyed by (ROM bank, address), so switching the ROM bank selects
+//! other blocks. Instructions are still executed one at a time through
+//! `Registers::execute_decoded`, so the timing is the same as
+//! `Registers::step`; only the opcode and operand fetches are skipped.
+//!
+//! Blocks in RAM are dropped when the CPU writes to a page (256 bytes) that
+//! holds them. Blocks in external RAM are dropped on writes to the MBC
+//! registers (the RAM bank may change), and blocks in 0xd000-0xdfff on writes
+//! to SVBK (CGB WRAM bank). Code is cached only where the CPU is the only
+//! writer: ROM, external RAM, WRAM and HRAM. Outside HRAM, the cache is
+//! bypassed during OAM DMA, when the CPU reads the bytes being transferred.
+
+use super::execute::{fetch, Decoded};
+use super::table::Exec;
+use super::trace::bank_of;
+use super::{Flags, Registers, M};
+use crate::memory::{MemoryIF, OamAccess, SVBK};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+const MAX_BLOCK_LEN: usize = 64;
+
+type Key = (usize, u16);
+
+struct Block {
+    // (address, instruction)
+    entries: Vec<(u16, Decoded)>,
+}
+
+#[derive(Default)]
+pub struct BlockCache {
+    blocks: HashMap<Key, Rc<Block>>,
+    // RAM page (address >> 8) -> blocks in it
+    pages: HashMap<u16, Vec<Key>>,
+    // block being executed and the index of the next instruction
+    cursor: Option<(Rc<Block>, usize)>,
+}
+
+/// Cacheable memory region containing `addr` (inclusive)
+fn region(addr: u16) -> Option<(u16, u16)> {
+    match addr {
+        0x0000..=0x3fff => Some((0x0000, 0x3fff)),
+        0x4000..=0x7fff => Some((0x4000, 0x7fff)),
+        0xa000..=0xbfff => Some((0xa000, 0xbfff)),
+        0xc000..=0xdfff => Some((0xc000, 0xdfff)),
+        0xff80..=0xfffe => Some((0xff80, 0xfffe)),
+        _ => None,
+    }
+}
+
+fn ends_block(exec: Exec) -> bool {
+    matches!(
+        exec,
+        Exec::Jp
+            | Exec::JpCc
+            | Exec::JpHl
+            | Exec::Jr
+            | Exec::JrCc
+            | Exec::Call
+            | Exec::CallCc
+            | Exec::Ret
+            | Exec::RetCc
+            | Exec::Reti
+            | Exec::Rst
+            | Exec::Halt
+            | Exec::Stop
+    )
+}
+
+impl BlockCache {
+    pub fn new() -> BlockCache {
+        BlockCache::default()
+    }
+
+    pub fn step<T: MemoryIF>(
+        &mut self,
+        reg: &mut Registers,
+        memory: &mut T,
+        ime: &mut bool,
+    ) -> Result<(M, Flags), String> {
+        let decoded = match self.next(reg.pc, memory) {
+            Some(decoded) => decoded,
+            None => return reg.step(memory, ime),
+        };
+        let mut watch = Watch {
+            memory,
+            cache: self,
+        };
+        reg.execute_decoded(&decoded, &mut watch, ime)
+    }
+
+    fn next(&mut self, pc: u16, memory: &impl MemoryIF) -> Option<Decoded> {
+        if memory.bus_conflict() && pc < 0xff00 {
+            self.cursor = None;
+            return None;
+        }
+        if let Some((block, i)) = &mut self.cursor {
+            if let Some((addr, decoded)) = block.entries.get(*i) {
+                if *addr == pc {
+                    *i += 1;
+                    return Some(*decoded);
+                }
+            }
+        }
+        self.cursor = None;
+        region(pc)?;
+        let key = (bank_of(pc, memory).unwrap_or(0), pc);
+        let block = match self.blocks.get(&key) {
+            Some(block) => block.clone(),
+            None => {
+                let block = Rc::new(build(pc, memory)?);
+                if pc >= 0x8000 {
+                    let (first, _) = block.entries[0];
+                    let (last, decoded) = block.entries[block.entries.len() - 1];
+                    let end = last + decoded.info().len - 1;
+                    for page in (first >> 8)..=(end >> 8) {
+                        self.pages.entry(page).or_default().push(key);
+                    }
+                }
+                self.blocks.insert(key, block.clone());
+                block
+            }
+        };
+        let (_, decoded) = block.entries[0];
+        self.cursor = Some((block, 1));
+        Some(decoded)
+    }
+
+    /// Drops the blocks that a write to `addr` may change.
+    pub fn written(&mut self, addr: u16) {
+        if addr < 0x8000 {
+            // MBC registers
+            self.cursor = None;
+            for page in 0xa0..=0xbf {
+                self.invalidate_page(page);
+            }
+        } else if addr == SVBK {
+            for page in 0xd0..=0xdf {
+                self.invalidate_page(page);
+            }
+        } else {
+            self.invalidate_page(addr >> 8);
+        }
+    }
+
+    fn invalidate_page(&mut self, page: u16) {
+        if let Some(keys) = self.pages.remove(&page) {
+            for key in keys {
+                self.blocks.remove(&key);
+            }
+            self.cursor = None;
+        }
+    }
+}
+
+fn build(pc: u16, memory: &impl MemoryIF) -> Option<Block> {
+    let (_, end) = region(pc)?;
+    let mut entries = Vec::new();
+    let mut addr = pc;
+    while entries.len() < MAX_BLOCK_LEN {
+        let decoded = fetch(addr, memory);
+        let info = decoded.info();
+        if info.inst.is_none() || addr as u32 + info.len as u32 - 1 > end as u32 {
+            break;
+        }
+        entries.push((addr, decoded));
+        if ends_block(info.exec) {
+            break;
+        }
+        addr += info.len;
+    }
+    if entries.is_empty() {
+        None
+    } else {
+        Some(Block { entries })
+    }
+}
+
+/// Drops the blocks on the pages written by an instruction.
+struct Watch<'a, T> {
+    memory: &'a mut T,
+    cache: &'a mut BlockCache,
+}
+
+impl<T: MemoryIF> MemoryIF for Watch<'_, T> {
+    fn read_byte(&self, addr: u16) -> u8 {
+        self.memory.read_byte(addr)
+    }
+    fn read_word(&self, addr: u16) -> u16 {
+        self.memory.read_word(addr)
+    }
+    fn write_byte(&mut self, addr: u16, val: u8) {
+        self.cache.written(addr);
+        self.memory.write_byte(addr, val);
+    }
+    fn write_word(&mut self, addr: u16, val: u16) {
+        self.cache.written(addr);
+        self.cache.written(addr.wrapping_add(1));
+        self.memory.write_word(addr, val);
+    }
+    fn rom_bank(&self) -> usize {
+        self.memory.rom_bank()
+    }
+    fn bus_conflict(&self) -> bool {
+        self.memory.bus_conflict()
+    }
+    fn oam_bug(&mut self, addr: u16, access: OamAccess, cycle: usize) {
+        self.memory.oam_bug(addr, access, cycle);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::asm::{assemble, build_rom};
+    use crate::cpu::Cpu;
+    use crate::io::Screen;
+    use crate::mmu::Mmu;
+
+    #[test]
+    fn invalidate_on_write() {
+        let mut cache = BlockCache::new();
+        let mut mmu = Mmu::new();
+        // $c000: inc a; inc a; ret
+        for (i, b) in [0x3c, 0x3c, 0xc9].iter().enumerate() {
+            mmu.write_byte(0xc000 + i as u16, *b);
+        }
+        assert_eq!(Some(0x3c), cache.next(0xc000, &mmu).map(|d| d.op));
+        assert_eq!(Some(0x3c), cache.next(0xc001, &mmu).map(|d| d.op));
+        assert_eq!(1, cache.blocks.len());
+        cache.written(0xc0ff);
+        assert!(cache.blocks.is_empty() && cache.cursor.is_none());
+        // other pages are kept
+        cache.next(0xc000, &mmu);
+        cache.written(0xc100);
+        assert_eq!(1, cache.blocks.len());
+        // any number of writes through a `Watch`
+        cache.next(0xc100, &mmu);
+        cache.next(0xc200, &mmu);
+        assert_eq!(3, cache.blocks.len());
+        let mut watch = Watch {
+            memory: &mut mmu,
+            cache: &mut cache,
+        };
+        watch.write_byte(0xc000, 0);
+        watch.write_word(0xc1ff, 0);
+        assert!(cache.blocks.is_empty());
+    }
+
+    // self-modifying code in WRAM and ROM bank switching
+    const PROGRAM: &str = "
+        SECTION \"vblank\", ROM0[$0040]
+            reti
+
+        SECTION \"main\", ROM0[$0150]
+            ld sp, $fffe
+            ; $c1f3: ld bc, $0000; ret, cached, then the interrupt pushes
+            ; its return address onto the operand
+            ld a, $01
+            ld [$c1f3], a
+            xor a
+            ld [$c1f4], a
+            ld [$c1f5], a
+            ld a, $c9
+            ld [$c1f6], a
+            call $c1f3
+            ld a, $01
+            ld [$ffff], a
+            ld sp, $c1f6
+            ei
+            halt
+            di
+            ld sp, $fffe
+            call $c1f3
+            ld hl, $c000
+            ld de, ram_code
+            ld b, ram_code_end - ram_code
+        .copy:
+            ld a, [de]
+            ld [hl+], a
+            inc de
+            dec b
+            jr nz, .copy
+            ld c, 0
+        .loop:
+            call $c000
+            xor a
+            ld [$c005], a
+            ld a, c
+            and 3
+            inc a
+            ld [$2000], a
+            call $4000
+            inc c
+            jr .loop
+        ram_code:
+            ld a, $3c
+            ld [$c005], a
+            nop
+            add a, c
+            ret
+        ram_code_end:
+
+        SECTION \"bank1\", ROMX[$4000], BANK[1]
+            ld d, 1
+            ret
+        SECTION \"bank2\", ROMX[$4000], BANK[2]
+            ld d, 2
+            add a, d
+            ret
+        SECTION \"bank3\", ROMX[$4000], BANK[3]
+            ld e, 3
+            jr @+2
+            inc e
+            ret
+        SECTION \"bank4\", ROMX[$4000], BANK[4]
+            dec e
+            ret
+    ";
+
+    #[test]
+    fn same_as_step() {
+        let rom = build_rom(&assemble(PROGRAM).unwrap(), "CACHE").unwrap();
+        let mut mmus = [Mmu::new(), Mmu::new()];
+        let mut cpus = [Cpu::new(), Cpu::new()];
+        cpus[1].enable_block_cache();
+        let mut screen = Screen::new();
+        for mmu in &mut mmus {
+            mmu.load_rom(&rom).unwrap();
+        }
+        for clock in 0..200_000 {
+            for (cpu, mmu) in cpus.iter_mut().zip(mmus.iter_mut()) {
+                let (_, stop) = cpu.run(mmu, false).unwrap();
+                mmu.run_ppu(&mut screen).unwrap();
+                mmu.run_timer(stop).unwrap();
+            }
+            assert_eq!(cpus[0].reg, cpus[1].reg, "clock {}", clock);
+            assert_eq!(cpus[0].m, cpus[1].m, "clock {}", clock);
+        }
+        for addr in 0xc000..0xc200 {
+            assert_eq!(mmus[0].read_byte(addr), mmus[1].read_byte(addr));
+        }
+        assert_ne!(0, mmus[0].read_word(0xc1f4));
+        let cache = cpus[1].block_cache.as_ref().unwrap();
+        // blocks of the 4 banks at $4000
+        assert!((1..=4).all(|bank| cache.blocks.contains_key(&(bank, 0x4000))));
+    }
+}
@@ -0,0 +1,204 @@
+//! Conformance runner for the SingleStepTests SM83 suite
+//! (https://github.com/SingleStepTests/sm83).
+//!
+//! Put the per-opcode JSON files (`00.json` ... `ff.json`, `cb 00.json` ...
+//! `cb ff.json`) in `tests/sm83/v1`, or point `SM83_TESTS_DIR` at them, then run
+//! `cargo test sm83 -- --nocapture` to see the per-opcode report.
+//! `SM83_TESTS_FILTER` restricts the run to files whose name starts with it
+//! (e.g. `SM83_TESTS_FILTER="cb 4"`).
+//! The test is skipped when the directory does not exist.
+
+use super::Registers;
+use crate::memory::MemoryIF;
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+
+const TESTS_DIR: &str = "tests/sm83/v1";
+
+struct FlatMemory {
+    memory: Vec<u8>,
+}
+impl FlatMemory {
+    fn new() -> FlatMemory {
+        FlatMemory {
+            memory: vec![0; 0x10000],
+        }
+    }
+}
+impl MemoryIF for FlatMemory {
+    fn read_byte(&self, addr: u16) -> u8 {
+        self.memory[addr as usize]
+    }
+    fn write_byte(&mut self, addr: u16, val: u8) {
+        self.memory[addr as usize] = val;
+    }
+}
+
+struct State {
+    reg: Registers,
+    ime: bool,
+    ram: Vec<(u16, u8)>,
+}
+
+fn field(v: &Value, name: &str) -> Result<u64, String> {
+    v[name]
+        .as_u64()
+        .ok_or_else(|| format!("missing field: {}", name))
+}
+
+fn parse_state(v: &Value) -> Result<State, String> {
+    let mut reg = Registers::new();
+    reg.a = field(v, "a")? as u8;
+    reg.b = field(v, "b")? as u8;
+    reg.c = field(v, "c")? as u8;
+    reg.d = field(v, "d")? as u8;
+    reg.e = field(v, "e")? as u8;
+    reg.f = field(v, "f")? as u8;
+    reg.h = field(v, "h")? as u8;
+    reg.l = field(v, "l")? as u8;
+    reg.pc = field(v, "pc")? as u16;
+    reg.sp = field(v, "sp")? as u16;
+    let ime = field(v, "ime")? != 0;
+    let ram = v["ram"]
+        .as_array()
+        .ok_or("missing field: ram")?
+        .iter()
+        .map(|e| match (e[0].as_u64(), e[1].as_u64()) {
+            (Some(addr), Some(val)) => Ok((addr as u16, val as u8)),
+            _ => Err(format!("invalid ram entry: {}", e)),
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(State { reg, ime, ram })
+}
+
+fn compare(expected: &State, reg: &Registers, ime: bool, memory: &FlatMemory) -> Vec<String> {
+    let mut diffs = Vec::new();
+    let e = &expected.reg;
+    let pairs = [
+        ("a", e.a as u16, reg.a as u16),
+        ("b", e.b as u16, reg.b as u16),
+        ("c", e.c as u16, reg.c as u16),
+        ("d", e.d as u16, reg.d as u16),
+        ("e", e.e as u16, reg.e as u16),
+        ("f", e.f as u16, reg.f as u16),
+        ("h", e.h as u16, reg.h as u16),
+        ("l", e.l as u16, reg.l as u16),
+        ("pc", e.pc, reg.pc),
+        ("sp", e.sp, reg.sp),
+    ];
+    for (name, want, got) in pairs {
+        if want != got {
+            diffs.push(format!("{}: expected {:#x}, got {:#x}", name, want, got));
+        }
+    }
+    if expected.ime != ime {
+        diffs.push(format!("ime: expected {}, got {}", expected.ime, ime));
+    }
+    for &(addr, want) in &expected.ram {
+        let got = memory.read_byte(addr);
+        if want != got {
+            diffs.push(format!(
+                "ram[{:#06x}]: expected {:#04x}, got {:#04x}",
+                addr, want, got
+            ));
+        }
+    }
+    diffs
+}
+
+fn run_case(case: &Value) -> Result<(), String> {
+    let initial = parse_state(&case["initial"])?;
+    let expected = parse_state(&case["final"])?;
+    let cycles = case["cycles"]
+        .as_array()
+        .ok_or("missing field: cycles")?
+        .len();
+
+    let mut memory = FlatMemory::new();
+    for &(addr, val) in &initial.ram {
+        memory.write_byte(addr, val);
+    }
+    let mut reg = initial.reg;
+    let mut ime = initial.ime;
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| reg.step(&mut memory, &mut ime)));
+    let (m, _) = match result {
+        Ok(r) => r?,
+        Err(_) => return Err("panicked".to_string()),
+    };
+
+    let mut diffs = compare(&expected, &reg, ime, &memory);
+    if m != cycles {
+        diffs.push(format!("m-cycles: expected {}, got {}", cycles, m));
+    }
+    if diffs.is_empty() {
+        Ok(())
+    } else {
+        Err(diffs.join(", "))
+    }
+}
+
+fn tests_dir() -> PathBuf {
+    match env::var("SM83_TESTS_DIR") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(TESTS_DIR),
+    }
+}
+
+#[test]
+fn sm83_single_step_tests() {
+    let dir = tests_dir();
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(_) => {
+            println!("sm83: {} not found, skipped", dir.display());
+            return;
+        }
+    };
+    let filter = env::var("SM83_TESTS_FILTER").unwrap_or_default();
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(&filter))
+        })
+        .collect();
+    files.sort();
+
+    let mut failed_opcodes = Vec::new();
+    for path in files {
+        let opcode = path.file_stem().unwrap().to_string_lossy().to_string();
+        let text = fs::read_to_string(&path).unwrap();
+        let cases: Vec<Value> = serde_json::from_str(&text).unwrap();
+
+        let mut passed = 0;
+        let mut first_failure = None;
+        for case in &cases {
+            match run_case(case) {
+                Ok(()) => passed += 1,
+                Err(e) => {
+                    if first_failure.is_none() {
+                        first_failure = Some(format!("{}: {}", case["name"], e));
+                    }
+                }
+            }
+        }
+        match first_failure {
+            None => println!("{:<6} ok   {}/{}", opcode, passed, cases.len()),
+            Some(e) => {
+                println!("{:<6} FAIL {}/{}  {}", opcode, passed, cases.len(), e);
+                failed_opcodes.push(opcode);
+            }
+        }
+    }
+    assert!(
+        failed_opcodes.is_empty(),
+        "failed opcodes: {}",
+        failed_opcodes.join(" ")
+    );
+}
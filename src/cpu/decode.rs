@@ -1,449 +1,29 @@
-use super::decode_prefix_cb as pf_cb;
-use super::inst::{Arg16, Arg8, Inst, JpFlag, Reg16, Reg8};
+use super::inst::Inst;
+use super::table::{self, Operands, CB_OPS, OPS};
 use crate::memory::MemoryIF;
 
 pub fn decode(pc: u16, memory: &impl MemoryIF) -> Result<(Inst, u16), String> {
-    let mut addvance = 1;
-    let inst = match memory.read_byte(pc) {
-        0x00 => Inst::Nop,
-        0x01 => {
-            let nn = memory.read_word(pc + 1);
-            addvance = 3;
-            Inst::Ld16(Arg16::Reg(Reg16::BC), Arg16::Immed(nn))
+    let code = memory.read_byte(pc);
+    let mut info = &OPS[code as usize];
+    let imm = match info.operands {
+        Operands::Implied => 0,
+        Operands::Imm8 => memory.read_byte(pc + 1) as u16,
+        Operands::Imm16 => memory.read_word(pc + 1),
+        Operands::Cb => {
+            info = &CB_OPS[memory.read_byte(pc + 1) as usize];
+            0
         }
-        0x02 => Inst::Ld8(Arg8::IndReg(Reg16::BC), Arg8::Reg(Reg8::A)),
-        0x03 => Inst::Inc16(Arg16::Reg(Reg16::BC)),
-        0x04 => Inst::Inc(Arg8::Reg(Reg8::B)),
-        0x05 => Inst::Dec(Arg8::Reg(Reg8::B)),
-        0x06 => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Ld8(Arg8::Reg(Reg8::B), Arg8::Immed(n))
-        }
-        0x07 => Inst::Rlca,
-        0x08 => {
-            let nn = memory.read_word(pc + 1);
-            addvance = 3;
-            Inst::Ld16(Arg16::Ind(nn), Arg16::Reg(Reg16::SP))
-        }
-        0x09 => Inst::Add16(Arg16::Reg(Reg16::HL), Arg16::Reg(Reg16::BC)),
-        0x0a => Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::BC)),
-        0x0b => Inst::Dec16(Arg16::Reg(Reg16::BC)),
-        0x0c => Inst::Inc(Arg8::Reg(Reg8::C)),
-        0x0d => Inst::Dec(Arg8::Reg(Reg8::C)),
-        0x0e => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Ld8(Arg8::Reg(Reg8::C), Arg8::Immed(n))
-        }
-        0x0f => Inst::Rrca,
-        0x10 => Inst::Stop,
-        0x11 => {
-            let nn = memory.read_word(pc + 1);
-            addvance = 3;
-            Inst::Ld16(Arg16::Reg(Reg16::DE), Arg16::Immed(nn))
-        }
-        0x12 => Inst::Ld8(Arg8::IndReg(Reg16::DE), Arg8::Reg(Reg8::A)),
-        0x13 => Inst::Inc16(Arg16::Reg(Reg16::DE)),
-        0x14 => Inst::Inc(Arg8::Reg(Reg8::D)),
-        0x15 => Inst::Dec(Arg8::Reg(Reg8::D)),
-        0x16 => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Ld8(Arg8::Reg(Reg8::D), Arg8::Immed(n))
-        }
-        0x17 => Inst::Rla,
-        0x18 => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Jr(n as i8)
-        }
-        0x19 => Inst::Add16(Arg16::Reg(Reg16::HL), Arg16::Reg(Reg16::DE)),
-        0x1a => Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::DE)),
-        0x1b => Inst::Dec16(Arg16::Reg(Reg16::DE)),
-        0x1c => Inst::Inc(Arg8::Reg(Reg8::E)),
-        0x1d => Inst::Dec(Arg8::Reg(Reg8::E)),
-        0x1e => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Ld8(Arg8::Reg(Reg8::E), Arg8::Immed(n))
-        }
-        0x1f => Inst::Rra,
-        0x20 => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Jrf(JpFlag::Nz, n as i8)
-        }
-        0x21 => {
-            let nn = memory.read_word(pc + 1);
-            addvance = 3;
-            Inst::Ld16(Arg16::Reg(Reg16::HL), Arg16::Immed(nn))
-        }
-        0x22 => Inst::Ld8(Arg8::IndIncHL, Arg8::Reg(Reg8::A)),
-        0x23 => Inst::Inc16(Arg16::Reg(Reg16::HL)),
-        0x24 => Inst::Inc(Arg8::Reg(Reg8::H)),
-        0x25 => Inst::Dec(Arg8::Reg(Reg8::H)),
-        0x26 => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Ld8(Arg8::Reg(Reg8::H), Arg8::Immed(n))
-        }
-        0x27 => Inst::Daa,
-        0x28 => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Jrf(JpFlag::Z, n as i8)
-        }
-        0x29 => Inst::Add16(Arg16::Reg(Reg16::HL), Arg16::Reg(Reg16::HL)),
-        0x2a => Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::IndIncHL),
-        0x2b => Inst::Dec16(Arg16::Reg(Reg16::HL)),
-        0x2c => Inst::Inc(Arg8::Reg(Reg8::L)),
-        0x2d => Inst::Dec(Arg8::Reg(Reg8::L)),
-        0x2e => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Ld8(Arg8::Reg(Reg8::L), Arg8::Immed(n))
-        }
-        0x2f => Inst::Cpl,
-        0x30 => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Jrf(JpFlag::Nc, n as i8)
-        }
-        0x31 => {
-            let nn = memory.read_word(pc + 1);
-            addvance = 3;
-            Inst::Ld16(Arg16::Reg(Reg16::SP), Arg16::Immed(nn))
-        }
-        0x32 => Inst::Ld8(Arg8::IndDecHL, Arg8::Reg(Reg8::A)),
-        0x33 => Inst::Inc16(Arg16::Reg(Reg16::SP)),
-        0x34 => Inst::Inc(Arg8::IndReg(Reg16::HL)),
-        0x35 => Inst::Dec(Arg8::IndReg(Reg16::HL)),
-        0x36 => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Ld8(Arg8::IndReg(Reg16::HL), Arg8::Immed(n))
-        }
-        0x37 => Inst::Scf,
-        0x38 => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Jrf(JpFlag::C, n as i8)
-        }
-        0x39 => Inst::Add16(Arg16::Reg(Reg16::HL), Arg16::Reg(Reg16::SP)),
-        0x3a => Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::IndDecHL),
-        0x3b => Inst::Dec16(Arg16::Reg(Reg16::SP)),
-        0x3c => Inst::Inc(Arg8::Reg(Reg8::A)),
-        0x3d => Inst::Dec(Arg8::Reg(Reg8::A)),
-        0x3e => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::Immed(n))
-        }
-        0x3f => Inst::Ccf,
-        //
-        0x40 => Inst::Ld8(Arg8::Reg(Reg8::B), Arg8::Reg(Reg8::B)),
-        0x41 => Inst::Ld8(Arg8::Reg(Reg8::B), Arg8::Reg(Reg8::C)),
-        0x42 => Inst::Ld8(Arg8::Reg(Reg8::B), Arg8::Reg(Reg8::D)),
-        0x43 => Inst::Ld8(Arg8::Reg(Reg8::B), Arg8::Reg(Reg8::E)),
-        0x44 => Inst::Ld8(Arg8::Reg(Reg8::B), Arg8::Reg(Reg8::H)),
-        0x45 => Inst::Ld8(Arg8::Reg(Reg8::B), Arg8::Reg(Reg8::L)),
-        0x46 => Inst::Ld8(Arg8::Reg(Reg8::B), Arg8::IndReg(Reg16::HL)),
-        0x47 => Inst::Ld8(Arg8::Reg(Reg8::B), Arg8::Reg(Reg8::A)),
-        0x48 => Inst::Ld8(Arg8::Reg(Reg8::C), Arg8::Reg(Reg8::B)),
-        0x49 => Inst::Ld8(Arg8::Reg(Reg8::C), Arg8::Reg(Reg8::C)),
-        0x4a => Inst::Ld8(Arg8::Reg(Reg8::C), Arg8::Reg(Reg8::D)),
-        0x4b => Inst::Ld8(Arg8::Reg(Reg8::C), Arg8::Reg(Reg8::E)),
-        0x4c => Inst::Ld8(Arg8::Reg(Reg8::C), Arg8::Reg(Reg8::H)),
-        0x4d => Inst::Ld8(Arg8::Reg(Reg8::C), Arg8::Reg(Reg8::L)),
-        0x4e => Inst::Ld8(Arg8::Reg(Reg8::C), Arg8::IndReg(Reg16::HL)),
-        0x4f => Inst::Ld8(Arg8::Reg(Reg8::C), Arg8::Reg(Reg8::A)),
-        0x50 => Inst::Ld8(Arg8::Reg(Reg8::D), Arg8::Reg(Reg8::B)),
-        0x51 => Inst::Ld8(Arg8::Reg(Reg8::D), Arg8::Reg(Reg8::C)),
-        0x52 => Inst::Ld8(Arg8::Reg(Reg8::D), Arg8::Reg(Reg8::D)),
-        0x53 => Inst::Ld8(Arg8::Reg(Reg8::D), Arg8::Reg(Reg8::E)),
-        0x54 => Inst::Ld8(Arg8::Reg(Reg8::D), Arg8::Reg(Reg8::H)),
-        0x55 => Inst::Ld8(Arg8::Reg(Reg8::D), Arg8::Reg(Reg8::L)),
-        0x56 => Inst::Ld8(Arg8::Reg(Reg8::D), Arg8::IndReg(Reg16::HL)),
-        0x57 => Inst::Ld8(Arg8::Reg(Reg8::D), Arg8::Reg(Reg8::A)),
-        0x58 => Inst::Ld8(Arg8::Reg(Reg8::E), Arg8::Reg(Reg8::B)),
-        0x59 => Inst::Ld8(Arg8::Reg(Reg8::E), Arg8::Reg(Reg8::C)),
-        0x5a => Inst::Ld8(Arg8::Reg(Reg8::E), Arg8::Reg(Reg8::D)),
-        0x5b => Inst::Ld8(Arg8::Reg(Reg8::E), Arg8::Reg(Reg8::E)),
-        0x5c => Inst::Ld8(Arg8::Reg(Reg8::E), Arg8::Reg(Reg8::H)),
-        0x5d => Inst::Ld8(Arg8::Reg(Reg8::E), Arg8::Reg(Reg8::L)),
-        0x5e => Inst::Ld8(Arg8::Reg(Reg8::E), Arg8::IndReg(Reg16::HL)),
-        0x5f => Inst::Ld8(Arg8::Reg(Reg8::E), Arg8::Reg(Reg8::A)),
-        0x60 => Inst::Ld8(Arg8::Reg(Reg8::H), Arg8::Reg(Reg8::B)),
-        0x61 => Inst::Ld8(Arg8::Reg(Reg8::H), Arg8::Reg(Reg8::C)),
-        0x62 => Inst::Ld8(Arg8::Reg(Reg8::H), Arg8::Reg(Reg8::D)),
-        0x63 => Inst::Ld8(Arg8::Reg(Reg8::H), Arg8::Reg(Reg8::E)),
-        0x64 => Inst::Ld8(Arg8::Reg(Reg8::H), Arg8::Reg(Reg8::H)),
-        0x65 => Inst::Ld8(Arg8::Reg(Reg8::H), Arg8::Reg(Reg8::L)),
-        0x66 => Inst::Ld8(Arg8::Reg(Reg8::H), Arg8::IndReg(Reg16::HL)),
-        0x67 => Inst::Ld8(Arg8::Reg(Reg8::H), Arg8::Reg(Reg8::A)),
-        0x68 => Inst::Ld8(Arg8::Reg(Reg8::L), Arg8::Reg(Reg8::B)),
-        0x69 => Inst::Ld8(Arg8::Reg(Reg8::L), Arg8::Reg(Reg8::C)),
-        0x6a => Inst::Ld8(Arg8::Reg(Reg8::L), Arg8::Reg(Reg8::D)),
-        0x6b => Inst::Ld8(Arg8::Reg(Reg8::L), Arg8::Reg(Reg8::E)),
-        0x6c => Inst::Ld8(Arg8::Reg(Reg8::L), Arg8::Reg(Reg8::H)),
-        0x6d => Inst::Ld8(Arg8::Reg(Reg8::L), Arg8::Reg(Reg8::L)),
-        0x6e => Inst::Ld8(Arg8::Reg(Reg8::L), Arg8::IndReg(Reg16::HL)),
-        0x6f => Inst::Ld8(Arg8::Reg(Reg8::L), Arg8::Reg(Reg8::A)),
-        0x70 => Inst::Ld8(Arg8::IndReg(Reg16::HL), Arg8::Reg(Reg8::B)),
-        0x71 => Inst::Ld8(Arg8::IndReg(Reg16::HL), Arg8::Reg(Reg8::C)),
-        0x72 => Inst::Ld8(Arg8::IndReg(Reg16::HL), Arg8::Reg(Reg8::D)),
-        0x73 => Inst::Ld8(Arg8::IndReg(Reg16::HL), Arg8::Reg(Reg8::E)),
-        0x74 => Inst::Ld8(Arg8::IndReg(Reg16::HL), Arg8::Reg(Reg8::H)),
-        0x75 => Inst::Ld8(Arg8::IndReg(Reg16::HL), Arg8::Reg(Reg8::L)),
-        0x76 => Inst::Halt,
-        0x77 => Inst::Ld8(Arg8::IndReg(Reg16::HL), Arg8::Reg(Reg8::A)),
-        0x78 => Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::B)),
-        0x79 => Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::C)),
-        0x7a => Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::D)),
-        0x7b => Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::E)),
-        0x7c => Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::H)),
-        0x7d => Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::L)),
-        0x7e => Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::HL)),
-        0x7f => Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::A)),
-        //
-        0x80 => Inst::Add(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::B)),
-        0x81 => Inst::Add(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::C)),
-        0x82 => Inst::Add(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::D)),
-        0x83 => Inst::Add(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::E)),
-        0x84 => Inst::Add(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::H)),
-        0x85 => Inst::Add(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::L)),
-        0x86 => Inst::Add(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::HL)),
-        0x87 => Inst::Add(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::A)),
-        0x88 => Inst::Adc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::B)),
-        0x89 => Inst::Adc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::C)),
-        0x8a => Inst::Adc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::D)),
-        0x8b => Inst::Adc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::E)),
-        0x8c => Inst::Adc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::H)),
-        0x8d => Inst::Adc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::L)),
-        0x8e => Inst::Adc(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::HL)),
-        0x8f => Inst::Adc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::A)),
-        0x90 => Inst::Sub(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::B)),
-        0x91 => Inst::Sub(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::C)),
-        0x92 => Inst::Sub(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::D)),
-        0x93 => Inst::Sub(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::E)),
-        0x94 => Inst::Sub(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::H)),
-        0x95 => Inst::Sub(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::L)),
-        0x96 => Inst::Sub(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::HL)),
-        0x97 => Inst::Sub(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::A)),
-        0x98 => Inst::Sbc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::B)),
-        0x99 => Inst::Sbc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::C)),
-        0x9a => Inst::Sbc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::D)),
-        0x9b => Inst::Sbc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::E)),
-        0x9c => Inst::Sbc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::H)),
-        0x9d => Inst::Sbc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::L)),
-        0x9e => Inst::Sbc(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::HL)),
-        0x9f => Inst::Sbc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::A)),
-        0xa0 => Inst::And(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::B)),
-        0xa1 => Inst::And(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::C)),
-        0xa2 => Inst::And(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::D)),
-        0xa3 => Inst::And(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::E)),
-        0xa4 => Inst::And(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::H)),
-        0xa5 => Inst::And(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::L)),
-        0xa6 => Inst::And(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::HL)),
-        0xa7 => Inst::And(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::A)),
-        0xa8 => Inst::Xor(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::B)),
-        0xa9 => Inst::Xor(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::C)),
-        0xaa => Inst::Xor(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::D)),
-        0xab => Inst::Xor(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::E)),
-        0xac => Inst::Xor(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::H)),
-        0xad => Inst::Xor(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::L)),
-        0xae => Inst::Xor(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::HL)),
-        0xaf => Inst::Xor(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::A)),
-        0xb0 => Inst::Or(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::B)),
-        0xb1 => Inst::Or(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::C)),
-        0xb2 => Inst::Or(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::D)),
-        0xb3 => Inst::Or(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::E)),
-        0xb4 => Inst::Or(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::H)),
-        0xb5 => Inst::Or(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::L)),
-        0xb6 => Inst::Or(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::HL)),
-        0xb7 => Inst::Or(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::A)),
-        0xb8 => Inst::Cp(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::B)),
-        0xb9 => Inst::Cp(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::C)),
-        0xba => Inst::Cp(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::D)),
-        0xbb => Inst::Cp(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::E)),
-        0xbc => Inst::Cp(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::H)),
-        0xbd => Inst::Cp(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::L)),
-        0xbe => Inst::Cp(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::HL)),
-        0xbf => Inst::Cp(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::A)),
-        //
-        0xc0 => Inst::Retf(JpFlag::Nz),
-        0xc1 => Inst::Pop16(Reg16::BC),
-        0xc2 => {
-            let nn = memory.read_word(pc + 1);
-            addvance = 3;
-            Inst::Jpf(JpFlag::Nz, nn)
-        }
-        0xc3 => {
-            let nn = memory.read_word(pc + 1);
-            addvance = 3;
-            Inst::Jp(nn)
-        }
-        0xc4 => {
-            let nn = memory.read_word(pc + 1);
-            addvance = 3;
-            Inst::Callf(JpFlag::Nz, nn)
-        }
-        0xc5 => Inst::Push16(Reg16::BC),
-        0xc6 => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Add(Arg8::Reg(Reg8::A), Arg8::Immed(n))
-        }
-        0xc7 => Inst::Rst(0x00),
-        0xc8 => Inst::Retf(JpFlag::Z),
-        0xc9 => Inst::Ret,
-        0xca => {
-            let nn = memory.read_word(pc + 1);
-            addvance = 3;
-            Inst::Jpf(JpFlag::Z, nn)
-        }
-        0xcb => {
-            let code = memory.read_byte(pc + 1);
-            addvance = 2;
-            pf_cb::decode_prefix_cb(code)
-        }
-        0xcc => {
-            let nn = memory.read_word(pc + 1);
-            addvance = 3;
-            Inst::Callf(JpFlag::Z, nn)
-        }
-        0xcd => {
-            let nn = memory.read_word(pc + 1);
-            addvance = 3;
-            Inst::Call(nn)
-        }
-        0xce => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Adc(Arg8::Reg(Reg8::A), Arg8::Immed(n))
-        }
-        0xcf => Inst::Rst(0x08),
-        0xd0 => Inst::Retf(JpFlag::Nc),
-        0xd1 => Inst::Pop16(Reg16::DE),
-        0xd2 => {
-            let nn = memory.read_word(pc + 1);
-            addvance = 3;
-            Inst::Jpf(JpFlag::Nc, nn)
-        }
-        // 0xd3
-        0xd4 => {
-            let nn = memory.read_word(pc + 1);
-            addvance = 3;
-            Inst::Callf(JpFlag::Nc, nn)
-        }
-        0xd5 => Inst::Push16(Reg16::DE),
-        0xd6 => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Sub(Arg8::Reg(Reg8::A), Arg8::Immed(n))
-        }
-        0xd7 => Inst::Rst(0x10),
-        0xd8 => Inst::Retf(JpFlag::C),
-        0xd9 => Inst::Reti,
-        0xda => {
-            let nn = memory.read_word(pc + 1);
-            addvance = 3;
-            Inst::Jpf(JpFlag::C, nn)
-        }
-        // 0xdb
-        0xdc => {
-            let nn = memory.read_word(pc + 1);
-            addvance = 3;
-            Inst::Callf(JpFlag::C, nn)
-        }
-        // 0xdd
-        0xde => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Sbc(Arg8::Reg(Reg8::A), Arg8::Immed(n))
-        }
-        0xdf => Inst::Rst(0x18),
-        0xe0 => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Ld8(Arg8::IndIo(n), Arg8::Reg(Reg8::A))
-        }
-        0xe1 => Inst::Pop16(Reg16::HL),
-        0xe2 => Inst::Ld8(Arg8::IndIoC, Arg8::Reg(Reg8::A)),
-        // 0xe3
-        // 0xe4
-        0xe5 => Inst::Push16(Reg16::HL),
-        0xe6 => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::And(Arg8::Reg(Reg8::A), Arg8::Immed(n))
-        }
-        0xe7 => Inst::Rst(0x20),
-        0xe8 => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Add16SP(n as i8)
-        }
-        0xe9 => Inst::JpHL,
-        0xea => {
-            let nn = memory.read_word(pc + 1);
-            addvance = 3;
-            Inst::Ld8(Arg8::Ind(nn), Arg8::Reg(Reg8::A))
-        }
-        // 0xeb
-        // 0xec
-        // 0xed
-        0xee => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Xor(Arg8::Reg(Reg8::A), Arg8::Immed(n))
-        }
-        0xef => Inst::Rst(0x28),
-        0xf0 => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::IndIo(n))
-        }
-        0xf1 => Inst::Pop16(Reg16::AF),
-        0xf2 => Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::IndIoC),
-        0xf3 => Inst::Di,
-        // 0xf4
-        0xf5 => Inst::Push16(Reg16::AF),
-        0xf6 => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Or(Arg8::Reg(Reg8::A), Arg8::Immed(n))
-        }
-        0xf7 => Inst::Rst(0x30),
-        0xf8 => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Ld16HLSP(n as i8)
-        }
-        0xf9 => Inst::Ld16(Arg16::Reg(Reg16::SP), Arg16::Reg(Reg16::HL)),
-        0xfa => {
-            let nn = memory.read_word(pc + 1);
-            addvance = 3;
-            Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::Ind(nn))
-        }
-        0xfb => Inst::Ei,
-        // 0xfc
-        // 0xfd
-        0xfe => {
-            let n = memory.read_byte(pc + 1);
-            addvance = 2;
-            Inst::Cp(Arg8::Reg(Reg8::A), Arg8::Immed(n))
-        }
-        0xff => Inst::Rst(0x38),
-        code => return Err(format!("Invalid code: {:#x}", code)),
     };
-    Ok((inst, addvance))
+    match &info.inst {
+        Some(inst) => Ok((table::with_immediate(inst.clone(), imm), info.len)),
+        None => Err(format!("Invalid code: {:#x}", code)),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cpu::inst::{Arg16, Arg8, JpFlag, Reg16, Reg8};
 
     struct TestMemory {
         memory: [u8; 0x200],
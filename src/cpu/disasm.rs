@@ -0,0 +1,339 @@
+//! Disassembler producing RGBDS source.
+//!
+//! Code is found by following the control flow from the entry points
+//! (0x0100 and the RST / interrupt vectors in bank 0, the start of the range
+//! otherwise); everything that is never reached is emitted as `db`.
+
+use super::decode;
+use super::inst::{Inst, JpFlag};
+use super::table::{CB_OPS, OPS};
+use crate::memory::MemoryIF;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+
+const BANK_SIZE: usize = 0x4000;
+// RST, interrupts and the entry point
+const VECTORS: [u16; 14] = [
+    0x00, 0x08, 0x10, 0x18, 0x20, 0x28, 0x30, 0x38, 0x40, 0x48, 0x50, 0x58, 0x60, 0x100,
+];
+
+/// `rs-gb disasm <rom> [bank] [begin end]`, `rs-gb disasm --opcodes`
+pub fn main(args: &[String]) -> i32 {
+    if args.first().is_some_and(|a| a == "--opcodes") {
+        print!("{}", opcodes());
+        return 0;
+    }
+    let (rom, bank) = match args {
+        [rom] => (rom, 0),
+        [rom, bank, ..] => (rom, parse_num(bank).expect("bank: cannot parse") as usize),
+        _ => {
+            println!("usage: rs-gb disasm <rom> [bank] [begin end]");
+            println!("       rs-gb disasm --opcodes");
+            return 2;
+        }
+    };
+    let rom = match fs::read(rom) {
+        Ok(rom) => rom,
+        Err(e) => {
+            println!("error {}", e);
+            return 1;
+        }
+    };
+    let (begin, end) = match args {
+        [_, _, begin, end] => (
+            parse_num(begin).expect("begin: cannot parse"),
+            parse_num(end).expect("end: cannot parse"),
+        ),
+        _ if bank == 0 => (0x0000, 0x4000),
+        _ => (0x4000, 0x8000),
+    };
+    print!("{}", disasm(&rom, bank, begin, end));
+    0
+}
+
+/// Parses `$1234`, `0x1234` or a decimal number.
+pub fn parse_num(s: &str) -> Option<u32> {
+    let s = s.trim();
+    if let Some(hex) = s.strip_prefix('$').or_else(|| s.strip_prefix("0x")) {
+        u32::from_str_radix(hex, 16).ok()
+    } else {
+        s.parse().ok()
+    }
+}
+
+struct RomBank<'a> {
+    rom: &'a [u8],
+    bank: usize,
+}
+
+impl MemoryIF for RomBank<'_> {
+    fn read_byte(&self, addr: u16) -> u8 {
+        let index = match addr {
+            0x0000..=0x3fff => addr as usize,
+            0x4000..=0x7fff => self.bank * BANK_SIZE + (addr as usize - BANK_SIZE),
+            _ => return 0xff,
+        };
+        self.rom.get(index).copied().unwrap_or(0xff)
+    }
+    fn write_byte(&mut self, _addr: u16, _val: u8) {}
+}
+
+#[derive(PartialEq, PartialOrd)]
+enum Label {
+    Jump,
+    Call,
+}
+
+/// Disassembles `begin..end` (CPU addresses) with `bank` mapped at 0x4000.
+pub fn disasm(rom: &[u8], bank: usize, begin: u32, end: u32) -> String {
+    let memory = RomBank { rom, bank };
+    let in_range = |addr: u32| begin <= addr && addr < end;
+
+    let mut entries: Vec<u32> = if begin == 0 {
+        VECTORS
+            .iter()
+            .map(|&v| v as u32)
+            .filter(|&v| in_range(v))
+            .collect()
+    } else {
+        vec![begin]
+    };
+    let mut code: BTreeMap<u32, (Inst, u32)> = BTreeMap::new();
+    let mut labels: BTreeMap<u32, Label> = BTreeMap::new();
+    let mut is_code = vec![false; (end.saturating_sub(begin)) as usize];
+
+    while let Some(mut addr) = entries.pop() {
+        while in_range(addr) && !is_code[(addr - begin) as usize] {
+            let (inst, len) = match decode::decode(addr as u16, &memory) {
+                Ok((Inst::Stop, _)) => (Inst::Stop, 2), // the following byte is skipped
+                Ok((inst, len)) => (inst, len as u32),
+                Err(_) => break,
+            };
+            let next = addr + len;
+            if !(addr..next).all(|a| in_range(a) && !is_code[(a - begin) as usize]) {
+                break;
+            }
+            for a in addr..next {
+                is_code[(a - begin) as usize] = true;
+            }
+
+            let (target, label, fall_through) = match inst {
+                Inst::Jp(nn) => (Some(nn as u32), Label::Jump, false),
+                Inst::Jpf(_, nn) => (Some(nn as u32), Label::Jump, true),
+                Inst::Jr(dd) => (Some(jr_target(next, dd)), Label::Jump, false),
+                Inst::Jrf(_, dd) => (Some(jr_target(next, dd)), Label::Jump, true),
+                Inst::Call(nn) | Inst::Callf(_, nn) => (Some(nn as u32), Label::Call, true),
+                Inst::Rst(n) => (Some(n as u32), Label::Call, true),
+                Inst::Ret | Inst::Reti | Inst::JpHL => (None, Label::Jump, false),
+                _ => (None, Label::Jump, true),
+            };
+            if let Some(target) = target.filter(|&t| in_range(t)) {
+                if !matches!(inst, Inst::Rst(_)) {
+                    let l = labels.entry(target).or_insert(Label::Jump);
+                    if label > *l {
+                        *l = label;
+                    }
+                }
+                entries.push(target);
+            }
+            code.insert(addr, (inst, len));
+            if !fall_through {
+                break;
+            }
+            addr = next;
+        }
+    }
+
+    // a target inside an instruction has no line to put its label on
+    labels.retain(|addr, _| !is_code[(addr - begin) as usize] || code.contains_key(addr));
+
+    let label_name = |addr: u32| {
+        labels.get(&addr).map(|l| {
+            let kind = match l {
+                Label::Jump => "Jump",
+                Label::Call => "Call",
+            };
+            let bank = if addr < BANK_SIZE as u32 { 0 } else { bank };
+            format!("{}_{:03x}_{:04x}", kind, bank, addr)
+        })
+    };
+
+    let mut out = String::new();
+    if begin < BANK_SIZE as u32 {
+        writeln!(out, "SECTION \"ROM0 ${:04x}\", ROM0[${:04x}]", begin, begin).unwrap();
+    } else {
+        writeln!(
+            out,
+            "SECTION \"ROMX ${:03x} ${:04x}\", ROMX[${:04x}], BANK[${:x}]",
+            bank, begin, begin, bank
+        )
+        .unwrap();
+    }
+    let mut addr = begin;
+    while addr < end {
+        if let Some(name) = label_name(addr) {
+            writeln!(out, "\n{}:", name).unwrap();
+        }
+        if let Some((inst, len)) = code.get(&addr) {
+            let text = match inst {
+                Inst::Jp(nn) => format_jump("jp", None, *nn as u32, &label_name),
+                Inst::Jpf(cc, nn) => format_jump("jp", Some(cc), *nn as u32, &label_name),
+                Inst::Jr(dd) => format_jump("jr", None, jr_target(addr + len, *dd), &label_name),
+                Inst::Jrf(cc, dd) => {
+                    format_jump("jr", Some(cc), jr_target(addr + len, *dd), &label_name)
+                }
+                Inst::Call(nn) => format_jump("call", None, *nn as u32, &label_name),
+                Inst::Callf(cc, nn) => format_jump("call", Some(cc), *nn as u32, &label_name),
+                inst => inst.to_string(),
+            };
+            let bytes: Vec<String> = (addr..addr + len)
+                .map(|a| format!("{:02x}", memory.read_byte(a as u16)))
+                .collect();
+            writeln!(out, "    {:<24}; ${:04x}: {}", text, addr, bytes.join(" ")).unwrap();
+            addr += len;
+        } else {
+            // data up to the next code or label, 8 bytes a line
+            let mut bytes = Vec::new();
+            while addr < end && bytes.len() < 8 && !code.contains_key(&addr) {
+                if !bytes.is_empty() && labels.contains_key(&addr) {
+                    break;
+                }
+                bytes.push(format!("${:02x}", memory.read_byte(addr as u16)));
+                addr += 1;
+            }
+            writeln!(out, "    db {}", bytes.join(", ")).unwrap();
+        }
+    }
+    out
+}
+
+/// Opcode reference: opcode, mnemonic, length, M-cycles (not taken / taken)
+/// and flags (ZNHC).
+pub fn opcodes() -> String {
+    let mut out = String::new();
+    for (prefix, table) in [("", &OPS), ("cb ", &CB_OPS)] {
+        for (code, info) in table.iter().enumerate() {
+            if info.inst.is_none() {
+                continue;
+            }
+            let cycles = if info.cycles == info.cycles_taken {
+                info.cycles.to_string()
+            } else {
+                format!("{}/{}", info.cycles, info.cycles_taken)
+            };
+            let code = format!("{}{:02x}", prefix, code);
+            writeln!(
+                out,
+                "{:<6}{:<16}{:>2}{:>5}  {}",
+                code, info.mnemonic, info.len, cycles, info.flags
+            )
+            .unwrap();
+        }
+    }
+    out
+}
+
+fn jr_target(next: u32, dd: i8) -> u32 {
+    (next as i32 + dd as i32) as u32 & 0xffff
+}
+
+fn format_jump(
+    mnemonic: &str,
+    cc: Option<&JpFlag>,
+    target: u32,
+    label_name: &impl Fn(u32) -> Option<String>,
+) -> String {
+    let target = label_name(target).unwrap_or_else(|| format!("${:04x}", target));
+    match cc {
+        Some(cc) => format!("{} {}, {}", mnemonic, cc, target),
+        None => format!("{} {}", mnemonic, target),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_num_test() {
+        assert_eq!(Some(0x150), parse_num("$150"));
+        assert_eq!(Some(0x150), parse_num("0x150"));
+        assert_eq!(Some(150), parse_num("150"));
+        assert_eq!(None, parse_num("$xyz"));
+    }
+    #[test]
+    fn opcodes_test() {
+        let s = opcodes();
+        assert_eq!(256 - 11 - 1 + 256, s.lines().count());
+        assert!(
+            s.starts_with("00    nop              1    1  ----\n"),
+            "{}",
+            s
+        );
+        assert!(
+            s.contains("\n20    jr nz, e8        2  2/3  ----\n"),
+            "{}",
+            s
+        );
+        assert!(
+            s.contains("\ncb 7e bit 7, [hl]      2    3  Z01-\n"),
+            "{}",
+            s
+        );
+    }
+    #[test]
+    fn disasm_follows_jumps() {
+        let mut rom = vec![0xff; 0x8000];
+        // 0x0100: nop; jp $0150
+        rom[0x100..0x104].copy_from_slice(&[0x00, 0xc3, 0x50, 0x01]);
+        // 0x0150: call $0158; jr nz, -5; ret; db $12, $34
+        // 0x0158: ld a, [hl+]; ret
+        rom[0x150..0x15a]
+            .copy_from_slice(&[0xcd, 0x58, 0x01, 0x20, 0xfb, 0xc9, 0x12, 0x34, 0x2a, 0xc9]);
+        let s = disasm(&rom, 0, 0x100, 0x15a);
+        let expected = "\
+SECTION \"ROM0 $0100\", ROM0[$0100]
+    nop                     ; $0100: 00
+    jp Jump_000_0150        ; $0101: c3 50 01
+    db $ff, $ff, $ff, $ff, $ff, $ff, $ff, $ff
+";
+        assert!(s.starts_with(expected), "{}", s);
+        let expected = "
+Jump_000_0150:
+    call Call_000_0158      ; $0150: cd 58 01
+    jr nz, Jump_000_0150    ; $0153: 20 fb
+    ret                     ; $0155: c9
+    db $12, $34
+
+Call_000_0158:
+    ld a, [hl+]             ; $0158: 2a
+    ret                     ; $0159: c9
+";
+        assert!(s.ends_with(expected), "{}", s);
+    }
+    #[test]
+    fn disasm_jump_into_inst() {
+        let mut rom = vec![0xff; 0x8000];
+        // 0x0100: ld a, $18; jr $0101, into the operand of ld
+        rom[0x100..0x104].copy_from_slice(&[0x3e, 0x18, 0x18, 0xfd]);
+        let s = disasm(&rom, 0, 0x100, 0x104);
+        let expected = "\
+SECTION \"ROM0 $0100\", ROM0[$0100]
+    ld a, $18               ; $0100: 3e 18
+    jr $0101                ; $0102: 18 fd
+";
+        assert_eq!(expected, s);
+    }
+    #[test]
+    fn disasm_bank() {
+        let mut rom = vec![0x00; 0x10000];
+        // bank 2, 0x4000: jr -2
+        rom[0x8000..0x8002].copy_from_slice(&[0x18, 0xfe]);
+        let s = disasm(&rom, 2, 0x4000, 0x4002);
+        assert_eq!(
+            "SECTION \"ROMX $002 $4000\", ROMX[$4000], BANK[$2]\n\nJump_002_4000:\n    jr Jump_002_4000        ; $4000: 18 fe\n",
+            s
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use super::inst::{Arg16, Arg8, FlagReg, Inst, JpFlag, Reg16, Reg8};
+use super::table::{self, Exec, OpInfo, Operands, CB_OPS, OPS};
 use super::{Flags, Registers, M};
-use crate::memory::MemoryIF;
+use crate::memory::{MemoryIF, OamAccess};
 
 impl Registers {
     pub fn execute(
@@ -1278,6 +1279,323 @@ impl Registers {
     }
 }
 
+// Table-driven execution: `step` decodes and executes the instruction at PC
+// through the handlers selected by `table::Exec`, and takes the timing from
+// the table.
+
+pub struct Ctx<'a> {
+    op: u8,
+    imm: u16,
+    ime: &'a mut bool,
+    flags: Flags,
+}
+
+type Handler<T> = fn(&mut Registers, &mut T, &mut Ctx) -> Result<(), String>;
+
+trait Dispatch: MemoryIF + Sized {
+    const HANDLERS: [Handler<Self>; 256];
+    const CB_HANDLERS: [Handler<Self>; 256];
+}
+
+impl<T: MemoryIF> Dispatch for T {
+    const HANDLERS: [Handler<T>; 256] = handlers(false);
+    const CB_HANDLERS: [Handler<T>; 256] = handlers(true);
+}
+
+const fn handlers<T: MemoryIF>(cb: bool) -> [Handler<T>; 256] {
+    let mut h = [invalid as Handler<T>; 256];
+    let mut i = 0;
+    while i < 256 {
+        h[i] = handler(if cb {
+            table::exec_cb(i)
+        } else {
+            table::exec(i)
+        });
+        i += 1;
+    }
+    h
+}
+
+const fn handler<T: MemoryIF>(exec: Exec) -> Handler<T> {
+    match exec {
+        Exec::Nop => |_, _, _| Ok(()),
+        Exec::LdRrNn => |r, m, c| {
+            r.ld16(Arg16::Reg(rr(c.op)), Arg16::Immed(c.imm), m)
+                .map(drop)
+        },
+        Exec::LdIndA => |r, m, c| {
+            m.oam_bug(r.read_reg16(&ind_reg(c.op)), OamAccess::Write, 1);
+            r.ld8(ind(c.op), Arg8::Reg(Reg8::A), m).map(drop)
+        },
+        Exec::LdAInd => |r, m, c| {
+            // [HL+] / [HL-] increment or decrement HL in the same cycle
+            let access = if c.op & 0x20 != 0 {
+                OamAccess::ReadIncDec
+            } else {
+                OamAccess::Read
+            };
+            m.oam_bug(r.read_reg16(&ind_reg(c.op)), access, 1);
+            r.ld8(Arg8::Reg(Reg8::A), ind(c.op), m).map(drop)
+        },
+        Exec::Inc16 => |r, m, c| {
+            m.oam_bug(r.read_reg16(&rr(c.op)), OamAccess::Write, 1);
+            ok(r.inc16_rr(rr(c.op)))
+        },
+        Exec::Dec16 => |r, m, c| {
+            m.oam_bug(r.read_reg16(&rr(c.op)), OamAccess::Write, 1);
+            ok(r.dec16_rr(rr(c.op)))
+        },
+        Exec::IncR => |r, m, c| r.inc(r8(c.op >> 3), m).map(drop),
+        Exec::DecR => |r, m, c| r.dec(r8(c.op >> 3), m).map(drop),
+        Exec::LdRN => |r, m, c| r.ld8(r8(c.op >> 3), Arg8::Immed(c.imm as u8), m).map(drop),
+        Exec::Rlca => |r, _, _| ok(r.rlca()),
+        Exec::Rrca => |r, _, _| ok(r.rrca()),
+        Exec::Rla => |r, _, _| ok(r.rla()),
+        Exec::Rra => |r, _, _| ok(r.rra()),
+        Exec::LdNnSp => |r, m, c| {
+            r.ld16(Arg16::Ind(c.imm), Arg16::Reg(Reg16::SP), m)
+                .map(drop)
+        },
+        Exec::AddHlRr => |r, _, c| ok(r.add16_hl(rr(c.op))),
+        Exec::Stop => |r, _, c| ok(r.stop(&mut c.flags.stop)),
+        Exec::Jr => |r, _, c| ok(r.jr_dd(c.imm as u8 as i8)),
+        Exec::JrCc => |r, _, c| ok(r.jr_f_dd(cc(c.op), c.imm as u8 as i8)),
+        Exec::Daa => |r, _, _| ok(r.daa()),
+        Exec::Cpl => |r, _, _| ok(r.cpl()),
+        Exec::Scf => |r, _, _| ok(r.scf()),
+        Exec::Ccf => |r, _, _| ok(r.ccf()),
+        Exec::LdRR => |r, m, c| r.ld8(r8(c.op >> 3), r8(c.op), m).map(drop),
+        Exec::Halt => |r, _, c| ok(r.halt(&mut c.flags.halt)),
+        Exec::AluR => |r, m, c| r.alu(c.op >> 3, r8(c.op), m),
+        Exec::AluN => |r, m, c| r.alu(c.op >> 3, Arg8::Immed(c.imm as u8), m),
+        Exec::RetCc => |r, m, c| ok(r.ret_f(cc(c.op), m)),
+        Exec::Pop => |r, m, c| {
+            let sp = r.read_reg16(&Reg16::SP);
+            m.oam_bug(sp, OamAccess::ReadIncDec, 1);
+            m.oam_bug(sp.wrapping_add(1), OamAccess::ReadIncDec, 2);
+            ok(r.pop(rr_af(c.op), m))
+        },
+        Exec::JpCc => |r, _, c| ok(r.jp_f_nn(cc(c.op), c.imm)),
+        Exec::Jp => |r, _, c| ok(r.jp_nn(c.imm)),
+        Exec::CallCc => |r, m, c| ok(r.call_f_nn(cc(c.op), c.imm, m)),
+        Exec::Push => |r, m, c| {
+            let sp = r.read_reg16(&Reg16::SP);
+            for i in 0..3 {
+                m.oam_bug(sp.wrapping_sub(i), OamAccess::Write, 1 + i as usize);
+            }
+            ok(r.push(rr_af(c.op), m))
+        },
+        Exec::Rst => |r, m, c| ok(r.rst_n(c.op & 0x38, m)),
+        Exec::Ret => |r, m, _| ok(r.ret(m)),
+        Exec::Reti => |r, m, c| ok(r.reti(m, c.ime)),
+        Exec::Call => |r, m, c| ok(r.call_nn(c.imm, m)),
+        Exec::LdhNA => |r, m, c| {
+            r.ld8(Arg8::IndIo(c.imm as u8), Arg8::Reg(Reg8::A), m)
+                .map(drop)
+        },
+        Exec::LdhAN => |r, m, c| {
+            r.ld8(Arg8::Reg(Reg8::A), Arg8::IndIo(c.imm as u8), m)
+                .map(drop)
+        },
+        Exec::LdhCA => |r, m, _| r.ld8(Arg8::IndIoC, Arg8::Reg(Reg8::A), m).map(drop),
+        Exec::LdhAC => |r, m, _| r.ld8(Arg8::Reg(Reg8::A), Arg8::IndIoC, m).map(drop),
+        Exec::AddSpE => |r, _, c| ok(r.add16_sp_dd(c.imm as u8 as i8)),
+        Exec::JpHl => |r, _, _| ok(r.jp_hl()),
+        Exec::LdNnA => |r, m, c| r.ld8(Arg8::Ind(c.imm), Arg8::Reg(Reg8::A), m).map(drop),
+        Exec::LdANn => |r, m, c| r.ld8(Arg8::Reg(Reg8::A), Arg8::Ind(c.imm), m).map(drop),
+        Exec::Di => |r, _, c| ok(r.di(c.ime)),
+        Exec::Ei => |r, _, c| ok(r.ei(c.ime)),
+        Exec::LdHlSpE => |r, _, c| ok(r.ld16_hl_sp_dd(c.imm as u8 as i8)),
+        Exec::LdSpHl => |r, m, _| {
+            r.ld16(Arg16::Reg(Reg16::SP), Arg16::Reg(Reg16::HL), m)
+                .map(drop)
+        },
+        // CB prefix
+        Exec::Rot => |r, m, c| r.rot_op(c.op >> 3, &r8(c.op), m),
+        Exec::Bit => |r, m, c| r.bit((c.op >> 3) & 7, &r8(c.op), m).map(drop),
+        Exec::Res => |r, m, c| r.res((c.op >> 3) & 7, &r8(c.op), m).map(drop),
+        Exec::Set => |r, m, c| r.set((c.op >> 3) & 7, &r8(c.op), m).map(drop),
+        // 0xcb is handled by `step`
+        Exec::Prefix | Exec::Invalid => invalid,
+    }
+}
+
+fn invalid<T: MemoryIF>(_: &mut Registers, _: &mut T, c: &mut Ctx) -> Result<(), String> {
+    Err(format!("Invalid code: {:#x}", c.op))
+}
+
+fn ok(_: M) -> Result<(), String> {
+    Ok(())
+}
+
+// operands encoded in the opcode
+fn r8(op: u8) -> Arg8 {
+    match op & 7 {
+        0 => Arg8::Reg(Reg8::B),
+        1 => Arg8::Reg(Reg8::C),
+        2 => Arg8::Reg(Reg8::D),
+        3 => Arg8::Reg(Reg8::E),
+        4 => Arg8::Reg(Reg8::H),
+        5 => Arg8::Reg(Reg8::L),
+        6 => Arg8::IndReg(Reg16::HL),
+        _ => Arg8::Reg(Reg8::A),
+    }
+}
+fn rr(op: u8) -> Reg16 {
+    match (op >> 4) & 3 {
+        0 => Reg16::BC,
+        1 => Reg16::DE,
+        2 => Reg16::HL,
+        _ => Reg16::SP,
+    }
+}
+fn rr_af(op: u8) -> Reg16 {
+    match (op >> 4) & 3 {
+        0 => Reg16::BC,
+        1 => Reg16::DE,
+        2 => Reg16::HL,
+        _ => Reg16::AF,
+    }
+}
+/// Address register of `ind`
+fn ind_reg(op: u8) -> Reg16 {
+    match (op >> 4) & 3 {
+        0 => Reg16::BC,
+        1 => Reg16::DE,
+        _ => Reg16::HL,
+    }
+}
+fn ind(op: u8) -> Arg8 {
+    match (op >> 4) & 3 {
+        0 => Arg8::IndReg(Reg16::BC),
+        1 => Arg8::IndReg(Reg16::DE),
+        2 => Arg8::IndIncHL,
+        _ => Arg8::IndDecHL,
+    }
+}
+fn cc(op: u8) -> JpFlag {
+    match (op >> 3) & 3 {
+        0 => JpFlag::Nz,
+        1 => JpFlag::Z,
+        2 => JpFlag::Nc,
+        _ => JpFlag::C,
+    }
+}
+
+/// An instruction with its operands read from memory
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Decoded {
+    pub op: u8,
+    // `op` is a CB-prefixed opcode
+    pub cb: bool,
+    pub imm: u16,
+}
+
+impl Decoded {
+    pub fn info(&self) -> &'static OpInfo {
+        if self.cb {
+            &CB_OPS[self.op as usize]
+        } else {
+            &OPS[self.op as usize]
+        }
+    }
+}
+
+pub fn fetch(pc: u16, memory: &impl MemoryIF) -> Decoded {
+    let op = memory.read_byte(pc);
+    let (op, cb) = if op == 0xcb {
+        (memory.read_byte(pc.wrapping_add(1)), true)
+    } else {
+        (op, false)
+    };
+    let imm = match OPS[op as usize].operands {
+        _ if cb => 0,
+        Operands::Imm8 => memory.read_byte(pc.wrapping_add(1)) as u16,
+        Operands::Imm16 => memory.read_word(pc.wrapping_add(1)),
+        Operands::Implied | Operands::Cb => 0,
+    };
+    Decoded { op, cb, imm }
+}
+
+impl Registers {
+    pub fn step<T: MemoryIF>(
+        &mut self,
+        memory: &mut T,
+        ime: &mut bool,
+    ) -> Result<(M, Flags), String> {
+        let decoded = fetch(self.pc, memory);
+        self.execute_decoded(&decoded, memory, ime)
+    }
+    /// Executes an instruction fetched from PC, see `fetch`.
+    pub fn execute_decoded<T: MemoryIF>(
+        &mut self,
+        decoded: &Decoded,
+        memory: &mut T,
+        ime: &mut bool,
+    ) -> Result<(M, Flags), String> {
+        let op = decoded.op;
+        let (info, handler) = if decoded.cb {
+            (&CB_OPS[op as usize], T::CB_HANDLERS[op as usize])
+        } else {
+            (&OPS[op as usize], T::HANDLERS[op as usize])
+        };
+        // conditions do not depend on the instruction itself
+        let taken = info.cycles_taken != info.cycles && self.condition(op);
+        self.pc = self.pc.wrapping_add(info.len);
+
+        let mut ctx = Ctx {
+            op,
+            imm: decoded.imm,
+            ime,
+            flags: Flags {
+                halt: false,
+                stop: false,
+            },
+        };
+        handler(self, memory, &mut ctx)?;
+        let m = if taken {
+            info.cycles_taken
+        } else {
+            info.cycles
+        };
+        Ok((m, ctx.flags))
+    }
+    fn condition(&self, op: u8) -> bool {
+        match cc(op) {
+            JpFlag::Nz => !self.test_f(FlagReg::Z),
+            JpFlag::Z => self.test_f(FlagReg::Z),
+            JpFlag::Nc => !self.test_f(FlagReg::C),
+            JpFlag::C => self.test_f(FlagReg::C),
+        }
+    }
+    fn alu(&mut self, op: u8, x: Arg8, memory: &mut impl MemoryIF) -> Result<(), String> {
+        match op & 7 {
+            0 => self.add_a(x, memory),
+            1 => self.adc_a(x, memory),
+            2 => self.sub_a(x, memory),
+            3 => self.sbc_a(x, memory),
+            4 => self.and_a(x, memory),
+            5 => self.xor_a(x, memory),
+            6 => self.or_a(x, memory),
+            _ => self.cp(x, memory),
+        }
+        .map(drop)
+    }
+    fn rot_op(&mut self, op: u8, x: &Arg8, memory: &mut impl MemoryIF) -> Result<(), String> {
+        match op & 7 {
+            0 => self.rlc(x, memory),
+            1 => self.rrc(x, memory),
+            2 => self.rl(x, memory),
+            3 => self.rr(x, memory),
+            4 => self.sla(x, memory),
+            5 => self.sra(x, memory),
+            6 => self.swap(x, memory),
+            _ => self.srl(x, memory),
+        }
+        .map(drop)
+    }
+}
+
 // utils
 enum Direction {
     Left,
@@ -1322,6 +1640,7 @@ fn rot_through_carry(v: u8, c: Carry, d: Direction) -> (u8, Carry) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cpu::decode;
 
     struct TestMemory {
         memory: [u8; 0x10000],
@@ -2680,4 +2999,119 @@ mod tests {
         assert_eq!(0xffe, reg.read_reg16(&Reg16::SP));
         assert_eq!(0x200, mem.read_word(0xffe));
     }
+
+    #[test]
+    fn step_matches_execute() {
+        // every opcode through `step` and through `decode` + `execute`,
+        // with all the flag combinations (taken and not taken branches)
+        let codes = (0..=0xffu16)
+            .filter(|&op| op != 0xcb)
+            .map(|op| [op as u8, 0x34, 0x12])
+            .chain((0..=0xffu8).map(|op| [0xcb, op, 0x00]));
+        for bytes in codes {
+            for f in [0x00, 0x10, 0x80, 0x90, 0x60] {
+                let mut reg = Registers::new();
+                (reg.a, reg.b, reg.c, reg.d, reg.e) = (0x3c, 0x01, 0x81, 0x7f, 0xff);
+                (reg.h, reg.l, reg.f) = (0xc0, 0x10, f);
+                (reg.pc, reg.sp) = (0x0200, 0xd000);
+                let mut memory = TestMemory::new();
+                memory.memory[0x200..0x203].copy_from_slice(&bytes);
+                memory.memory[0xc010] = 0x5a;
+                memory.memory[0xd000..0xd002].copy_from_slice(&[0x00, 0x03]);
+
+                let (mut reg1, mut memory1, mut ime1) = (reg.clone(), TestMemory::new(), false);
+                memory1.memory = memory.memory;
+                let expected = decode::decode(reg1.pc, &memory1).and_then(|(inst, len)| {
+                    reg1.pc += len;
+                    reg1.execute(inst, &mut memory1, &mut ime1)
+                });
+                let mut ime = false;
+                let actual = reg.step(&mut memory, &mut ime);
+
+                let name = format!("{:02x?} f={:02x}", bytes, f);
+                match (expected, actual) {
+                    (Ok((m1, flags1)), Ok((m, flags))) => {
+                        // HALT and STOP take 1 M-cycle, `execute` returns 0
+                        if flags1.halt || flags1.stop {
+                            assert_eq!((0, 1), (m1, m), "{}", name);
+                        } else {
+                            assert_eq!(m1, m, "{}", name);
+                        }
+                        assert_eq!(flags1, flags, "{}", name);
+                    }
+                    (Err(_), Err(_)) => continue,
+                    (e, a) => panic!("{}: {:?} {:?}", name, e, a),
+                }
+                assert_eq!(reg1, reg, "{}", name);
+                assert_eq!(ime1, ime, "{}", name);
+                assert!(memory1.memory == memory.memory, "{}", name);
+            }
+        }
+    }
+
+    // cargo test --release bench_step -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_step() {
+        use crate::cpu::asm::assemble;
+        use std::time::Instant;
+
+        let src = "
+        start:
+            ld hl, $c000
+            ld de, $c800
+            ld bc, $0100
+        .loop:
+            ld a, [hl+]
+            add a, b
+            xor c
+            ld [de], a
+            inc de
+            call .sub
+            dec bc
+            ld a, b
+            or c
+            jr nz, .loop
+            jp start
+        .sub:
+            bit 0, a
+            ret z
+            swap a
+            ret
+        ";
+        let program = assemble(src).unwrap().remove(0).bytes;
+        let setup = || {
+            let mut memory = TestMemory::new();
+            memory.memory[..program.len()].copy_from_slice(&program);
+            let mut reg = Registers::new();
+            (reg.pc, reg.sp) = (0, 0xfffe);
+            (reg, memory)
+        };
+        const N: usize = 20_000_000;
+
+        let (mut reg, mut memory) = setup();
+        let mut ime = false;
+        let time = Instant::now();
+        let mut cycles = 0;
+        for _ in 0..N {
+            let (inst, len) = decode::decode(reg.pc, &memory).unwrap();
+            reg.pc += len;
+            cycles += reg.execute(inst, &mut memory, &mut ime).unwrap().0;
+        }
+        let execute = time.elapsed();
+
+        let (mut reg1, mut memory1) = setup();
+        let time = Instant::now();
+        let mut cycles1 = 0;
+        for _ in 0..N {
+            cycles1 += reg1.step(&mut memory1, &mut ime).unwrap().0;
+        }
+        let step = time.elapsed();
+
+        assert_eq!((cycles, &reg), (cycles1, &reg1));
+        let ns = |d: std::time::Duration| d.as_nanos() as f64 / N as f64;
+        println!("decode + execute: {:.2} ns/inst", ns(execute));
+        println!("step:             {:.2} ns/inst", ns(step));
+        println!("speedup:          {:.2}x", ns(execute) / ns(step));
+    }
 }
@@ -1,4 +1,6 @@
-#[derive(Debug, PartialEq)]
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum Inst {
     Ld8(Arg8, Arg8),
     Ld16(Arg16, Arg16),
@@ -86,7 +88,7 @@ pub enum Reg8 {
     L,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Arg16 {
     Reg(Reg16),
     Immed(u16),
@@ -103,7 +105,7 @@ pub enum Reg16 {
     PC,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum JpFlag {
     Nz,
     Z,
@@ -118,3 +120,199 @@ pub enum FlagReg {
     H, // HalfCarry
     C, // Carry
 }
+
+// RGBDS syntax
+impl fmt::Display for Inst {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Inst::Ld8(d @ (Arg8::IndIo(_) | Arg8::IndIoC), s)
+            | Inst::Ld8(d, s @ (Arg8::IndIo(_) | Arg8::IndIoC)) => write!(f, "ldh {}, {}", d, s),
+            Inst::Ld8(d, s) => write!(f, "ld {}, {}", d, s),
+            Inst::Ld16(d, s) => write!(f, "ld {}, {}", d, s),
+            Inst::Push16(rr) => write!(f, "push {}", rr),
+            Inst::Pop16(rr) => write!(f, "pop {}", rr),
+            Inst::Add(d, s) => write!(f, "add {}, {}", d, s),
+            Inst::Adc(d, s) => write!(f, "adc {}, {}", d, s),
+            Inst::Sub(d, s) => write!(f, "sub {}, {}", d, s),
+            Inst::Sbc(d, s) => write!(f, "sbc {}, {}", d, s),
+            Inst::And(d, s) => write!(f, "and {}, {}", d, s),
+            Inst::Xor(d, s) => write!(f, "xor {}, {}", d, s),
+            Inst::Or(d, s) => write!(f, "or {}, {}", d, s),
+            Inst::Cp(d, s) => write!(f, "cp {}, {}", d, s),
+            Inst::Inc(x) => write!(f, "inc {}", x),
+            Inst::Dec(x) => write!(f, "dec {}", x),
+            Inst::Daa => write!(f, "daa"),
+            Inst::Cpl => write!(f, "cpl"),
+            Inst::Add16(d, s) => write!(f, "add {}, {}", d, s),
+            Inst::Inc16(x) => write!(f, "inc {}", x),
+            Inst::Dec16(x) => write!(f, "dec {}", x),
+            Inst::Add16SP(dd) => write!(f, "add sp, {}", dd),
+            Inst::Ld16HLSP(dd) => write!(f, "ld hl, sp{:+}", dd),
+            Inst::Rlca => write!(f, "rlca"),
+            Inst::Rla => write!(f, "rla"),
+            Inst::Rrca => write!(f, "rrca"),
+            Inst::Rra => write!(f, "rra"),
+            Inst::Rlc(x) => write!(f, "rlc {}", x),
+            Inst::Rl(x) => write!(f, "rl {}", x),
+            Inst::Rrc(x) => write!(f, "rrc {}", x),
+            Inst::Rr(x) => write!(f, "rr {}", x),
+            Inst::Sla(x) => write!(f, "sla {}", x),
+            Inst::Swap(x) => write!(f, "swap {}", x),
+            Inst::Sra(x) => write!(f, "sra {}", x),
+            Inst::Srl(x) => write!(f, "srl {}", x),
+            Inst::Bit(n, x) => write!(f, "bit {}, {}", n, x),
+            Inst::Set(n, x) => write!(f, "set {}, {}", n, x),
+            Inst::Res(n, x) => write!(f, "res {}, {}", n, x),
+            Inst::Ccf => write!(f, "ccf"),
+            Inst::Scf => write!(f, "scf"),
+            Inst::Nop => write!(f, "nop"),
+            Inst::Halt => write!(f, "halt"),
+            Inst::Stop => write!(f, "stop"),
+            Inst::Di => write!(f, "di"),
+            Inst::Ei => write!(f, "ei"),
+            Inst::Jp(nn) => write!(f, "jp ${:04x}", nn),
+            Inst::JpHL => write!(f, "jp hl"),
+            Inst::Jpf(cc, nn) => write!(f, "jp {}, ${:04x}", cc, nn),
+            // `@` is the address of the jr itself, the offset is from the next instruction
+            Inst::Jr(dd) => write!(f, "jr @{:+}", *dd as i16 + 2),
+            Inst::Jrf(cc, dd) => write!(f, "jr {}, @{:+}", cc, *dd as i16 + 2),
+            Inst::Call(nn) => write!(f, "call ${:04x}", nn),
+            Inst::Callf(cc, nn) => write!(f, "call {}, ${:04x}", cc, nn),
+            Inst::Ret => write!(f, "ret"),
+            Inst::Retf(cc) => write!(f, "ret {}", cc),
+            Inst::Reti => write!(f, "reti"),
+            Inst::Rst(n) => write!(f, "rst ${:02x}", n),
+        }
+    }
+}
+
+impl fmt::Display for Arg8 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Arg8::Reg(r) => write!(f, "{}", r),
+            Arg8::Immed(n) => write!(f, "${:02x}", n),
+            Arg8::Ind(nn) => write!(f, "[${:04x}]", nn),
+            Arg8::IndReg(rr) => write!(f, "[{}]", rr),
+            Arg8::IndIncHL => write!(f, "[hl+]"),
+            Arg8::IndDecHL => write!(f, "[hl-]"),
+            Arg8::IndIo(n) => write!(f, "[$ff{:02x}]", n),
+            Arg8::IndIoC => write!(f, "[c]"),
+        }
+    }
+}
+
+impl fmt::Display for Reg8 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Reg8::A => "a",
+            Reg8::B => "b",
+            Reg8::C => "c",
+            Reg8::D => "d",
+            Reg8::E => "e",
+            Reg8::H => "h",
+            Reg8::L => "l",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl fmt::Display for Arg16 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Arg16::Reg(rr) => write!(f, "{}", rr),
+            Arg16::Immed(nn) => write!(f, "${:04x}", nn),
+            Arg16::Ind(nn) => write!(f, "[${:04x}]", nn),
+        }
+    }
+}
+
+impl fmt::Display for Reg16 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Reg16::AF => "af",
+            Reg16::BC => "bc",
+            Reg16::DE => "de",
+            Reg16::HL => "hl",
+            Reg16::SP => "sp",
+            Reg16::PC => "pc",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl fmt::Display for JpFlag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            JpFlag::Nz => "nz",
+            JpFlag::Z => "z",
+            JpFlag::Nc => "nc",
+            JpFlag::C => "c",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_inst() {
+        let l = [
+            (Inst::Nop, "nop"),
+            (Inst::Ld8(Arg8::Reg(Reg8::B), Arg8::Reg(Reg8::C)), "ld b, c"),
+            (
+                Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::Immed(0x0f)),
+                "ld a, $0f",
+            ),
+            (
+                Inst::Ld8(Arg8::IndReg(Reg16::HL), Arg8::Reg(Reg8::A)),
+                "ld [hl], a",
+            ),
+            (Inst::Ld8(Arg8::IndIncHL, Arg8::Reg(Reg8::A)), "ld [hl+], a"),
+            (Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::IndDecHL), "ld a, [hl-]"),
+            (
+                Inst::Ld8(Arg8::Ind(0xc000), Arg8::Reg(Reg8::A)),
+                "ld [$c000], a",
+            ),
+            (
+                Inst::Ld8(Arg8::IndIo(0x40), Arg8::Reg(Reg8::A)),
+                "ldh [$ff40], a",
+            ),
+            (Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::IndIoC), "ldh a, [c]"),
+            (
+                Inst::Ld16(Arg16::Reg(Reg16::SP), Arg16::Immed(0xfffe)),
+                "ld sp, $fffe",
+            ),
+            (
+                Inst::Ld16(Arg16::Ind(0xc100), Arg16::Reg(Reg16::SP)),
+                "ld [$c100], sp",
+            ),
+            (Inst::Ld16HLSP(-2), "ld hl, sp-2"),
+            (Inst::Ld16HLSP(3), "ld hl, sp+3"),
+            (Inst::Add16SP(-16), "add sp, -16"),
+            (Inst::Push16(Reg16::AF), "push af"),
+            (
+                Inst::Xor(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::A)),
+                "xor a, a",
+            ),
+            (Inst::Cp(Arg8::Reg(Reg8::A), Arg8::Immed(0x90)), "cp a, $90"),
+            (
+                Inst::Add16(Arg16::Reg(Reg16::HL), Arg16::Reg(Reg16::DE)),
+                "add hl, de",
+            ),
+            (Inst::Bit(7, Arg8::IndReg(Reg16::HL)), "bit 7, [hl]"),
+            (Inst::Jp(0x0150), "jp $0150"),
+            (Inst::Jpf(JpFlag::Nc, 0x4000), "jp nc, $4000"),
+            (Inst::Jr(-2), "jr @+0"),
+            (Inst::Jrf(JpFlag::Z, 5), "jr z, @+7"),
+            (Inst::Callf(JpFlag::Nz, 0x1234), "call nz, $1234"),
+            (Inst::Retf(JpFlag::C), "ret c"),
+            (Inst::Rst(0x38), "rst $38"),
+            (Inst::JpHL, "jp hl"),
+        ];
+        for (i, des) in l {
+            assert_eq!(des, i.to_string());
+        }
+    }
+}
@@ -0,0 +1,765 @@
+//! Opcode tables: one entry per opcode (and per CB-prefixed opcode) with the
+//! mnemonic, the operand layout, the length, the timing, the flags affected,
+//! the handler used by `Registers::step` and the decoded `Inst`.
+//!
+//! Cycles are M-cycles; `cycles_taken` is used when the condition of a
+//! conditional jump / call / return holds. Flags are written `ZNHC`, with
+//! `-` for unchanged and `0` / `1` when always reset / set.
+//! Immediates in `inst` are 0 and filled in by `decode`.
+
+use super::inst::{Arg16, Arg8, Inst, JpFlag, Reg16, Reg8};
+use super::M;
+use Exec::*;
+use Operands::*;
+
+/// Bytes following the opcode
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operands {
+    Implied,
+    Imm8,
+    Imm16,
+    // the next byte is a CB-prefixed opcode
+    Cb,
+}
+
+/// Handler of `Registers::step`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Exec {
+    Nop,
+    LdRrNn,
+    LdIndA,
+    LdAInd,
+    Inc16,
+    Dec16,
+    IncR,
+    DecR,
+    LdRN,
+    Rlca,
+    Rrca,
+    Rla,
+    Rra,
+    LdNnSp,
+    AddHlRr,
+    Stop,
+    Jr,
+    JrCc,
+    Daa,
+    Cpl,
+    Scf,
+    Ccf,
+    LdRR,
+    Halt,
+    AluR,
+    AluN,
+    RetCc,
+    Pop,
+    JpCc,
+    Jp,
+    CallCc,
+    Push,
+    Rst,
+    Ret,
+    Reti,
+    Call,
+    Prefix,
+    LdhNA,
+    LdhAN,
+    LdhCA,
+    LdhAC,
+    AddSpE,
+    JpHl,
+    LdNnA,
+    LdANn,
+    Di,
+    Ei,
+    LdHlSpE,
+    LdSpHl,
+    Invalid,
+    // CB prefix
+    Rot,
+    Bit,
+    Res,
+    Set,
+}
+
+pub struct OpInfo {
+    pub mnemonic: &'static str,
+    pub operands: Operands,
+    pub len: u16,
+    pub cycles: M,
+    pub cycles_taken: M,
+    pub flags: &'static str,
+    pub exec: Exec,
+    pub inst: Option<Inst>,
+}
+
+#[allow(clippy::too_many_arguments)]
+const fn op(
+    mnemonic: &'static str,
+    operands: Operands,
+    len: u16,
+    cycles: M,
+    cycles_taken: M,
+    flags: &'static str,
+    exec: Exec,
+    inst: Option<Inst>,
+) -> OpInfo {
+    OpInfo {
+        mnemonic,
+        operands,
+        len,
+        cycles,
+        cycles_taken,
+        flags,
+        exec,
+        inst,
+    }
+}
+
+pub static OPS: [OpInfo; 256] = OPS_TABLE;
+pub static CB_OPS: [OpInfo; 256] = CB_OPS_TABLE;
+
+/// Handlers by opcode, for building the dispatch tables in constants
+pub const fn exec(op: usize) -> Exec {
+    OPS_TABLE[op].exec
+}
+pub const fn exec_cb(op: usize) -> Exec {
+    CB_OPS_TABLE[op].exec
+}
+
+/// Sets the immediate of an `Inst` from the table.
+pub fn with_immediate(inst: Inst, imm: u16) -> Inst {
+    let n = imm as u8;
+    let arg8 = |x: Arg8| match x {
+        Arg8::Immed(_) => Arg8::Immed(n),
+        Arg8::Ind(_) => Arg8::Ind(imm),
+        Arg8::IndIo(_) => Arg8::IndIo(n),
+        x => x,
+    };
+    let arg16 = |x: Arg16| match x {
+        Arg16::Immed(_) => Arg16::Immed(imm),
+        Arg16::Ind(_) => Arg16::Ind(imm),
+        x => x,
+    };
+    match inst {
+        Inst::Ld8(d, s) => Inst::Ld8(arg8(d), arg8(s)),
+        Inst::Ld16(d, s) => Inst::Ld16(arg16(d), arg16(s)),
+        Inst::Add(a, x) => Inst::Add(a, arg8(x)),
+        Inst::Adc(a, x) => Inst::Adc(a, arg8(x)),
+        Inst::Sub(a, x) => Inst::Sub(a, arg8(x)),
+        Inst::Sbc(a, x) => Inst::Sbc(a, arg8(x)),
+        Inst::And(a, x) => Inst::And(a, arg8(x)),
+        Inst::Xor(a, x) => Inst::Xor(a, arg8(x)),
+        Inst::Or(a, x) => Inst::Or(a, arg8(x)),
+        Inst::Cp(a, x) => Inst::Cp(a, arg8(x)),
+        Inst::Add16SP(_) => Inst::Add16SP(n as i8),
+        Inst::Ld16HLSP(_) => Inst::Ld16HLSP(n as i8),
+        Inst::Jr(_) => Inst::Jr(n as i8),
+        Inst::Jrf(f, _) => Inst::Jrf(f, n as i8),
+        Inst::Jp(_) => Inst::Jp(imm),
+        Inst::Jpf(f, _) => Inst::Jpf(f, imm),
+        Inst::Call(_) => Inst::Call(imm),
+        Inst::Callf(f, _) => Inst::Callf(f, imm),
+        inst => inst,
+    }
+}
+
+#[rustfmt::skip]
+const OPS_TABLE: [OpInfo; 256] = [
+    /* 00 */ op("nop", Implied, 1, 1, 1, "----", Nop, Some(Inst::Nop)),
+    /* 01 */ op("ld bc, n16", Imm16, 3, 3, 3, "----", LdRrNn, Some(Inst::Ld16(Arg16::Reg(Reg16::BC), Arg16::Immed(0)))),
+    /* 02 */ op("ld [bc], a", Implied, 1, 2, 2, "----", LdIndA, Some(Inst::Ld8(Arg8::IndReg(Reg16::BC), Arg8::Reg(Reg8::A)))),
+    /* 03 */ op("inc bc", Implied, 1, 2, 2, "----", Inc16, Some(Inst::Inc16(Arg16::Reg(Reg16::BC)))),
+    /* 04 */ op("inc b", Implied, 1, 1, 1, "Z0H-", IncR, Some(Inst::Inc(Arg8::Reg(Reg8::B)))),
+    /* 05 */ op("dec b", Implied, 1, 1, 1, "Z1H-", DecR, Some(Inst::Dec(Arg8::Reg(Reg8::B)))),
+    /* 06 */ op("ld b, n8", Imm8, 2, 2, 2, "----", LdRN, Some(Inst::Ld8(Arg8::Reg(Reg8::B), Arg8::Immed(0)))),
+    /* 07 */ op("rlca", Implied, 1, 1, 1, "000C", Rlca, Some(Inst::Rlca)),
+    /* 08 */ op("ld [a16], sp", Imm16, 3, 5, 5, "----", LdNnSp, Some(Inst::Ld16(Arg16::Ind(0), Arg16::Reg(Reg16::SP)))),
+    /* 09 */ op("add hl, bc", Implied, 1, 2, 2, "-0HC", AddHlRr, Some(Inst::Add16(Arg16::Reg(Reg16::HL), Arg16::Reg(Reg16::BC)))),
+    /* 0a */ op("ld a, [bc]", Implied, 1, 2, 2, "----", LdAInd, Some(Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::BC)))),
+    /* 0b */ op("dec bc", Implied, 1, 2, 2, "----", Dec16, Some(Inst::Dec16(Arg16::Reg(Reg16::BC)))),
+    /* 0c */ op("inc c", Implied, 1, 1, 1, "Z0H-", IncR, Some(Inst::Inc(Arg8::Reg(Reg8::C)))),
+    /* 0d */ op("dec c", Implied, 1, 1, 1, "Z1H-", DecR, Some(Inst::Dec(Arg8::Reg(Reg8::C)))),
+    /* 0e */ op("ld c, n8", Imm8, 2, 2, 2, "----", LdRN, Some(Inst::Ld8(Arg8::Reg(Reg8::C), Arg8::Immed(0)))),
+    /* 0f */ op("rrca", Implied, 1, 1, 1, "000C", Rrca, Some(Inst::Rrca)),
+    /* 10 */ op("stop", Implied, 1, 1, 1, "----", Stop, Some(Inst::Stop)),
+    /* 11 */ op("ld de, n16", Imm16, 3, 3, 3, "----", LdRrNn, Some(Inst::Ld16(Arg16::Reg(Reg16::DE), Arg16::Immed(0)))),
+    /* 12 */ op("ld [de], a", Implied, 1, 2, 2, "----", LdIndA, Some(Inst::Ld8(Arg8::IndReg(Reg16::DE), Arg8::Reg(Reg8::A)))),
+    /* 13 */ op("inc de", Implied, 1, 2, 2, "----", Inc16, Some(Inst::Inc16(Arg16::Reg(Reg16::DE)))),
+    /* 14 */ op("inc d", Implied, 1, 1, 1, "Z0H-", IncR, Some(Inst::Inc(Arg8::Reg(Reg8::D)))),
+    /* 15 */ op("dec d", Implied, 1, 1, 1, "Z1H-", DecR, Some(Inst::Dec(Arg8::Reg(Reg8::D)))),
+    /* 16 */ op("ld d, n8", Imm8, 2, 2, 2, "----", LdRN, Some(Inst::Ld8(Arg8::Reg(Reg8::D), Arg8::Immed(0)))),
+    /* 17 */ op("rla", Implied, 1, 1, 1, "000C", Rla, Some(Inst::Rla)),
+    /* 18 */ op("jr e8", Imm8, 2, 3, 3, "----", Jr, Some(Inst::Jr(0))),
+    /* 19 */ op("add hl, de", Implied, 1, 2, 2, "-0HC", AddHlRr, Some(Inst::Add16(Arg16::Reg(Reg16::HL), Arg16::Reg(Reg16::DE)))),
+    /* 1a */ op("ld a, [de]", Implied, 1, 2, 2, "----", LdAInd, Some(Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::DE)))),
+    /* 1b */ op("dec de", Implied, 1, 2, 2, "----", Dec16, Some(Inst::Dec16(Arg16::Reg(Reg16::DE)))),
+    /* 1c */ op("inc e", Implied, 1, 1, 1, "Z0H-", IncR, Some(Inst::Inc(Arg8::Reg(Reg8::E)))),
+    /* 1d */ op("dec e", Implied, 1, 1, 1, "Z1H-", DecR, Some(Inst::Dec(Arg8::Reg(Reg8::E)))),
+    /* 1e */ op("ld e, n8", Imm8, 2, 2, 2, "----", LdRN, Some(Inst::Ld8(Arg8::Reg(Reg8::E), Arg8::Immed(0)))),
+    /* 1f */ op("rra", Implied, 1, 1, 1, "000C", Rra, Some(Inst::Rra)),
+    /* 20 */ op("jr nz, e8", Imm8, 2, 2, 3, "----", JrCc, Some(Inst::Jrf(JpFlag::Nz, 0))),
+    /* 21 */ op("ld hl, n16", Imm16, 3, 3, 3, "----", LdRrNn, Some(Inst::Ld16(Arg16::Reg(Reg16::HL), Arg16::Immed(0)))),
+    /* 22 */ op("ld [hl+], a", Implied, 1, 2, 2, "----", LdIndA, Some(Inst::Ld8(Arg8::IndIncHL, Arg8::Reg(Reg8::A)))),
+    /* 23 */ op("inc hl", Implied, 1, 2, 2, "----", Inc16, Some(Inst::Inc16(Arg16::Reg(Reg16::HL)))),
+    /* 24 */ op("inc h", Implied, 1, 1, 1, "Z0H-", IncR, Some(Inst::Inc(Arg8::Reg(Reg8::H)))),
+    /* 25 */ op("dec h", Implied, 1, 1, 1, "Z1H-", DecR, Some(Inst::Dec(Arg8::Reg(Reg8::H)))),
+    /* 26 */ op("ld h, n8", Imm8, 2, 2, 2, "----", LdRN, Some(Inst::Ld8(Arg8::Reg(Reg8::H), Arg8::Immed(0)))),
+    /* 27 */ op("daa", Implied, 1, 1, 1, "Z-0C", Daa, Some(Inst::Daa)),
+    /* 28 */ op("jr z, e8", Imm8, 2, 2, 3, "----", JrCc, Some(Inst::Jrf(JpFlag::Z, 0))),
+    /* 29 */ op("add hl, hl", Implied, 1, 2, 2, "-0HC", AddHlRr, Some(Inst::Add16(Arg16::Reg(Reg16::HL), Arg16::Reg(Reg16::HL)))),
+    /* 2a */ op("ld a, [hl+]", Implied, 1, 2, 2, "----", LdAInd, Some(Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::IndIncHL))),
+    /* 2b */ op("dec hl", Implied, 1, 2, 2, "----", Dec16, Some(Inst::Dec16(Arg16::Reg(Reg16::HL)))),
+    /* 2c */ op("inc l", Implied, 1, 1, 1, "Z0H-", IncR, Some(Inst::Inc(Arg8::Reg(Reg8::L)))),
+    /* 2d */ op("dec l", Implied, 1, 1, 1, "Z1H-", DecR, Some(Inst::Dec(Arg8::Reg(Reg8::L)))),
+    /* 2e */ op("ld l, n8", Imm8, 2, 2, 2, "----", LdRN, Some(Inst::Ld8(Arg8::Reg(Reg8::L), Arg8::Immed(0)))),
+    /* 2f */ op("cpl", Implied, 1, 1, 1, "-11-", Cpl, Some(Inst::Cpl)),
+    /* 30 */ op("jr nc, e8", Imm8, 2, 2, 3, "----", JrCc, Some(Inst::Jrf(JpFlag::Nc, 0))),
+    /* 31 */ op("ld sp, n16", Imm16, 3, 3, 3, "----", LdRrNn, Some(Inst::Ld16(Arg16::Reg(Reg16::SP), Arg16::Immed(0)))),
+    /* 32 */ op("ld [hl-], a", Implied, 1, 2, 2, "----", LdIndA, Some(Inst::Ld8(Arg8::IndDecHL, Arg8::Reg(Reg8::A)))),
+    /* 33 */ op("inc sp", Implied, 1, 2, 2, "----", Inc16, Some(Inst::Inc16(Arg16::Reg(Reg16::SP)))),
+    /* 34 */ op("inc [hl]", Implied, 1, 3, 3, "Z0H-", IncR, Some(Inst::Inc(Arg8::IndReg(Reg16::HL)))),
+    /* 35 */ op("dec [hl]", Implied, 1, 3, 3, "Z1H-", DecR, Some(Inst::Dec(Arg8::IndReg(Reg16::HL)))),
+    /* 36 */ op("ld [hl], n8", Imm8, 2, 3, 3, "----", LdRN, Some(Inst::Ld8(Arg8::IndReg(Reg16::HL), Arg8::Immed(0)))),
+    /* 37 */ op("scf", Implied, 1, 1, 1, "-001", Scf, Some(Inst::Scf)),
+    /* 38 */ op("jr c, e8", Imm8, 2, 2, 3, "----", JrCc, Some(Inst::Jrf(JpFlag::C, 0))),
+    /* 39 */ op("add hl, sp", Implied, 1, 2, 2, "-0HC", AddHlRr, Some(Inst::Add16(Arg16::Reg(Reg16::HL), Arg16::Reg(Reg16::SP)))),
+    /* 3a */ op("ld a, [hl-]", Implied, 1, 2, 2, "----", LdAInd, Some(Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::IndDecHL))),
+    /* 3b */ op("dec sp", Implied, 1, 2, 2, "----", Dec16, Some(Inst::Dec16(Arg16::Reg(Reg16::SP)))),
+    /* 3c */ op("inc a", Implied, 1, 1, 1, "Z0H-", IncR, Some(Inst::Inc(Arg8::Reg(Reg8::A)))),
+    /* 3d */ op("dec a", Implied, 1, 1, 1, "Z1H-", DecR, Some(Inst::Dec(Arg8::Reg(Reg8::A)))),
+    /* 3e */ op("ld a, n8", Imm8, 2, 2, 2, "----", LdRN, Some(Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::Immed(0)))),
+    /* 3f */ op("ccf", Implied, 1, 1, 1, "-00C", Ccf, Some(Inst::Ccf)),
+    /* 40 */ op("ld b, b", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::B), Arg8::Reg(Reg8::B)))),
+    /* 41 */ op("ld b, c", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::B), Arg8::Reg(Reg8::C)))),
+    /* 42 */ op("ld b, d", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::B), Arg8::Reg(Reg8::D)))),
+    /* 43 */ op("ld b, e", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::B), Arg8::Reg(Reg8::E)))),
+    /* 44 */ op("ld b, h", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::B), Arg8::Reg(Reg8::H)))),
+    /* 45 */ op("ld b, l", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::B), Arg8::Reg(Reg8::L)))),
+    /* 46 */ op("ld b, [hl]", Implied, 1, 2, 2, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::B), Arg8::IndReg(Reg16::HL)))),
+    /* 47 */ op("ld b, a", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::B), Arg8::Reg(Reg8::A)))),
+    /* 48 */ op("ld c, b", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::C), Arg8::Reg(Reg8::B)))),
+    /* 49 */ op("ld c, c", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::C), Arg8::Reg(Reg8::C)))),
+    /* 4a */ op("ld c, d", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::C), Arg8::Reg(Reg8::D)))),
+    /* 4b */ op("ld c, e", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::C), Arg8::Reg(Reg8::E)))),
+    /* 4c */ op("ld c, h", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::C), Arg8::Reg(Reg8::H)))),
+    /* 4d */ op("ld c, l", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::C), Arg8::Reg(Reg8::L)))),
+    /* 4e */ op("ld c, [hl]", Implied, 1, 2, 2, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::C), Arg8::IndReg(Reg16::HL)))),
+    /* 4f */ op("ld c, a", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::C), Arg8::Reg(Reg8::A)))),
+    /* 50 */ op("ld d, b", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::D), Arg8::Reg(Reg8::B)))),
+    /* 51 */ op("ld d, c", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::D), Arg8::Reg(Reg8::C)))),
+    /* 52 */ op("ld d, d", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::D), Arg8::Reg(Reg8::D)))),
+    /* 53 */ op("ld d, e", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::D), Arg8::Reg(Reg8::E)))),
+    /* 54 */ op("ld d, h", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::D), Arg8::Reg(Reg8::H)))),
+    /* 55 */ op("ld d, l", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::D), Arg8::Reg(Reg8::L)))),
+    /* 56 */ op("ld d, [hl]", Implied, 1, 2, 2, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::D), Arg8::IndReg(Reg16::HL)))),
+    /* 57 */ op("ld d, a", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::D), Arg8::Reg(Reg8::A)))),
+    /* 58 */ op("ld e, b", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::E), Arg8::Reg(Reg8::B)))),
+    /* 59 */ op("ld e, c", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::E), Arg8::Reg(Reg8::C)))),
+    /* 5a */ op("ld e, d", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::E), Arg8::Reg(Reg8::D)))),
+    /* 5b */ op("ld e, e", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::E), Arg8::Reg(Reg8::E)))),
+    /* 5c */ op("ld e, h", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::E), Arg8::Reg(Reg8::H)))),
+    /* 5d */ op("ld e, l", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::E), Arg8::Reg(Reg8::L)))),
+    /* 5e */ op("ld e, [hl]", Implied, 1, 2, 2, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::E), Arg8::IndReg(Reg16::HL)))),
+    /* 5f */ op("ld e, a", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::E), Arg8::Reg(Reg8::A)))),
+    /* 60 */ op("ld h, b", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::H), Arg8::Reg(Reg8::B)))),
+    /* 61 */ op("ld h, c", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::H), Arg8::Reg(Reg8::C)))),
+    /* 62 */ op("ld h, d", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::H), Arg8::Reg(Reg8::D)))),
+    /* 63 */ op("ld h, e", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::H), Arg8::Reg(Reg8::E)))),
+    /* 64 */ op("ld h, h", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::H), Arg8::Reg(Reg8::H)))),
+    /* 65 */ op("ld h, l", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::H), Arg8::Reg(Reg8::L)))),
+    /* 66 */ op("ld h, [hl]", Implied, 1, 2, 2, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::H), Arg8::IndReg(Reg16::HL)))),
+    /* 67 */ op("ld h, a", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::H), Arg8::Reg(Reg8::A)))),
+    /* 68 */ op("ld l, b", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::L), Arg8::Reg(Reg8::B)))),
+    /* 69 */ op("ld l, c", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::L), Arg8::Reg(Reg8::C)))),
+    /* 6a */ op("ld l, d", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::L), Arg8::Reg(Reg8::D)))),
+    /* 6b */ op("ld l, e", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::L), Arg8::Reg(Reg8::E)))),
+    /* 6c */ op("ld l, h", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::L), Arg8::Reg(Reg8::H)))),
+    /* 6d */ op("ld l, l", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::L), Arg8::Reg(Reg8::L)))),
+    /* 6e */ op("ld l, [hl]", Implied, 1, 2, 2, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::L), Arg8::IndReg(Reg16::HL)))),
+    /* 6f */ op("ld l, a", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::L), Arg8::Reg(Reg8::A)))),
+    /* 70 */ op("ld [hl], b", Implied, 1, 2, 2, "----", LdRR, Some(Inst::Ld8(Arg8::IndReg(Reg16::HL), Arg8::Reg(Reg8::B)))),
+    /* 71 */ op("ld [hl], c", Implied, 1, 2, 2, "----", LdRR, Some(Inst::Ld8(Arg8::IndReg(Reg16::HL), Arg8::Reg(Reg8::C)))),
+    /* 72 */ op("ld [hl], d", Implied, 1, 2, 2, "----", LdRR, Some(Inst::Ld8(Arg8::IndReg(Reg16::HL), Arg8::Reg(Reg8::D)))),
+    /* 73 */ op("ld [hl], e", Implied, 1, 2, 2, "----", LdRR, Some(Inst::Ld8(Arg8::IndReg(Reg16::HL), Arg8::Reg(Reg8::E)))),
+    /* 74 */ op("ld [hl], h", Implied, 1, 2, 2, "----", LdRR, Some(Inst::Ld8(Arg8::IndReg(Reg16::HL), Arg8::Reg(Reg8::H)))),
+    /* 75 */ op("ld [hl], l", Implied, 1, 2, 2, "----", LdRR, Some(Inst::Ld8(Arg8::IndReg(Reg16::HL), Arg8::Reg(Reg8::L)))),
+    /* 76 */ op("halt", Implied, 1, 1, 1, "----", Halt, Some(Inst::Halt)),
+    /* 77 */ op("ld [hl], a", Implied, 1, 2, 2, "----", LdRR, Some(Inst::Ld8(Arg8::IndReg(Reg16::HL), Arg8::Reg(Reg8::A)))),
+    /* 78 */ op("ld a, b", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::B)))),
+    /* 79 */ op("ld a, c", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::C)))),
+    /* 7a */ op("ld a, d", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::D)))),
+    /* 7b */ op("ld a, e", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::E)))),
+    /* 7c */ op("ld a, h", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::H)))),
+    /* 7d */ op("ld a, l", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::L)))),
+    /* 7e */ op("ld a, [hl]", Implied, 1, 2, 2, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::HL)))),
+    /* 7f */ op("ld a, a", Implied, 1, 1, 1, "----", LdRR, Some(Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::A)))),
+    /* 80 */ op("add a, b", Implied, 1, 1, 1, "Z0HC", AluR, Some(Inst::Add(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::B)))),
+    /* 81 */ op("add a, c", Implied, 1, 1, 1, "Z0HC", AluR, Some(Inst::Add(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::C)))),
+    /* 82 */ op("add a, d", Implied, 1, 1, 1, "Z0HC", AluR, Some(Inst::Add(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::D)))),
+    /* 83 */ op("add a, e", Implied, 1, 1, 1, "Z0HC", AluR, Some(Inst::Add(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::E)))),
+    /* 84 */ op("add a, h", Implied, 1, 1, 1, "Z0HC", AluR, Some(Inst::Add(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::H)))),
+    /* 85 */ op("add a, l", Implied, 1, 1, 1, "Z0HC", AluR, Some(Inst::Add(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::L)))),
+    /* 86 */ op("add a, [hl]", Implied, 1, 2, 2, "Z0HC", AluR, Some(Inst::Add(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::HL)))),
+    /* 87 */ op("add a, a", Implied, 1, 1, 1, "Z0HC", AluR, Some(Inst::Add(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::A)))),
+    /* 88 */ op("adc a, b", Implied, 1, 1, 1, "Z0HC", AluR, Some(Inst::Adc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::B)))),
+    /* 89 */ op("adc a, c", Implied, 1, 1, 1, "Z0HC", AluR, Some(Inst::Adc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::C)))),
+    /* 8a */ op("adc a, d", Implied, 1, 1, 1, "Z0HC", AluR, Some(Inst::Adc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::D)))),
+    /* 8b */ op("adc a, e", Implied, 1, 1, 1, "Z0HC", AluR, Some(Inst::Adc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::E)))),
+    /* 8c */ op("adc a, h", Implied, 1, 1, 1, "Z0HC", AluR, Some(Inst::Adc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::H)))),
+    /* 8d */ op("adc a, l", Implied, 1, 1, 1, "Z0HC", AluR, Some(Inst::Adc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::L)))),
+    /* 8e */ op("adc a, [hl]", Implied, 1, 2, 2, "Z0HC", AluR, Some(Inst::Adc(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::HL)))),
+    /* 8f */ op("adc a, a", Implied, 1, 1, 1, "Z0HC", AluR, Some(Inst::Adc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::A)))),
+    /* 90 */ op("sub a, b", Implied, 1, 1, 1, "Z1HC", AluR, Some(Inst::Sub(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::B)))),
+    /* 91 */ op("sub a, c", Implied, 1, 1, 1, "Z1HC", AluR, Some(Inst::Sub(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::C)))),
+    /* 92 */ op("sub a, d", Implied, 1, 1, 1, "Z1HC", AluR, Some(Inst::Sub(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::D)))),
+    /* 93 */ op("sub a, e", Implied, 1, 1, 1, "Z1HC", AluR, Some(Inst::Sub(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::E)))),
+    /* 94 */ op("sub a, h", Implied, 1, 1, 1, "Z1HC", AluR, Some(Inst::Sub(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::H)))),
+    /* 95 */ op("sub a, l", Implied, 1, 1, 1, "Z1HC", AluR, Some(Inst::Sub(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::L)))),
+    /* 96 */ op("sub a, [hl]", Implied, 1, 2, 2, "Z1HC", AluR, Some(Inst::Sub(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::HL)))),
+    /* 97 */ op("sub a, a", Implied, 1, 1, 1, "Z1HC", AluR, Some(Inst::Sub(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::A)))),
+    /* 98 */ op("sbc a, b", Implied, 1, 1, 1, "Z1HC", AluR, Some(Inst::Sbc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::B)))),
+    /* 99 */ op("sbc a, c", Implied, 1, 1, 1, "Z1HC", AluR, Some(Inst::Sbc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::C)))),
+    /* 9a */ op("sbc a, d", Implied, 1, 1, 1, "Z1HC", AluR, Some(Inst::Sbc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::D)))),
+    /* 9b */ op("sbc a, e", Implied, 1, 1, 1, "Z1HC", AluR, Some(Inst::Sbc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::E)))),
+    /* 9c */ op("sbc a, h", Implied, 1, 1, 1, "Z1HC", AluR, Some(Inst::Sbc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::H)))),
+    /* 9d */ op("sbc a, l", Implied, 1, 1, 1, "Z1HC", AluR, Some(Inst::Sbc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::L)))),
+    /* 9e */ op("sbc a, [hl]", Implied, 1, 2, 2, "Z1HC", AluR, Some(Inst::Sbc(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::HL)))),
+    /* 9f */ op("sbc a, a", Implied, 1, 1, 1, "Z1HC", AluR, Some(Inst::Sbc(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::A)))),
+    /* a0 */ op("and a, b", Implied, 1, 1, 1, "Z010", AluR, Some(Inst::And(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::B)))),
+    /* a1 */ op("and a, c", Implied, 1, 1, 1, "Z010", AluR, Some(Inst::And(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::C)))),
+    /* a2 */ op("and a, d", Implied, 1, 1, 1, "Z010", AluR, Some(Inst::And(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::D)))),
+    /* a3 */ op("and a, e", Implied, 1, 1, 1, "Z010", AluR, Some(Inst::And(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::E)))),
+    /* a4 */ op("and a, h", Implied, 1, 1, 1, "Z010", AluR, Some(Inst::And(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::H)))),
+    /* a5 */ op("and a, l", Implied, 1, 1, 1, "Z010", AluR, Some(Inst::And(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::L)))),
+    /* a6 */ op("and a, [hl]", Implied, 1, 2, 2, "Z010", AluR, Some(Inst::And(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::HL)))),
+    /* a7 */ op("and a, a", Implied, 1, 1, 1, "Z010", AluR, Some(Inst::And(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::A)))),
+    /* a8 */ op("xor a, b", Implied, 1, 1, 1, "Z000", AluR, Some(Inst::Xor(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::B)))),
+    /* a9 */ op("xor a, c", Implied, 1, 1, 1, "Z000", AluR, Some(Inst::Xor(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::C)))),
+    /* aa */ op("xor a, d", Implied, 1, 1, 1, "Z000", AluR, Some(Inst::Xor(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::D)))),
+    /* ab */ op("xor a, e", Implied, 1, 1, 1, "Z000", AluR, Some(Inst::Xor(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::E)))),
+    /* ac */ op("xor a, h", Implied, 1, 1, 1, "Z000", AluR, Some(Inst::Xor(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::H)))),
+    /* ad */ op("xor a, l", Implied, 1, 1, 1, "Z000", AluR, Some(Inst::Xor(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::L)))),
+    /* ae */ op("xor a, [hl]", Implied, 1, 2, 2, "Z000", AluR, Some(Inst::Xor(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::HL)))),
+    /* af */ op("xor a, a", Implied, 1, 1, 1, "Z000", AluR, Some(Inst::Xor(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::A)))),
+    /* b0 */ op("or a, b", Implied, 1, 1, 1, "Z000", AluR, Some(Inst::Or(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::B)))),
+    /* b1 */ op("or a, c", Implied, 1, 1, 1, "Z000", AluR, Some(Inst::Or(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::C)))),
+    /* b2 */ op("or a, d", Implied, 1, 1, 1, "Z000", AluR, Some(Inst::Or(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::D)))),
+    /* b3 */ op("or a, e", Implied, 1, 1, 1, "Z000", AluR, Some(Inst::Or(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::E)))),
+    /* b4 */ op("or a, h", Implied, 1, 1, 1, "Z000", AluR, Some(Inst::Or(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::H)))),
+    /* b5 */ op("or a, l", Implied, 1, 1, 1, "Z000", AluR, Some(Inst::Or(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::L)))),
+    /* b6 */ op("or a, [hl]", Implied, 1, 2, 2, "Z000", AluR, Some(Inst::Or(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::HL)))),
+    /* b7 */ op("or a, a", Implied, 1, 1, 1, "Z000", AluR, Some(Inst::Or(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::A)))),
+    /* b8 */ op("cp a, b", Implied, 1, 1, 1, "Z1HC", AluR, Some(Inst::Cp(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::B)))),
+    /* b9 */ op("cp a, c", Implied, 1, 1, 1, "Z1HC", AluR, Some(Inst::Cp(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::C)))),
+    /* ba */ op("cp a, d", Implied, 1, 1, 1, "Z1HC", AluR, Some(Inst::Cp(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::D)))),
+    /* bb */ op("cp a, e", Implied, 1, 1, 1, "Z1HC", AluR, Some(Inst::Cp(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::E)))),
+    /* bc */ op("cp a, h", Implied, 1, 1, 1, "Z1HC", AluR, Some(Inst::Cp(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::H)))),
+    /* bd */ op("cp a, l", Implied, 1, 1, 1, "Z1HC", AluR, Some(Inst::Cp(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::L)))),
+    /* be */ op("cp a, [hl]", Implied, 1, 2, 2, "Z1HC", AluR, Some(Inst::Cp(Arg8::Reg(Reg8::A), Arg8::IndReg(Reg16::HL)))),
+    /* bf */ op("cp a, a", Implied, 1, 1, 1, "Z1HC", AluR, Some(Inst::Cp(Arg8::Reg(Reg8::A), Arg8::Reg(Reg8::A)))),
+    /* c0 */ op("ret nz", Implied, 1, 2, 5, "----", RetCc, Some(Inst::Retf(JpFlag::Nz))),
+    /* c1 */ op("pop bc", Implied, 1, 3, 3, "----", Pop, Some(Inst::Pop16(Reg16::BC))),
+    /* c2 */ op("jp nz, a16", Imm16, 3, 3, 4, "----", JpCc, Some(Inst::Jpf(JpFlag::Nz, 0))),
+    /* c3 */ op("jp a16", Imm16, 3, 4, 4, "----", Jp, Some(Inst::Jp(0))),
+    /* c4 */ op("call nz, a16", Imm16, 3, 3, 6, "----", CallCc, Some(Inst::Callf(JpFlag::Nz, 0))),
+    /* c5 */ op("push bc", Implied, 1, 4, 4, "----", Push, Some(Inst::Push16(Reg16::BC))),
+    /* c6 */ op("add a, n8", Imm8, 2, 2, 2, "Z0HC", AluN, Some(Inst::Add(Arg8::Reg(Reg8::A), Arg8::Immed(0)))),
+    /* c7 */ op("rst $00", Implied, 1, 4, 4, "----", Rst, Some(Inst::Rst(0x00))),
+    /* c8 */ op("ret z", Implied, 1, 2, 5, "----", RetCc, Some(Inst::Retf(JpFlag::Z))),
+    /* c9 */ op("ret", Implied, 1, 4, 4, "----", Ret, Some(Inst::Ret)),
+    /* ca */ op("jp z, a16", Imm16, 3, 3, 4, "----", JpCc, Some(Inst::Jpf(JpFlag::Z, 0))),
+    /* cb */ op("prefix", Cb, 2, 0, 0, "----", Prefix, None),
+    /* cc */ op("call z, a16", Imm16, 3, 3, 6, "----", CallCc, Some(Inst::Callf(JpFlag::Z, 0))),
+    /* cd */ op("call a16", Imm16, 3, 6, 6, "----", Call, Some(Inst::Call(0))),
+    /* ce */ op("adc a, n8", Imm8, 2, 2, 2, "Z0HC", AluN, Some(Inst::Adc(Arg8::Reg(Reg8::A), Arg8::Immed(0)))),
+    /* cf */ op("rst $08", Implied, 1, 4, 4, "----", Rst, Some(Inst::Rst(0x08))),
+    /* d0 */ op("ret nc", Implied, 1, 2, 5, "----", RetCc, Some(Inst::Retf(JpFlag::Nc))),
+    /* d1 */ op("pop de", Implied, 1, 3, 3, "----", Pop, Some(Inst::Pop16(Reg16::DE))),
+    /* d2 */ op("jp nc, a16", Imm16, 3, 3, 4, "----", JpCc, Some(Inst::Jpf(JpFlag::Nc, 0))),
+    /* d3 */ op("-", Implied, 1, 0, 0, "----", Invalid, None),
+    /* d4 */ op("call nc, a16", Imm16, 3, 3, 6, "----", CallCc, Some(Inst::Callf(JpFlag::Nc, 0))),
+    /* d5 */ op("push de", Implied, 1, 4, 4, "----", Push, Some(Inst::Push16(Reg16::DE))),
+    /* d6 */ op("sub a, n8", Imm8, 2, 2, 2, "Z1HC", AluN, Some(Inst::Sub(Arg8::Reg(Reg8::A), Arg8::Immed(0)))),
+    /* d7 */ op("rst $10", Implied, 1, 4, 4, "----", Rst, Some(Inst::Rst(0x10))),
+    /* d8 */ op("ret c", Implied, 1, 2, 5, "----", RetCc, Some(Inst::Retf(JpFlag::C))),
+    /* d9 */ op("reti", Implied, 1, 4, 4, "----", Reti, Some(Inst::Reti)),
+    /* da */ op("jp c, a16", Imm16, 3, 3, 4, "----", JpCc, Some(Inst::Jpf(JpFlag::C, 0))),
+    /* db */ op("-", Implied, 1, 0, 0, "----", Invalid, None),
+    /* dc */ op("call c, a16", Imm16, 3, 3, 6, "----", CallCc, Some(Inst::Callf(JpFlag::C, 0))),
+    /* dd */ op("-", Implied, 1, 0, 0, "----", Invalid, None),
+    /* de */ op("sbc a, n8", Imm8, 2, 2, 2, "Z1HC", AluN, Some(Inst::Sbc(Arg8::Reg(Reg8::A), Arg8::Immed(0)))),
+    /* df */ op("rst $18", Implied, 1, 4, 4, "----", Rst, Some(Inst::Rst(0x18))),
+    /* e0 */ op("ldh [a8], a", Imm8, 2, 3, 3, "----", LdhNA, Some(Inst::Ld8(Arg8::IndIo(0), Arg8::Reg(Reg8::A)))),
+    /* e1 */ op("pop hl", Implied, 1, 3, 3, "----", Pop, Some(Inst::Pop16(Reg16::HL))),
+    /* e2 */ op("ldh [c], a", Implied, 1, 2, 2, "----", LdhCA, Some(Inst::Ld8(Arg8::IndIoC, Arg8::Reg(Reg8::A)))),
+    /* e3 */ op("-", Implied, 1, 0, 0, "----", Invalid, None),
+    /* e4 */ op("-", Implied, 1, 0, 0, "----", Invalid, None),
+    /* e5 */ op("push hl", Implied, 1, 4, 4, "----", Push, Some(Inst::Push16(Reg16::HL))),
+    /* e6 */ op("and a, n8", Imm8, 2, 2, 2, "Z010", AluN, Some(Inst::And(Arg8::Reg(Reg8::A), Arg8::Immed(0)))),
+    /* e7 */ op("rst $20", Implied, 1, 4, 4, "----", Rst, Some(Inst::Rst(0x20))),
+    /* e8 */ op("add sp, e8", Imm8, 2, 4, 4, "00HC", AddSpE, Some(Inst::Add16SP(0))),
+    /* e9 */ op("jp hl", Implied, 1, 1, 1, "----", JpHl, Some(Inst::JpHL)),
+    /* ea */ op("ld [a16], a", Imm16, 3, 4, 4, "----", LdNnA, Some(Inst::Ld8(Arg8::Ind(0), Arg8::Reg(Reg8::A)))),
+    /* eb */ op("-", Implied, 1, 0, 0, "----", Invalid, None),
+    /* ec */ op("-", Implied, 1, 0, 0, "----", Invalid, None),
+    /* ed */ op("-", Implied, 1, 0, 0, "----", Invalid, None),
+    /* ee */ op("xor a, n8", Imm8, 2, 2, 2, "Z000", AluN, Some(Inst::Xor(Arg8::Reg(Reg8::A), Arg8::Immed(0)))),
+    /* ef */ op("rst $28", Implied, 1, 4, 4, "----", Rst, Some(Inst::Rst(0x28))),
+    /* f0 */ op("ldh a, [a8]", Imm8, 2, 3, 3, "----", LdhAN, Some(Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::IndIo(0)))),
+    /* f1 */ op("pop af", Implied, 1, 3, 3, "ZNHC", Pop, Some(Inst::Pop16(Reg16::AF))),
+    /* f2 */ op("ldh a, [c]", Implied, 1, 2, 2, "----", LdhAC, Some(Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::IndIoC))),
+    /* f3 */ op("di", Implied, 1, 1, 1, "----", Di, Some(Inst::Di)),
+    /* f4 */ op("-", Implied, 1, 0, 0, "----", Invalid, None),
+    /* f5 */ op("push af", Implied, 1, 4, 4, "----", Push, Some(Inst::Push16(Reg16::AF))),
+    /* f6 */ op("or a, n8", Imm8, 2, 2, 2, "Z000", AluN, Some(Inst::Or(Arg8::Reg(Reg8::A), Arg8::Immed(0)))),
+    /* f7 */ op("rst $30", Implied, 1, 4, 4, "----", Rst, Some(Inst::Rst(0x30))),
+    /* f8 */ op("ld hl, sp+e8", Imm8, 2, 3, 3, "00HC", LdHlSpE, Some(Inst::Ld16HLSP(0))),
+    /* f9 */ op("ld sp, hl", Implied, 1, 2, 2, "----", LdSpHl, Some(Inst::Ld16(Arg16::Reg(Reg16::SP), Arg16::Reg(Reg16::HL)))),
+    /* fa */ op("ld a, [a16]", Imm16, 3, 4, 4, "----", LdANn, Some(Inst::Ld8(Arg8::Reg(Reg8::A), Arg8::Ind(0)))),
+    /* fb */ op("ei", Implied, 1, 1, 1, "----", Ei, Some(Inst::Ei)),
+    /* fc */ op("-", Implied, 1, 0, 0, "----", Invalid, None),
+    /* fd */ op("-", Implied, 1, 0, 0, "----", Invalid, None),
+    /* fe */ op("cp a, n8", Imm8, 2, 2, 2, "Z1HC", AluN, Some(Inst::Cp(Arg8::Reg(Reg8::A), Arg8::Immed(0)))),
+    /* ff */ op("rst $38", Implied, 1, 4, 4, "----", Rst, Some(Inst::Rst(0x38))),
+];
+
+#[rustfmt::skip]
+const CB_OPS_TABLE: [OpInfo; 256] = [
+    /* 00 */ op("rlc b", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rlc(Arg8::Reg(Reg8::B)))),
+    /* 01 */ op("rlc c", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rlc(Arg8::Reg(Reg8::C)))),
+    /* 02 */ op("rlc d", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rlc(Arg8::Reg(Reg8::D)))),
+    /* 03 */ op("rlc e", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rlc(Arg8::Reg(Reg8::E)))),
+    /* 04 */ op("rlc h", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rlc(Arg8::Reg(Reg8::H)))),
+    /* 05 */ op("rlc l", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rlc(Arg8::Reg(Reg8::L)))),
+    /* 06 */ op("rlc [hl]", Implied, 2, 4, 4, "Z00C", Rot, Some(Inst::Rlc(Arg8::IndReg(Reg16::HL)))),
+    /* 07 */ op("rlc a", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rlc(Arg8::Reg(Reg8::A)))),
+    /* 08 */ op("rrc b", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rrc(Arg8::Reg(Reg8::B)))),
+    /* 09 */ op("rrc c", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rrc(Arg8::Reg(Reg8::C)))),
+    /* 0a */ op("rrc d", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rrc(Arg8::Reg(Reg8::D)))),
+    /* 0b */ op("rrc e", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rrc(Arg8::Reg(Reg8::E)))),
+    /* 0c */ op("rrc h", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rrc(Arg8::Reg(Reg8::H)))),
+    /* 0d */ op("rrc l", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rrc(Arg8::Reg(Reg8::L)))),
+    /* 0e */ op("rrc [hl]", Implied, 2, 4, 4, "Z00C", Rot, Some(Inst::Rrc(Arg8::IndReg(Reg16::HL)))),
+    /* 0f */ op("rrc a", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rrc(Arg8::Reg(Reg8::A)))),
+    /* 10 */ op("rl b", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rl(Arg8::Reg(Reg8::B)))),
+    /* 11 */ op("rl c", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rl(Arg8::Reg(Reg8::C)))),
+    /* 12 */ op("rl d", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rl(Arg8::Reg(Reg8::D)))),
+    /* 13 */ op("rl e", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rl(Arg8::Reg(Reg8::E)))),
+    /* 14 */ op("rl h", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rl(Arg8::Reg(Reg8::H)))),
+    /* 15 */ op("rl l", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rl(Arg8::Reg(Reg8::L)))),
+    /* 16 */ op("rl [hl]", Implied, 2, 4, 4, "Z00C", Rot, Some(Inst::Rl(Arg8::IndReg(Reg16::HL)))),
+    /* 17 */ op("rl a", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rl(Arg8::Reg(Reg8::A)))),
+    /* 18 */ op("rr b", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rr(Arg8::Reg(Reg8::B)))),
+    /* 19 */ op("rr c", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rr(Arg8::Reg(Reg8::C)))),
+    /* 1a */ op("rr d", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rr(Arg8::Reg(Reg8::D)))),
+    /* 1b */ op("rr e", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rr(Arg8::Reg(Reg8::E)))),
+    /* 1c */ op("rr h", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rr(Arg8::Reg(Reg8::H)))),
+    /* 1d */ op("rr l", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rr(Arg8::Reg(Reg8::L)))),
+    /* 1e */ op("rr [hl]", Implied, 2, 4, 4, "Z00C", Rot, Some(Inst::Rr(Arg8::IndReg(Reg16::HL)))),
+    /* 1f */ op("rr a", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Rr(Arg8::Reg(Reg8::A)))),
+    /* 20 */ op("sla b", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Sla(Arg8::Reg(Reg8::B)))),
+    /* 21 */ op("sla c", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Sla(Arg8::Reg(Reg8::C)))),
+    /* 22 */ op("sla d", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Sla(Arg8::Reg(Reg8::D)))),
+    /* 23 */ op("sla e", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Sla(Arg8::Reg(Reg8::E)))),
+    /* 24 */ op("sla h", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Sla(Arg8::Reg(Reg8::H)))),
+    /* 25 */ op("sla l", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Sla(Arg8::Reg(Reg8::L)))),
+    /* 26 */ op("sla [hl]", Implied, 2, 4, 4, "Z00C", Rot, Some(Inst::Sla(Arg8::IndReg(Reg16::HL)))),
+    /* 27 */ op("sla a", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Sla(Arg8::Reg(Reg8::A)))),
+    /* 28 */ op("sra b", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Sra(Arg8::Reg(Reg8::B)))),
+    /* 29 */ op("sra c", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Sra(Arg8::Reg(Reg8::C)))),
+    /* 2a */ op("sra d", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Sra(Arg8::Reg(Reg8::D)))),
+    /* 2b */ op("sra e", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Sra(Arg8::Reg(Reg8::E)))),
+    /* 2c */ op("sra h", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Sra(Arg8::Reg(Reg8::H)))),
+    /* 2d */ op("sra l", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Sra(Arg8::Reg(Reg8::L)))),
+    /* 2e */ op("sra [hl]", Implied, 2, 4, 4, "Z00C", Rot, Some(Inst::Sra(Arg8::IndReg(Reg16::HL)))),
+    /* 2f */ op("sra a", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Sra(Arg8::Reg(Reg8::A)))),
+    /* 30 */ op("swap b", Implied, 2, 2, 2, "Z000", Rot, Some(Inst::Swap(Arg8::Reg(Reg8::B)))),
+    /* 31 */ op("swap c", Implied, 2, 2, 2, "Z000", Rot, Some(Inst::Swap(Arg8::Reg(Reg8::C)))),
+    /* 32 */ op("swap d", Implied, 2, 2, 2, "Z000", Rot, Some(Inst::Swap(Arg8::Reg(Reg8::D)))),
+    /* 33 */ op("swap e", Implied, 2, 2, 2, "Z000", Rot, Some(Inst::Swap(Arg8::Reg(Reg8::E)))),
+    /* 34 */ op("swap h", Implied, 2, 2, 2, "Z000", Rot, Some(Inst::Swap(Arg8::Reg(Reg8::H)))),
+    /* 35 */ op("swap l", Implied, 2, 2, 2, "Z000", Rot, Some(Inst::Swap(Arg8::Reg(Reg8::L)))),
+    /* 36 */ op("swap [hl]", Implied, 2, 4, 4, "Z000", Rot, Some(Inst::Swap(Arg8::IndReg(Reg16::HL)))),
+    /* 37 */ op("swap a", Implied, 2, 2, 2, "Z000", Rot, Some(Inst::Swap(Arg8::Reg(Reg8::A)))),
+    /* 38 */ op("srl b", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Srl(Arg8::Reg(Reg8::B)))),
+    /* 39 */ op("srl c", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Srl(Arg8::Reg(Reg8::C)))),
+    /* 3a */ op("srl d", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Srl(Arg8::Reg(Reg8::D)))),
+    /* 3b */ op("srl e", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Srl(Arg8::Reg(Reg8::E)))),
+    /* 3c */ op("srl h", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Srl(Arg8::Reg(Reg8::H)))),
+    /* 3d */ op("srl l", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Srl(Arg8::Reg(Reg8::L)))),
+    /* 3e */ op("srl [hl]", Implied, 2, 4, 4, "Z00C", Rot, Some(Inst::Srl(Arg8::IndReg(Reg16::HL)))),
+    /* 3f */ op("srl a", Implied, 2, 2, 2, "Z00C", Rot, Some(Inst::Srl(Arg8::Reg(Reg8::A)))),
+    /* 40 */ op("bit 0, b", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(0, Arg8::Reg(Reg8::B)))),
+    /* 41 */ op("bit 0, c", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(0, Arg8::Reg(Reg8::C)))),
+    /* 42 */ op("bit 0, d", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(0, Arg8::Reg(Reg8::D)))),
+    /* 43 */ op("bit 0, e", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(0, Arg8::Reg(Reg8::E)))),
+    /* 44 */ op("bit 0, h", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(0, Arg8::Reg(Reg8::H)))),
+    /* 45 */ op("bit 0, l", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(0, Arg8::Reg(Reg8::L)))),
+    /* 46 */ op("bit 0, [hl]", Implied, 2, 3, 3, "Z01-", Bit, Some(Inst::Bit(0, Arg8::IndReg(Reg16::HL)))),
+    /* 47 */ op("bit 0, a", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(0, Arg8::Reg(Reg8::A)))),
+    /* 48 */ op("bit 1, b", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(1, Arg8::Reg(Reg8::B)))),
+    /* 49 */ op("bit 1, c", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(1, Arg8::Reg(Reg8::C)))),
+    /* 4a */ op("bit 1, d", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(1, Arg8::Reg(Reg8::D)))),
+    /* 4b */ op("bit 1, e", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(1, Arg8::Reg(Reg8::E)))),
+    /* 4c */ op("bit 1, h", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(1, Arg8::Reg(Reg8::H)))),
+    /* 4d */ op("bit 1, l", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(1, Arg8::Reg(Reg8::L)))),
+    /* 4e */ op("bit 1, [hl]", Implied, 2, 3, 3, "Z01-", Bit, Some(Inst::Bit(1, Arg8::IndReg(Reg16::HL)))),
+    /* 4f */ op("bit 1, a", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(1, Arg8::Reg(Reg8::A)))),
+    /* 50 */ op("bit 2, b", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(2, Arg8::Reg(Reg8::B)))),
+    /* 51 */ op("bit 2, c", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(2, Arg8::Reg(Reg8::C)))),
+    /* 52 */ op("bit 2, d", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(2, Arg8::Reg(Reg8::D)))),
+    /* 53 */ op("bit 2, e", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(2, Arg8::Reg(Reg8::E)))),
+    /* 54 */ op("bit 2, h", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(2, Arg8::Reg(Reg8::H)))),
+    /* 55 */ op("bit 2, l", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(2, Arg8::Reg(Reg8::L)))),
+    /* 56 */ op("bit 2, [hl]", Implied, 2, 3, 3, "Z01-", Bit, Some(Inst::Bit(2, Arg8::IndReg(Reg16::HL)))),
+    /* 57 */ op("bit 2, a", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(2, Arg8::Reg(Reg8::A)))),
+    /* 58 */ op("bit 3, b", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(3, Arg8::Reg(Reg8::B)))),
+    /* 59 */ op("bit 3, c", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(3, Arg8::Reg(Reg8::C)))),
+    /* 5a */ op("bit 3, d", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(3, Arg8::Reg(Reg8::D)))),
+    /* 5b */ op("bit 3, e", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(3, Arg8::Reg(Reg8::E)))),
+    /* 5c */ op("bit 3, h", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(3, Arg8::Reg(Reg8::H)))),
+    /* 5d */ op("bit 3, l", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(3, Arg8::Reg(Reg8::L)))),
+    /* 5e */ op("bit 3, [hl]", Implied, 2, 3, 3, "Z01-", Bit, Some(Inst::Bit(3, Arg8::IndReg(Reg16::HL)))),
+    /* 5f */ op("bit 3, a", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(3, Arg8::Reg(Reg8::A)))),
+    /* 60 */ op("bit 4, b", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(4, Arg8::Reg(Reg8::B)))),
+    /* 61 */ op("bit 4, c", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(4, Arg8::Reg(Reg8::C)))),
+    /* 62 */ op("bit 4, d", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(4, Arg8::Reg(Reg8::D)))),
+    /* 63 */ op("bit 4, e", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(4, Arg8::Reg(Reg8::E)))),
+    /* 64 */ op("bit 4, h", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(4, Arg8::Reg(Reg8::H)))),
+    /* 65 */ op("bit 4, l", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(4, Arg8::Reg(Reg8::L)))),
+    /* 66 */ op("bit 4, [hl]", Implied, 2, 3, 3, "Z01-", Bit, Some(Inst::Bit(4, Arg8::IndReg(Reg16::HL)))),
+    /* 67 */ op("bit 4, a", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(4, Arg8::Reg(Reg8::A)))),
+    /* 68 */ op("bit 5, b", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(5, Arg8::Reg(Reg8::B)))),
+    /* 69 */ op("bit 5, c", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(5, Arg8::Reg(Reg8::C)))),
+    /* 6a */ op("bit 5, d", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(5, Arg8::Reg(Reg8::D)))),
+    /* 6b */ op("bit 5, e", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(5, Arg8::Reg(Reg8::E)))),
+    /* 6c */ op("bit 5, h", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(5, Arg8::Reg(Reg8::H)))),
+    /* 6d */ op("bit 5, l", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(5, Arg8::Reg(Reg8::L)))),
+    /* 6e */ op("bit 5, [hl]", Implied, 2, 3, 3, "Z01-", Bit, Some(Inst::Bit(5, Arg8::IndReg(Reg16::HL)))),
+    /* 6f */ op("bit 5, a", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(5, Arg8::Reg(Reg8::A)))),
+    /* 70 */ op("bit 6, b", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(6, Arg8::Reg(Reg8::B)))),
+    /* 71 */ op("bit 6, c", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(6, Arg8::Reg(Reg8::C)))),
+    /* 72 */ op("bit 6, d", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(6, Arg8::Reg(Reg8::D)))),
+    /* 73 */ op("bit 6, e", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(6, Arg8::Reg(Reg8::E)))),
+    /* 74 */ op("bit 6, h", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(6, Arg8::Reg(Reg8::H)))),
+    /* 75 */ op("bit 6, l", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(6, Arg8::Reg(Reg8::L)))),
+    /* 76 */ op("bit 6, [hl]", Implied, 2, 3, 3, "Z01-", Bit, Some(Inst::Bit(6, Arg8::IndReg(Reg16::HL)))),
+    /* 77 */ op("bit 6, a", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(6, Arg8::Reg(Reg8::A)))),
+    /* 78 */ op("bit 7, b", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(7, Arg8::Reg(Reg8::B)))),
+    /* 79 */ op("bit 7, c", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(7, Arg8::Reg(Reg8::C)))),
+    /* 7a */ op("bit 7, d", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(7, Arg8::Reg(Reg8::D)))),
+    /* 7b */ op("bit 7, e", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(7, Arg8::Reg(Reg8::E)))),
+    /* 7c */ op("bit 7, h", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(7, Arg8::Reg(Reg8::H)))),
+    /* 7d */ op("bit 7, l", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(7, Arg8::Reg(Reg8::L)))),
+    /* 7e */ op("bit 7, [hl]", Implied, 2, 3, 3, "Z01-", Bit, Some(Inst::Bit(7, Arg8::IndReg(Reg16::HL)))),
+    /* 7f */ op("bit 7, a", Implied, 2, 2, 2, "Z01-", Bit, Some(Inst::Bit(7, Arg8::Reg(Reg8::A)))),
+    /* 80 */ op("res 0, b", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(0, Arg8::Reg(Reg8::B)))),
+    /* 81 */ op("res 0, c", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(0, Arg8::Reg(Reg8::C)))),
+    /* 82 */ op("res 0, d", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(0, Arg8::Reg(Reg8::D)))),
+    /* 83 */ op("res 0, e", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(0, Arg8::Reg(Reg8::E)))),
+    /* 84 */ op("res 0, h", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(0, Arg8::Reg(Reg8::H)))),
+    /* 85 */ op("res 0, l", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(0, Arg8::Reg(Reg8::L)))),
+    /* 86 */ op("res 0, [hl]", Implied, 2, 4, 4, "----", Res, Some(Inst::Res(0, Arg8::IndReg(Reg16::HL)))),
+    /* 87 */ op("res 0, a", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(0, Arg8::Reg(Reg8::A)))),
+    /* 88 */ op("res 1, b", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(1, Arg8::Reg(Reg8::B)))),
+    /* 89 */ op("res 1, c", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(1, Arg8::Reg(Reg8::C)))),
+    /* 8a */ op("res 1, d", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(1, Arg8::Reg(Reg8::D)))),
+    /* 8b */ op("res 1, e", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(1, Arg8::Reg(Reg8::E)))),
+    /* 8c */ op("res 1, h", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(1, Arg8::Reg(Reg8::H)))),
+    /* 8d */ op("res 1, l", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(1, Arg8::Reg(Reg8::L)))),
+    /* 8e */ op("res 1, [hl]", Implied, 2, 4, 4, "----", Res, Some(Inst::Res(1, Arg8::IndReg(Reg16::HL)))),
+    /* 8f */ op("res 1, a", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(1, Arg8::Reg(Reg8::A)))),
+    /* 90 */ op("res 2, b", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(2, Arg8::Reg(Reg8::B)))),
+    /* 91 */ op("res 2, c", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(2, Arg8::Reg(Reg8::C)))),
+    /* 92 */ op("res 2, d", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(2, Arg8::Reg(Reg8::D)))),
+    /* 93 */ op("res 2, e", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(2, Arg8::Reg(Reg8::E)))),
+    /* 94 */ op("res 2, h", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(2, Arg8::Reg(Reg8::H)))),
+    /* 95 */ op("res 2, l", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(2, Arg8::Reg(Reg8::L)))),
+    /* 96 */ op("res 2, [hl]", Implied, 2, 4, 4, "----", Res, Some(Inst::Res(2, Arg8::IndReg(Reg16::HL)))),
+    /* 97 */ op("res 2, a", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(2, Arg8::Reg(Reg8::A)))),
+    /* 98 */ op("res 3, b", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(3, Arg8::Reg(Reg8::B)))),
+    /* 99 */ op("res 3, c", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(3, Arg8::Reg(Reg8::C)))),
+    /* 9a */ op("res 3, d", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(3, Arg8::Reg(Reg8::D)))),
+    /* 9b */ op("res 3, e", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(3, Arg8::Reg(Reg8::E)))),
+    /* 9c */ op("res 3, h", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(3, Arg8::Reg(Reg8::H)))),
+    /* 9d */ op("res 3, l", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(3, Arg8::Reg(Reg8::L)))),
+    /* 9e */ op("res 3, [hl]", Implied, 2, 4, 4, "----", Res, Some(Inst::Res(3, Arg8::IndReg(Reg16::HL)))),
+    /* 9f */ op("res 3, a", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(3, Arg8::Reg(Reg8::A)))),
+    /* a0 */ op("res 4, b", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(4, Arg8::Reg(Reg8::B)))),
+    /* a1 */ op("res 4, c", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(4, Arg8::Reg(Reg8::C)))),
+    /* a2 */ op("res 4, d", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(4, Arg8::Reg(Reg8::D)))),
+    /* a3 */ op("res 4, e", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(4, Arg8::Reg(Reg8::E)))),
+    /* a4 */ op("res 4, h", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(4, Arg8::Reg(Reg8::H)))),
+    /* a5 */ op("res 4, l", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(4, Arg8::Reg(Reg8::L)))),
+    /* a6 */ op("res 4, [hl]", Implied, 2, 4, 4, "----", Res, Some(Inst::Res(4, Arg8::IndReg(Reg16::HL)))),
+    /* a7 */ op("res 4, a", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(4, Arg8::Reg(Reg8::A)))),
+    /* a8 */ op("res 5, b", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(5, Arg8::Reg(Reg8::B)))),
+    /* a9 */ op("res 5, c", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(5, Arg8::Reg(Reg8::C)))),
+    /* aa */ op("res 5, d", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(5, Arg8::Reg(Reg8::D)))),
+    /* ab */ op("res 5, e", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(5, Arg8::Reg(Reg8::E)))),
+    /* ac */ op("res 5, h", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(5, Arg8::Reg(Reg8::H)))),
+    /* ad */ op("res 5, l", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(5, Arg8::Reg(Reg8::L)))),
+    /* ae */ op("res 5, [hl]", Implied, 2, 4, 4, "----", Res, Some(Inst::Res(5, Arg8::IndReg(Reg16::HL)))),
+    /* af */ op("res 5, a", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(5, Arg8::Reg(Reg8::A)))),
+    /* b0 */ op("res 6, b", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(6, Arg8::Reg(Reg8::B)))),
+    /* b1 */ op("res 6, c", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(6, Arg8::Reg(Reg8::C)))),
+    /* b2 */ op("res 6, d", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(6, Arg8::Reg(Reg8::D)))),
+    /* b3 */ op("res 6, e", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(6, Arg8::Reg(Reg8::E)))),
+    /* b4 */ op("res 6, h", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(6, Arg8::Reg(Reg8::H)))),
+    /* b5 */ op("res 6, l", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(6, Arg8::Reg(Reg8::L)))),
+    /* b6 */ op("res 6, [hl]", Implied, 2, 4, 4, "----", Res, Some(Inst::Res(6, Arg8::IndReg(Reg16::HL)))),
+    /* b7 */ op("res 6, a", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(6, Arg8::Reg(Reg8::A)))),
+    /* b8 */ op("res 7, b", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(7, Arg8::Reg(Reg8::B)))),
+    /* b9 */ op("res 7, c", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(7, Arg8::Reg(Reg8::C)))),
+    /* ba */ op("res 7, d", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(7, Arg8::Reg(Reg8::D)))),
+    /* bb */ op("res 7, e", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(7, Arg8::Reg(Reg8::E)))),
+    /* bc */ op("res 7, h", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(7, Arg8::Reg(Reg8::H)))),
+    /* bd */ op("res 7, l", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(7, Arg8::Reg(Reg8::L)))),
+    /* be */ op("res 7, [hl]", Implied, 2, 4, 4, "----", Res, Some(Inst::Res(7, Arg8::IndReg(Reg16::HL)))),
+    /* bf */ op("res 7, a", Implied, 2, 2, 2, "----", Res, Some(Inst::Res(7, Arg8::Reg(Reg8::A)))),
+    /* c0 */ op("set 0, b", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(0, Arg8::Reg(Reg8::B)))),
+    /* c1 */ op("set 0, c", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(0, Arg8::Reg(Reg8::C)))),
+    /* c2 */ op("set 0, d", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(0, Arg8::Reg(Reg8::D)))),
+    /* c3 */ op("set 0, e", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(0, Arg8::Reg(Reg8::E)))),
+    /* c4 */ op("set 0, h", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(0, Arg8::Reg(Reg8::H)))),
+    /* c5 */ op("set 0, l", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(0, Arg8::Reg(Reg8::L)))),
+    /* c6 */ op("set 0, [hl]", Implied, 2, 4, 4, "----", Set, Some(Inst::Set(0, Arg8::IndReg(Reg16::HL)))),
+    /* c7 */ op("set 0, a", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(0, Arg8::Reg(Reg8::A)))),
+    /* c8 */ op("set 1, b", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(1, Arg8::Reg(Reg8::B)))),
+    /* c9 */ op("set 1, c", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(1, Arg8::Reg(Reg8::C)))),
+    /* ca */ op("set 1, d", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(1, Arg8::Reg(Reg8::D)))),
+    /* cb */ op("set 1, e", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(1, Arg8::Reg(Reg8::E)))),
+    /* cc */ op("set 1, h", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(1, Arg8::Reg(Reg8::H)))),
+    /* cd */ op("set 1, l", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(1, Arg8::Reg(Reg8::L)))),
+    /* ce */ op("set 1, [hl]", Implied, 2, 4, 4, "----", Set, Some(Inst::Set(1, Arg8::IndReg(Reg16::HL)))),
+    /* cf */ op("set 1, a", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(1, Arg8::Reg(Reg8::A)))),
+    /* d0 */ op("set 2, b", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(2, Arg8::Reg(Reg8::B)))),
+    /* d1 */ op("set 2, c", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(2, Arg8::Reg(Reg8::C)))),
+    /* d2 */ op("set 2, d", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(2, Arg8::Reg(Reg8::D)))),
+    /* d3 */ op("set 2, e", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(2, Arg8::Reg(Reg8::E)))),
+    /* d4 */ op("set 2, h", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(2, Arg8::Reg(Reg8::H)))),
+    /* d5 */ op("set 2, l", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(2, Arg8::Reg(Reg8::L)))),
+    /* d6 */ op("set 2, [hl]", Implied, 2, 4, 4, "----", Set, Some(Inst::Set(2, Arg8::IndReg(Reg16::HL)))),
+    /* d7 */ op("set 2, a", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(2, Arg8::Reg(Reg8::A)))),
+    /* d8 */ op("set 3, b", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(3, Arg8::Reg(Reg8::B)))),
+    /* d9 */ op("set 3, c", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(3, Arg8::Reg(Reg8::C)))),
+    /* da */ op("set 3, d", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(3, Arg8::Reg(Reg8::D)))),
+    /* db */ op("set 3, e", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(3, Arg8::Reg(Reg8::E)))),
+    /* dc */ op("set 3, h", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(3, Arg8::Reg(Reg8::H)))),
+    /* dd */ op("set 3, l", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(3, Arg8::Reg(Reg8::L)))),
+    /* de */ op("set 3, [hl]", Implied, 2, 4, 4, "----", Set, Some(Inst::Set(3, Arg8::IndReg(Reg16::HL)))),
+    /* df */ op("set 3, a", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(3, Arg8::Reg(Reg8::A)))),
+    /* e0 */ op("set 4, b", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(4, Arg8::Reg(Reg8::B)))),
+    /* e1 */ op("set 4, c", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(4, Arg8::Reg(Reg8::C)))),
+    /* e2 */ op("set 4, d", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(4, Arg8::Reg(Reg8::D)))),
+    /* e3 */ op("set 4, e", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(4, Arg8::Reg(Reg8::E)))),
+    /* e4 */ op("set 4, h", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(4, Arg8::Reg(Reg8::H)))),
+    /* e5 */ op("set 4, l", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(4, Arg8::Reg(Reg8::L)))),
+    /* e6 */ op("set 4, [hl]", Implied, 2, 4, 4, "----", Set, Some(Inst::Set(4, Arg8::IndReg(Reg16::HL)))),
+    /* e7 */ op("set 4, a", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(4, Arg8::Reg(Reg8::A)))),
+    /* e8 */ op("set 5, b", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(5, Arg8::Reg(Reg8::B)))),
+    /* e9 */ op("set 5, c", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(5, Arg8::Reg(Reg8::C)))),
+    /* ea */ op("set 5, d", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(5, Arg8::Reg(Reg8::D)))),
+    /* eb */ op("set 5, e", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(5, Arg8::Reg(Reg8::E)))),
+    /* ec */ op("set 5, h", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(5, Arg8::Reg(Reg8::H)))),
+    /* ed */ op("set 5, l", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(5, Arg8::Reg(Reg8::L)))),
+    /* ee */ op("set 5, [hl]", Implied, 2, 4, 4, "----", Set, Some(Inst::Set(5, Arg8::IndReg(Reg16::HL)))),
+    /* ef */ op("set 5, a", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(5, Arg8::Reg(Reg8::A)))),
+    /* f0 */ op("set 6, b", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(6, Arg8::Reg(Reg8::B)))),
+    /* f1 */ op("set 6, c", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(6, Arg8::Reg(Reg8::C)))),
+    /* f2 */ op("set 6, d", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(6, Arg8::Reg(Reg8::D)))),
+    /* f3 */ op("set 6, e", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(6, Arg8::Reg(Reg8::E)))),
+    /* f4 */ op("set 6, h", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(6, Arg8::Reg(Reg8::H)))),
+    /* f5 */ op("set 6, l", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(6, Arg8::Reg(Reg8::L)))),
+    /* f6 */ op("set 6, [hl]", Implied, 2, 4, 4, "----", Set, Some(Inst::Set(6, Arg8::IndReg(Reg16::HL)))),
+    /* f7 */ op("set 6, a", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(6, Arg8::Reg(Reg8::A)))),
+    /* f8 */ op("set 7, b", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(7, Arg8::Reg(Reg8::B)))),
+    /* f9 */ op("set 7, c", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(7, Arg8::Reg(Reg8::C)))),
+    /* fa */ op("set 7, d", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(7, Arg8::Reg(Reg8::D)))),
+    /* fb */ op("set 7, e", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(7, Arg8::Reg(Reg8::E)))),
+    /* fc */ op("set 7, h", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(7, Arg8::Reg(Reg8::H)))),
+    /* fd */ op("set 7, l", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(7, Arg8::Reg(Reg8::L)))),
+    /* fe */ op("set 7, [hl]", Implied, 2, 4, 4, "----", Set, Some(Inst::Set(7, Arg8::IndReg(Reg16::HL)))),
+    /* ff */ op("set 7, a", Implied, 2, 2, 2, "----", Set, Some(Inst::Set(7, Arg8::Reg(Reg8::A)))),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_prefix_cb_test() {
+        let l = [
+            (0x00, Inst::Rlc(Arg8::Reg(Reg8::B))),
+            (0x08, Inst::Rrc(Arg8::Reg(Reg8::B))),
+            (0x11, Inst::Rl(Arg8::Reg(Reg8::C))),
+            (0x19, Inst::Rr(Arg8::Reg(Reg8::C))),
+            (0x22, Inst::Sla(Arg8::Reg(Reg8::D))),
+            (0x2a, Inst::Sra(Arg8::Reg(Reg8::D))),
+            (0x33, Inst::Swap(Arg8::Reg(Reg8::E))),
+            (0x3b, Inst::Srl(Arg8::Reg(Reg8::E))),
+            (0x44, Inst::Bit(0, Arg8::Reg(Reg8::H))),
+            (0x4c, Inst::Bit(1, Arg8::Reg(Reg8::H))),
+            (0x55, Inst::Bit(2, Arg8::Reg(Reg8::L))),
+            (0x5d, Inst::Bit(3, Arg8::Reg(Reg8::L))),
+            (0x66, Inst::Bit(4, Arg8::IndReg(Reg16::HL))),
+            (0x6e, Inst::Bit(5, Arg8::IndReg(Reg16::HL))),
+            (0x77, Inst::Bit(6, Arg8::Reg(Reg8::A))),
+            (0x7f, Inst::Bit(7, Arg8::Reg(Reg8::A))),
+            (0x80, Inst::Res(0, Arg8::Reg(Reg8::B))),
+            (0x88, Inst::Res(1, Arg8::Reg(Reg8::B))),
+            (0x91, Inst::Res(2, Arg8::Reg(Reg8::C))),
+            (0x99, Inst::Res(3, Arg8::Reg(Reg8::C))),
+            (0xa2, Inst::Res(4, Arg8::Reg(Reg8::D))),
+            (0xaa, Inst::Res(5, Arg8::Reg(Reg8::D))),
+            (0xb3, Inst::Res(6, Arg8::Reg(Reg8::E))),
+            (0xbb, Inst::Res(7, Arg8::Reg(Reg8::E))),
+            (0xc4, Inst::Set(0, Arg8::Reg(Reg8::H))),
+            (0xcc, Inst::Set(1, Arg8::Reg(Reg8::H))),
+            (0xd5, Inst::Set(2, Arg8::Reg(Reg8::L))),
+            (0xdd, Inst::Set(3, Arg8::Reg(Reg8::L))),
+            (0xe6, Inst::Set(4, Arg8::IndReg(Reg16::HL))),
+            (0xee, Inst::Set(5, Arg8::IndReg(Reg16::HL))),
+            (0xf7, Inst::Set(6, Arg8::Reg(Reg8::A))),
+            (0xff, Inst::Set(7, Arg8::Reg(Reg8::A))),
+        ];
+        for (c, des) in l {
+            let i = CB_OPS[c].inst.clone().unwrap();
+            assert_eq!(des, i);
+        }
+    }
+    #[test]
+    fn mnemonics_match_display() {
+        for (prefix, table) in [("", &OPS), ("cb ", &CB_OPS)] {
+            for (code, info) in table.iter().enumerate() {
+                let inst = match &info.inst {
+                    Some(inst) => inst,
+                    None => continue,
+                };
+                let expected = info
+                    .mnemonic
+                    .replace("jr e8", "jr @+2")
+                    .replace("jr nz, e8", "jr nz, @+2")
+                    .replace("jr z, e8", "jr z, @+2")
+                    .replace("jr nc, e8", "jr nc, @+2")
+                    .replace("jr c, e8", "jr c, @+2")
+                    .replace("sp+e8", "sp+0")
+                    .replace("e8", "0")
+                    .replace("a8", "$ff00")
+                    .replace("n8", "$00")
+                    .replace("n16", "$0000")
+                    .replace("a16", "$0000");
+                assert_eq!(expected, inst.to_string(), "{}{:02x}", prefix, code);
+                let conditional = matches!(info.exec, JrCc | JpCc | CallCc | RetCc);
+                assert_eq!(
+                    conditional,
+                    info.cycles != info.cycles_taken,
+                    "{}{:02x}",
+                    prefix,
+                    code
+                );
+            }
+        }
+    }
+}
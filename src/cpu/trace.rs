@@ -0,0 +1,287 @@
+//! Instruction trace in the gameboy-doctor format:
+//!
+//! `A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,C3,13,02`
+//!
+//! A line is written before each instruction is executed. The cycle count,
+//! the ROM bank and the disassembly can be appended to the line; they come
+//! after the doctor fields so the traces can still be diffed with `cut`.
+//! LY can be appended too, for comparing with logs that contain it.
+
+use super::decode;
+use super::Registers;
+use crate::memory::{MemoryIF, LY};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+#[derive(Default)]
+pub struct TraceOptions {
+    pub cycles: bool,
+    pub bank: bool,
+    pub disasm: bool,
+    pub ly: bool,
+    // inclusive
+    pub pc_range: Option<(u16, u16)>,
+    pub rom_bank: Option<usize>,
+    // start logging when PC reaches this address for the first time
+    pub start_pc: Option<u16>,
+}
+
+enum Output {
+    Writer(Box<dyn Write>),
+    // kept until `take_lines`
+    Lines(Vec<String>),
+}
+
+pub struct Tracer {
+    options: TraceOptions,
+    out: Output,
+    started: bool,
+}
+
+const USAGE: &str = "\
+trace options:
+  --trace=<file>           log every instruction to <file>
+  --trace-cycles           append the T-cycle count
+  --trace-bank             append the ROM bank of PC
+  --trace-disasm           append the disassembled instruction
+  --trace-ly               append LY
+  --trace-pc=<begin>-<end> log only PC in begin..=end
+  --trace-rom-bank=<n>     log only PC in ROM bank n
+  --trace-start=<pc>       log only after PC first reaches pc";
+
+/// Removes the `--trace*` options from `args` and builds a tracer when
+/// `--trace=<file>` is given.
+pub fn parse_args(args: &mut Vec<String>) -> Result<Option<Tracer>, String> {
+    let mut file = None;
+    let mut options = TraceOptions::default();
+    let mut rest = Vec::new();
+    for arg in args.drain(..) {
+        let (key, val) = match arg.split_once('=') {
+            Some((key, val)) => (key, Some(val)),
+            None => (arg.as_str(), None),
+        };
+        let num = |s: &str| {
+            super::disasm::parse_num(s).ok_or(format!("{}: cannot parse {}\n{}", key, s, USAGE))
+        };
+        match (key, val) {
+            ("--trace", Some(val)) => file = Some(val.to_string()),
+            ("--trace-cycles", None) => options.cycles = true,
+            ("--trace-bank", None) => options.bank = true,
+            ("--trace-disasm", None) => options.disasm = true,
+            ("--trace-ly", None) => options.ly = true,
+            ("--trace-pc", Some(val)) => {
+                let (begin, end) = val.split_once('-').ok_or(format!("{}\n{}", arg, USAGE))?;
+                options.pc_range = Some((num(begin)? as u16, num(end)? as u16));
+            }
+            ("--trace-rom-bank", Some(val)) => options.rom_bank = Some(num(val)? as usize),
+            ("--trace-start", Some(val)) => options.start_pc = Some(num(val)? as u16),
+            _ if key.starts_with("--trace") => return Err(format!("{}\n{}", arg, USAGE)),
+            _ => rest.push(arg),
+        }
+    }
+    *args = rest;
+    match file {
+        Some(file) => {
+            let out = File::create(&file).map_err(|e| format!("{}: {}", file, e))?;
+            Ok(Some(Tracer::new(options, Box::new(BufWriter::new(out)))))
+        }
+        None => Ok(None),
+    }
+}
+
+impl Tracer {
+    pub fn new(options: TraceOptions, out: Box<dyn Write>) -> Tracer {
+        let started = options.start_pc.is_none();
+        Tracer {
+            options,
+            out: Output::Writer(out),
+            started,
+        }
+    }
+    /// Keeps the lines in memory, see `take_lines`.
+    pub fn buffered(options: TraceOptions) -> Tracer {
+        let started = options.start_pc.is_none();
+        Tracer {
+            options,
+            out: Output::Lines(Vec::new()),
+            started,
+        }
+    }
+    pub fn take_lines(&mut self) -> Vec<String> {
+        match &mut self.out {
+            Output::Lines(lines) => std::mem::take(lines),
+            Output::Writer(_) => Vec::new(),
+        }
+    }
+
+    pub fn log(
+        &mut self,
+        clock_t: u64,
+        reg: &Registers,
+        memory: &impl MemoryIF,
+    ) -> Result<(), String> {
+        let bank = bank_of(reg.pc, memory);
+        if !self.enabled(reg.pc, bank) {
+            return Ok(());
+        }
+        let line = self.line(clock_t, reg, bank, memory);
+        match &mut self.out {
+            Output::Writer(out) => writeln!(out, "{}", line).map_err(|e| e.to_string()),
+            Output::Lines(lines) => {
+                lines.push(line);
+                Ok(())
+            }
+        }
+    }
+
+    fn enabled(&mut self, pc: u16, bank: Option<usize>) -> bool {
+        if !self.started {
+            if Some(pc) != self.options.start_pc {
+                return false;
+            }
+            self.started = true;
+        }
+        let in_range = self
+            .options
+            .pc_range
+            .is_none_or(|(begin, end)| begin <= pc && pc <= end);
+        let in_bank = self.options.rom_bank.is_none_or(|b| Some(b) == bank);
+        in_range && in_bank
+    }
+
+    fn line(
+        &self,
+        clock_t: u64,
+        reg: &Registers,
+        bank: Option<usize>,
+        memory: &impl MemoryIF,
+    ) -> String {
+        let pc = reg.pc;
+        let mut line = format!(
+            "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} SP:{:04X} PC:{:04X} PCMEM:{:02X},{:02X},{:02X},{:02X}",
+            reg.a,
+            reg.f,
+            reg.b,
+            reg.c,
+            reg.d,
+            reg.e,
+            reg.h,
+            reg.l,
+            reg.sp,
+            pc,
+            memory.read_byte(pc),
+            memory.read_byte(pc.wrapping_add(1)),
+            memory.read_byte(pc.wrapping_add(2)),
+            memory.read_byte(pc.wrapping_add(3)),
+        );
+        if self.options.ly {
+            line += &format!(" LY:{:02X}", memory.read_byte(LY));
+        }
+        if self.options.cycles {
+            line += &format!(" CY:{}", clock_t);
+        }
+        if self.options.bank {
+            match bank {
+                Some(bank) => line += &format!(" BANK:{:02X}", bank),
+                None => line += " BANK:--",
+            }
+        }
+        if self.options.disasm {
+            match decode::decode(pc, memory) {
+                Ok((inst, _)) => line += &format!(" | {}", inst),
+                Err(_) => line += " | ???",
+            }
+        }
+        line
+    }
+}
+
+/// ROM bank mapped at `pc`, `None` outside the ROM.
+pub(super) fn bank_of(pc: u16, memory: &impl MemoryIF) -> Option<usize> {
+    match pc {
+        0x0000..=0x3fff => Some(0),
+        0x4000..=0x7fff => Some(memory.rom_bank()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    struct TestMemory {
+        memory: Vec<u8>,
+    }
+    impl MemoryIF for TestMemory {
+        fn read_byte(&self, addr: u16) -> u8 {
+            self.memory[addr as usize]
+        }
+        fn write_byte(&mut self, addr: u16, val: u8) {
+            self.memory[addr as usize] = val;
+        }
+        fn rom_bank(&self) -> usize {
+            3
+        }
+    }
+
+    fn registers(pc: u16) -> Registers {
+        let mut reg = Registers::new();
+        (reg.a, reg.f, reg.b, reg.c) = (0x01, 0xb0, 0x00, 0x13);
+        (reg.d, reg.e, reg.h, reg.l) = (0x00, 0xd8, 0x01, 0x4d);
+        reg.pc = pc;
+        reg
+    }
+
+    #[test]
+    fn trace_line() {
+        let mut memory = TestMemory {
+            memory: vec![0; 0x10000],
+        };
+        memory.memory[0x4100..0x4104].copy_from_slice(&[0xc3, 0x50, 0x41, 0x00]);
+        let mut tracer = Tracer::new(TraceOptions::default(), Box::new(io::sink()));
+        assert_eq!(
+            "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:4100 PCMEM:C3,50,41,00",
+            tracer.line(8, &registers(0x4100), Some(3), &memory)
+        );
+        tracer.options = TraceOptions {
+            cycles: true,
+            bank: true,
+            disasm: true,
+            ..TraceOptions::default()
+        };
+        assert_eq!(
+            "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:4100 PCMEM:C3,50,41,00 CY:8 BANK:03 | jp $4150",
+            tracer.line(8, &registers(0x4100), bank_of(0x4100, &memory), &memory)
+        );
+        assert_eq!(None, bank_of(0xc000, &memory));
+    }
+    #[test]
+    fn trace_filters() {
+        let options = TraceOptions {
+            pc_range: Some((0x4000, 0x4fff)),
+            rom_bank: Some(2),
+            start_pc: Some(0x0150),
+            ..TraceOptions::default()
+        };
+        let mut tracer = Tracer::new(options, Box::new(io::sink()));
+        assert!(!tracer.enabled(0x4000, Some(2)));
+        assert!(!tracer.enabled(0x0150, Some(0)));
+        assert!(tracer.enabled(0x4000, Some(2)));
+        assert!(!tracer.enabled(0x4000, Some(1)));
+        assert!(!tracer.enabled(0x5000, Some(2)));
+        assert!(!tracer.enabled(0xc000, None));
+    }
+    #[test]
+    fn trace_args() {
+        let mut args: Vec<String> = ["rom.gb", "--trace-cycles", "--trace-pc=$100-$200", "10"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        // no file: no tracer, but the options are removed
+        assert!(parse_args(&mut args).unwrap().is_none());
+        assert_eq!(vec!["rom.gb", "10"], args);
+        let mut args = vec!["--trace-foo".to_string()];
+        assert!(parse_args(&mut args).is_err());
+    }
+}
@@ -0,0 +1,218 @@
+//! Debug windows next to the main one, showing the PPU memory. They are
+//! redrawn when the PPU presents a frame, so they freeze while the
+//! emulation is paused.
+
+use crate::io::draw;
+use crate::ppu::{Ppu, ViewPalette, MAPS_SIZE, OBJS_SIZE};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::Keycode;
+use sdl2::render::{TextureCreator, WindowCanvas};
+use sdl2::video::WindowContext;
+use sdl2::VideoSubsystem;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum View {
+    // all the tiles of VRAM
+    Tiles,
+    // the BG and window tile maps
+    Maps,
+    // the 40 entries of OAM
+    Objs,
+}
+
+impl View {
+    fn name(self) -> &'static str {
+        match self {
+            View::Tiles => "tiles",
+            View::Maps => "maps",
+            View::Objs => "OAM",
+        }
+    }
+    fn pixel_size(self) -> usize {
+        match self {
+            View::Objs => 4,
+            _ => 2,
+        }
+    }
+    fn size(self, ppu: &Ppu) -> (usize, usize) {
+        match self {
+            View::Tiles => ppu.tile_sheet_size(),
+            View::Maps => MAPS_SIZE,
+            View::Objs => OBJS_SIZE,
+        }
+    }
+}
+
+struct ViewWindow {
+    view: View,
+    canvas: WindowCanvas,
+    texture_creator: TextureCreator<WindowContext>,
+    // mouse position, in pixels of the view
+    cursor: Option<(usize, usize)>,
+    palette: ViewPalette,
+    size: (usize, usize),
+}
+
+impl ViewWindow {
+    fn new(video: &VideoSubsystem, view: View) -> Result<ViewWindow, String> {
+        let window = video
+            .window(&format!("rs-gb {}", view.name()), 1, 1)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+        Ok(ViewWindow {
+            view,
+            texture_creator: canvas.texture_creator(),
+            canvas,
+            cursor: None,
+            palette: ViewPalette::Shades,
+            size: (0, 0),
+        })
+    }
+    fn id(&self) -> u32 {
+        self.canvas.window().id()
+    }
+    /// Text of the title bar: what is under the cursor
+    fn title(&self, ppu: &Ppu) -> String {
+        let mut title = format!("rs-gb {}", self.view.name());
+        match self.view {
+            View::Tiles => {
+                title += &format!(" [{}]", self.palette);
+                if let Some(tile) = self.cursor.and_then(|(x, y)| ppu.tile_at(x, y)) {
+                    title += &format!(" {}", tile);
+                }
+            }
+            View::Maps => {
+                if let Some(tile) = self.cursor.and_then(|(x, y)| ppu.map_tile_at(x, y)) {
+                    title += &format!(" {}", tile);
+                }
+            }
+            View::Objs => {
+                if let Some(obj) = self.cursor.and_then(|(x, y)| ppu.obj_at(x, y)) {
+                    title += &format!(" {}", obj);
+                }
+            }
+        }
+        title
+    }
+    fn refresh(&mut self, ppu: &Ppu) {
+        let size = self.view.size(ppu);
+        if size != self.size {
+            self.size = size;
+            self.canvas
+                .window_mut()
+                .set_size(
+                    (size.0 * self.view.pixel_size()) as u32,
+                    (size.1 * self.view.pixel_size()) as u32,
+                )
+                .unwrap();
+        }
+        let pixels = match self.view {
+            View::Tiles => ppu.tile_sheet(self.palette),
+            View::Maps => ppu.tile_maps(),
+            View::Objs => ppu.obj_sheet(),
+        };
+        let title = self.title(ppu);
+        self.canvas.window_mut().set_title(&title).unwrap();
+        draw(
+            &mut self.canvas,
+            &self.texture_creator,
+            &pixels,
+            size.0,
+            size.1,
+            self.view.pixel_size(),
+        );
+    }
+}
+
+/// The open debug windows
+pub struct DebugViews {
+    windows: Vec<ViewWindow>,
+    // something to redraw without waiting for a frame
+    dirty: bool,
+    cgb: bool,
+    // list the objects on the console
+    print_objs: bool,
+}
+
+impl DebugViews {
+    pub fn new() -> DebugViews {
+        DebugViews {
+            windows: Vec::new(),
+            dirty: false,
+            cgb: false,
+            print_objs: false,
+        }
+    }
+    /// Opens the window of `view`, or closes it.
+    pub fn toggle(&mut self, video: &VideoSubsystem, view: View) -> Result<(), String> {
+        match self.windows.iter().position(|w| w.view == view) {
+            Some(i) => {
+                self.windows.remove(i);
+            }
+            None => {
+                self.windows.push(ViewWindow::new(video, view)?);
+                self.dirty = true;
+            }
+        }
+        Ok(())
+    }
+    /// Handles the mouse, the palette key of the tile viewer (P), the
+    /// listing key of the OAM viewer (L) and the close button of the
+    /// debug windows. Returns false for the events of the other windows
+    /// and for the keys left to the emulator.
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        let Some(i) = event
+            .get_window_id()
+            .and_then(|id| self.windows.iter().position(|w| w.id() == id))
+        else {
+            return false;
+        };
+        let window = &mut self.windows[i];
+        match event {
+            Event::MouseMotion { x, y, .. } => {
+                let pos = |p: i32| p.max(0) as usize / window.view.pixel_size();
+                window.cursor = Some((pos(*x), pos(*y)));
+            }
+            Event::Window {
+                win_event: WindowEvent::Leave,
+                ..
+            } => window.cursor = None,
+            Event::Window {
+                win_event: WindowEvent::Close,
+                ..
+            } => {
+                self.windows.remove(i);
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::P),
+                ..
+            } if window.view == View::Tiles => window.palette = window.palette.next(self.cgb),
+            Event::KeyDown {
+                keycode: Some(Keycode::L),
+                ..
+            } if window.view == View::Objs => self.print_objs = true,
+            _ => return false,
+        }
+        self.dirty = true;
+        true
+    }
+    /// Redraws the windows after a frame, or after a change of the cursor
+    /// or of the palette.
+    pub fn refresh(&mut self, ppu: &Ppu, frame: bool) {
+        if self.windows.is_empty() || !(frame || self.dirty) {
+            return;
+        }
+        self.dirty = false;
+        self.cgb = ppu.view_banks() == 2;
+        if self.print_objs {
+            self.print_objs = false;
+            for obj in ppu.objs() {
+                println!("{}", obj);
+            }
+        }
+        for window in &mut self.windows {
+            window.refresh(ppu);
+        }
+    }
+}
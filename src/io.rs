@@ -1,4 +1,7 @@
+use crate::debug_view::{DebugViews, View};
 use crate::memory::{MemoryIF, IF, JOYP};
+use crate::ppu::{DebugToggle, Mark, Ppu};
+use crate::sgb::{SCREEN_X, SCREEN_Y, SGB_SIZE_X, SGB_SIZE_Y};
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use sdl2::pixels::PixelFormatEnum;
@@ -6,17 +9,22 @@ use sdl2::rect::Rect;
 use sdl2::render::TextureCreator;
 use sdl2::render::WindowCanvas;
 use sdl2::video::WindowContext;
-use sdl2::EventPump;
+use sdl2::{EventPump, VideoSubsystem};
+use std::thread;
+use std::time::{Duration, Instant};
 
-const WHITE: (u8, u8, u8) = (0xe0, 0xf8, 0xd0);
-const LIGHT_GRAY: (u8, u8, u8) = (0x88, 0xc0, 0x70);
-const DARK_GRAY: (u8, u8, u8) = (0x34, 0x68, 0x56);
-const BLACK: (u8, u8, u8) = (0x08, 0x18, 0x20);
+const WHITE: Rgb = Rgb(0xe0, 0xf8, 0xd0);
+const LIGHT_GRAY: Rgb = Rgb(0x88, 0xc0, 0x70);
+const DARK_GRAY: Rgb = Rgb(0x34, 0x68, 0x56);
+const BLACK: Rgb = Rgb(0x08, 0x18, 0x20);
 const PIXEL_SIZE: usize = 3;
 const JOYPAD_NUM: usize = 8;
+// SGB multiplayer
+const PLAYERS: usize = 4;
 pub const GFX_SIZE_Y: usize = 144;
 pub const GFX_SIZE_X: usize = 160;
 
+/// DMG shades
 #[derive(Copy, Clone, PartialEq)]
 pub enum GfxColor {
     W,
@@ -24,13 +32,91 @@ pub enum GfxColor {
     DG,
     B,
 }
+
+impl GfxColor {
+    pub fn rgb(self) -> Rgb {
+        match self {
+            GfxColor::W => WHITE,
+            GfxColor::LG => LIGHT_GRAY,
+            GfxColor::DG => DARK_GRAY,
+            GfxColor::B => BLACK,
+        }
+    }
+}
+
+/// Pixel of the framebuffer, 8 bits per channel
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    /// CGB colour: 5 bits per channel, red in the low bits
+    pub fn from_rgb555(color: u16) -> Rgb {
+        let channel = |shift: u16| {
+            let c = ((color >> shift) & 0x1f) as u8;
+            (c << 3) | (c >> 2)
+        };
+        Rgb(channel(0), channel(5), channel(10))
+    }
+}
+
+/// Destination of the pixels produced by the `Ppu`.
+pub trait LcdIF {
+    fn gfx(&mut self) -> &mut [Rgb; GFX_SIZE_X * GFX_SIZE_Y];
+    /// Called once per frame, when the PPU enters VBlank.
+    fn present(&mut self);
+    /// Called instead of `present` on the SGB, with the screen inside its
+    /// border. Only the screen is kept by default.
+    fn present_sgb(&mut self, frame: &[Rgb; SGB_SIZE_X * SGB_SIZE_Y]) {
+        for (y, row) in self.gfx().chunks_exact_mut(GFX_SIZE_X).enumerate() {
+            row.copy_from_slice(&frame[(SCREEN_Y + y) * SGB_SIZE_X + SCREEN_X..][..GFX_SIZE_X]);
+        }
+        self.present();
+    }
+    /// Line `ly` drawn with the layer debug views, the marks go over the
+    /// colours. It replaces the line of `gfx` by default.
+    fn debug_line(&mut self, ly: usize, line: &[Rgb; GFX_SIZE_X], marks: &[Mark; GFX_SIZE_X]) {
+        let gfx = &mut self.gfx()[ly * GFX_SIZE_X..][..GFX_SIZE_X];
+        for ((pixel, color), mark) in gfx.iter_mut().zip(line).zip(marks) {
+            *pixel = mark.apply(*color);
+        }
+    }
+}
+
+/// Framebuffer without a window, for headless runs.
+pub struct Screen {
+    pub gfx: [Rgb; GFX_SIZE_X * GFX_SIZE_Y],
+    pub frames: usize,
+}
+
+impl Screen {
+    pub fn new() -> Screen {
+        Screen {
+            gfx: [WHITE; GFX_SIZE_X * GFX_SIZE_Y],
+            frames: 0,
+        }
+    }
+}
+
+impl LcdIF for Screen {
+    fn gfx(&mut self) -> &mut [Rgb; GFX_SIZE_X * GFX_SIZE_Y] {
+        &mut self.gfx
+    }
+    fn present(&mut self) {
+        self.frames += 1;
+    }
+}
+
 pub enum EmuControl {
     Quit,
     Run,
     Step,
     NextStep,
+    NextPalette,
+    Debug(DebugToggle),
+    // decoded I/O registers in the debugger
+    IoRegs,
 }
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Joypad {
     A,
     B,
@@ -43,16 +129,64 @@ pub enum Joypad {
 }
 pub enum GbKey {
     Emu(EmuControl),
-    GameKeyDown(Joypad),
-    GameKeyUp(Joypad),
+    // player, key
+    GameKeyDown(usize, Joypad),
+    GameKeyUp(usize, Joypad),
+}
+
+/// Keys of the joypads: player 1 on the arrows, players 2-4 (SGB
+/// multiplayer) on IJKL, the keypad and TFGH.
+fn joypad_key(key_code: Keycode) -> Option<(usize, Joypad)> {
+    let key = match key_code {
+        Keycode::Right => (0, Joypad::Right),
+        Keycode::Left => (0, Joypad::Left),
+        Keycode::Up => (0, Joypad::Up),
+        Keycode::Down => (0, Joypad::Down),
+        Keycode::S => (0, Joypad::A),
+        Keycode::A => (0, Joypad::B),
+        Keycode::Return => (0, Joypad::Start),
+        Keycode::Space => (0, Joypad::Select),
+        Keycode::L => (1, Joypad::Right),
+        Keycode::J => (1, Joypad::Left),
+        Keycode::I => (1, Joypad::Up),
+        Keycode::K => (1, Joypad::Down),
+        Keycode::O => (1, Joypad::A),
+        Keycode::U => (1, Joypad::B),
+        Keycode::P => (1, Joypad::Start),
+        Keycode::Y => (1, Joypad::Select),
+        Keycode::Kp6 => (2, Joypad::Right),
+        Keycode::Kp4 => (2, Joypad::Left),
+        Keycode::Kp8 => (2, Joypad::Up),
+        Keycode::Kp2 => (2, Joypad::Down),
+        Keycode::Kp9 => (2, Joypad::A),
+        Keycode::Kp7 => (2, Joypad::B),
+        Keycode::Kp3 => (2, Joypad::Start),
+        Keycode::Kp1 => (2, Joypad::Select),
+        Keycode::H => (3, Joypad::Right),
+        Keycode::F => (3, Joypad::Left),
+        Keycode::T => (3, Joypad::Up),
+        Keycode::G => (3, Joypad::Down),
+        Keycode::R => (3, Joypad::A),
+        Keycode::E => (3, Joypad::B),
+        Keycode::V => (3, Joypad::Start),
+        Keycode::C => (3, Joypad::Select),
+        _ => return None,
+    };
+    Some(key)
 }
 
 pub struct Io {
     canvas: WindowCanvas,
     event_pump: EventPump,
     texture_creator: TextureCreator<WindowContext>,
-    joypad_state: [bool; JOYPAD_NUM],
-    pub gfx: [GfxColor; GFX_SIZE_X * GFX_SIZE_Y],
+    joypad_state: [[bool; JOYPAD_NUM]; PLAYERS],
+    // the window is sized for the SGB border
+    sgb_window: bool,
+    video: VideoSubsystem,
+    views: DebugViews,
+    // a frame was presented since the last refresh of the views
+    frame: bool,
+    pub gfx: [Rgb; GFX_SIZE_X * GFX_SIZE_Y],
 }
 
 impl Io {
@@ -75,7 +209,7 @@ impl Io {
 
         let _texture_creator = _canvas.texture_creator();
 
-        _canvas.set_draw_color(WHITE);
+        _canvas.set_draw_color((WHITE.0, WHITE.1, WHITE.2));
         _canvas.clear();
         _canvas.present();
 
@@ -83,56 +217,19 @@ impl Io {
             canvas: _canvas,
             event_pump: _event_pump,
             texture_creator: _texture_creator,
-            joypad_state: [false; JOYPAD_NUM],
-            gfx: [GfxColor::W; GFX_SIZE_X * GFX_SIZE_Y],
+            joypad_state: [[false; JOYPAD_NUM]; PLAYERS],
+            sgb_window: false,
+            video: video_subsystem,
+            views: DebugViews::new(),
+            frame: false,
+            gfx: [WHITE; GFX_SIZE_X * GFX_SIZE_Y],
         }
     }
-    pub fn present(&mut self) {
-        let mut texture = self
-            .texture_creator
-            .create_texture_streaming(
-                PixelFormatEnum::RGB24,
-                (GFX_SIZE_X * PIXEL_SIZE) as u32,
-                (GFX_SIZE_Y * PIXEL_SIZE) as u32,
-            )
-            .unwrap();
-        texture
-            .with_lock(None, |buffer: &mut [u8], pitch: usize| {
-                for _x in 0..(GFX_SIZE_X * PIXEL_SIZE) {
-                    for _y in 0..(GFX_SIZE_Y * PIXEL_SIZE) {
-                        let x = _x / PIXEL_SIZE;
-                        let y = _y / PIXEL_SIZE;
-                        let (r, g, b) = match self.gfx[y * GFX_SIZE_X + x] {
-                            GfxColor::W => WHITE,
-                            GfxColor::LG => LIGHT_GRAY,
-                            GfxColor::DG => DARK_GRAY,
-                            GfxColor::B => BLACK,
-                        };
-                        let offset = _y * pitch + _x * 3;
-                        buffer[offset] = r;
-                        buffer[offset + 1] = g;
-                        buffer[offset + 2] = b;
-                    }
-                }
-            })
-            .unwrap();
-        self.canvas
-            .copy(
-                &texture,
-                None,
-                Rect::new(
-                    0,
-                    0,
-                    (GFX_SIZE_X * PIXEL_SIZE) as u32,
-                    (GFX_SIZE_Y * PIXEL_SIZE) as u32,
-                ),
-            )
-            .unwrap();
-
-        self.canvas.present();
-    }
     pub fn get_key(&mut self, memory: &mut impl MemoryIF) -> (Option<EmuControl>, bool) {
         for event in self.event_pump.poll_iter() {
+            if self.views.handle_event(&event) {
+                continue;
+            }
             let key = match event {
                 Event::Quit { .. } => Some(GbKey::Emu(EmuControl::Quit)),
                 Event::KeyDown {
@@ -142,44 +239,45 @@ impl Io {
                     Keycode::F5 => Some(GbKey::Emu(EmuControl::Run)),
                     Keycode::F7 => Some(GbKey::Emu(EmuControl::Step)),
                     Keycode::F10 => Some(GbKey::Emu(EmuControl::NextStep)),
-                    Keycode::Right => Some(GbKey::GameKeyDown(Joypad::Right)),
-                    Keycode::Left => Some(GbKey::GameKeyDown(Joypad::Left)),
-                    Keycode::Up => Some(GbKey::GameKeyDown(Joypad::Up)),
-                    Keycode::Down => Some(GbKey::GameKeyDown(Joypad::Down)),
-                    Keycode::S => Some(GbKey::GameKeyDown(Joypad::A)),
-                    Keycode::A => Some(GbKey::GameKeyDown(Joypad::B)),
-                    Keycode::Return => Some(GbKey::GameKeyDown(Joypad::Start)),
-                    Keycode::Space => Some(GbKey::GameKeyDown(Joypad::Select)),
-                    _ => None,
+                    Keycode::F2 => Some(GbKey::Emu(EmuControl::NextPalette)),
+                    Keycode::F8 => Some(GbKey::Emu(EmuControl::IoRegs)),
+                    Keycode::F3 | Keycode::F4 | Keycode::F6 => {
+                        let view = match key_code {
+                            Keycode::F3 => View::Tiles,
+                            Keycode::F4 => View::Maps,
+                            _ => View::Objs,
+                        };
+                        if let Err(e) = self.views.toggle(&self.video, view) {
+                            println!("error {}", e);
+                        }
+                        None
+                    }
+                    Keycode::Num1 => Some(GbKey::Emu(EmuControl::Debug(DebugToggle::Bg))),
+                    Keycode::Num2 => Some(GbKey::Emu(EmuControl::Debug(DebugToggle::Window))),
+                    Keycode::Num3 => Some(GbKey::Emu(EmuControl::Debug(DebugToggle::Obj))),
+                    Keycode::Num4 => Some(GbKey::Emu(EmuControl::Debug(DebugToggle::Highlight))),
+                    Keycode::Num5 => Some(GbKey::Emu(EmuControl::Debug(DebugToggle::ObjBoxes))),
+                    Keycode::Num6 => Some(GbKey::Emu(EmuControl::Debug(DebugToggle::Priority))),
+                    _ => joypad_key(key_code).map(|(p, j)| GbKey::GameKeyDown(p, j)),
                 },
                 Event::KeyUp {
                     keycode: Some(key_code),
                     ..
-                } => match key_code {
-                    Keycode::Right => Some(GbKey::GameKeyUp(Joypad::Right)),
-                    Keycode::Left => Some(GbKey::GameKeyUp(Joypad::Left)),
-                    Keycode::Up => Some(GbKey::GameKeyUp(Joypad::Up)),
-                    Keycode::Down => Some(GbKey::GameKeyUp(Joypad::Down)),
-                    Keycode::S => Some(GbKey::GameKeyUp(Joypad::A)),
-                    Keycode::A => Some(GbKey::GameKeyUp(Joypad::B)),
-                    Keycode::Return => Some(GbKey::GameKeyUp(Joypad::Start)),
-                    Keycode::Space => Some(GbKey::GameKeyUp(Joypad::Select)),
-                    _ => None,
-                },
+                } => joypad_key(key_code).map(|(p, j)| GbKey::GameKeyUp(p, j)),
                 _ => None,
             };
             if let Some(gb_key) = key {
                 match gb_key {
                     GbKey::Emu(emu_control) => return (Some(emu_control), false),
-                    GbKey::GameKeyDown(joypad) => self.joypad_state[joypad as usize] = true,
-                    GbKey::GameKeyUp(joypad) => self.joypad_state[joypad as usize] = false,
+                    GbKey::GameKeyDown(p, joypad) => self.joypad_state[p][joypad as usize] = true,
+                    GbKey::GameKeyUp(p, joypad) => self.joypad_state[p][joypad as usize] = false,
                 }
             }
         }
         //self.set_joypad_state(joypads);
         //dbg!(&joypads);
         let joyp = memory.read_byte(JOYP);
-        let (pressed, joyp_out) = self.set_joypad_input(joyp);
+        let (pressed, joyp_out) = self.set_joypad_input(joyp, memory.joypad_player());
         memory.write_byte(JOYP, joyp_out);
         if pressed {
             let i_flag = memory.read_byte(IF);
@@ -188,34 +286,64 @@ impl Io {
         (None, pressed)
     }
 
-    fn set_joypad_input(&self, joyp: u8) -> (bool, u8) {
+    /// A frame was presented since the last call
+    pub fn take_frame(&mut self) -> bool {
+        std::mem::take(&mut self.frame)
+    }
+    /// Redraws the debug windows after a frame, and after the mouse moved
+    /// over them.
+    pub fn refresh_views(&mut self, ppu: &Ppu, frame: bool) {
+        self.views.refresh(ppu, frame);
+    }
+
+    /// Keys of player 1 held after `wait`, for the CGB boot combos. The
+    /// window is open meanwhile, as during the boot logo.
+    pub fn held_keys(&mut self, wait: Duration) -> Vec<Joypad> {
+        let start = Instant::now();
+        while start.elapsed() < wait {
+            // keeps the keyboard state up to date
+            for _ in self.event_pump.poll_iter() {}
+            thread::sleep(Duration::from_millis(10));
+        }
+        self.event_pump
+            .keyboard_state()
+            .pressed_scancodes()
+            .filter_map(Keycode::from_scancode)
+            .filter_map(joypad_key)
+            .filter(|(player, _)| *player == 0)
+            .map(|(_, key)| key)
+            .collect()
+    }
+
+    fn set_joypad_input(&self, joyp: u8, player: usize) -> (bool, u8) {
+        let joypad_state = &self.joypad_state[player];
         let select_buttons = joyp & 0x20 == 0;
         let select_dpad = joyp & 0x10 == 0;
         let mut joyp_out = (joyp & 0x30) + 0x0f;
         if select_dpad {
-            if self.joypad_state[Joypad::Right as usize] {
+            if joypad_state[Joypad::Right as usize] {
                 joyp_out &= !0x01
             }
-            if self.joypad_state[Joypad::Left as usize] {
+            if joypad_state[Joypad::Left as usize] {
                 joyp_out &= !0x02
             }
-            if self.joypad_state[Joypad::Up as usize] {
+            if joypad_state[Joypad::Up as usize] {
                 joyp_out &= !0x04
             }
-            if self.joypad_state[Joypad::Down as usize] {
+            if joypad_state[Joypad::Down as usize] {
                 joyp_out &= !0x08
             }
         } else if select_buttons {
-            if self.joypad_state[Joypad::A as usize] {
+            if joypad_state[Joypad::A as usize] {
                 joyp_out &= !0x01
             }
-            if self.joypad_state[Joypad::B as usize] {
+            if joypad_state[Joypad::B as usize] {
                 joyp_out &= !0x02
             }
-            if self.joypad_state[Joypad::Select as usize] {
+            if joypad_state[Joypad::Select as usize] {
                 joyp_out &= !0x04
             }
-            if self.joypad_state[Joypad::Start as usize] {
+            if joypad_state[Joypad::Start as usize] {
                 joyp_out &= !0x08
             }
         }
@@ -225,3 +353,89 @@ impl Io {
         (pressed, joyp_out)
     }
 }
+
+impl LcdIF for Io {
+    fn gfx(&mut self) -> &mut [Rgb; GFX_SIZE_X * GFX_SIZE_Y] {
+        &mut self.gfx
+    }
+    fn present(&mut self) {
+        let (canvas, texture_creator) = (&mut self.canvas, &self.texture_creator);
+        draw(
+            canvas,
+            texture_creator,
+            &self.gfx,
+            GFX_SIZE_X,
+            GFX_SIZE_Y,
+            PIXEL_SIZE,
+        );
+        self.frame = true;
+    }
+    fn present_sgb(&mut self, frame: &[Rgb; SGB_SIZE_X * SGB_SIZE_Y]) {
+        if !self.sgb_window {
+            self.sgb_window = true;
+            self.canvas
+                .window_mut()
+                .set_size(
+                    (SGB_SIZE_X * PIXEL_SIZE) as u32,
+                    (SGB_SIZE_Y * PIXEL_SIZE) as u32,
+                )
+                .unwrap();
+        }
+        draw(
+            &mut self.canvas,
+            &self.texture_creator,
+            frame,
+            SGB_SIZE_X,
+            SGB_SIZE_Y,
+            PIXEL_SIZE,
+        );
+        self.frame = true;
+    }
+}
+
+/// Draws `width` x `height` pixels scaled by `pixel_size`.
+pub fn draw(
+    canvas: &mut WindowCanvas,
+    texture_creator: &TextureCreator<WindowContext>,
+    pixels: &[Rgb],
+    width: usize,
+    height: usize,
+    pixel_size: usize,
+) {
+    let mut texture = texture_creator
+        .create_texture_streaming(
+            PixelFormatEnum::RGB24,
+            (width * pixel_size) as u32,
+            (height * pixel_size) as u32,
+        )
+        .unwrap();
+    texture
+        .with_lock(None, |buffer: &mut [u8], pitch: usize| {
+            for _x in 0..(width * pixel_size) {
+                for _y in 0..(height * pixel_size) {
+                    let x = _x / pixel_size;
+                    let y = _y / pixel_size;
+                    let Rgb(r, g, b) = pixels[y * width + x];
+                    let offset = _y * pitch + _x * 3;
+                    buffer[offset] = r;
+                    buffer[offset + 1] = g;
+                    buffer[offset + 2] = b;
+                }
+            }
+        })
+        .unwrap();
+    canvas
+        .copy(
+            &texture,
+            None,
+            Rect::new(
+                0,
+                0,
+                (width * pixel_size) as u32,
+                (height * pixel_size) as u32,
+            ),
+        )
+        .unwrap();
+
+    canvas.present();
+}
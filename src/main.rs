@@ -2,28 +2,108 @@ use cpu::Cpu;
 use io::{EmuControl, Io};
 use mmu::Mmu;
 use ppu::Ppu;
+use serial::Serial;
 use std::env;
+use std::process;
+use std::time::Duration;
 use timer::Timer;
 
 mod cpu;
+mod debug_view;
 mod io;
 mod memory;
 mod mmu;
+mod palette;
 mod ppu;
+mod serial;
+mod sgb;
+mod test_rom;
 mod timer;
+mod trace_diff;
+
+// time to hold a CGB boot combo, as long as the boot logo
+const BOOT_LOGO: Duration = Duration::from_secs(2);
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    let tracer = match cpu::trace::parse_args(&mut args) {
+        Ok(tracer) => tracer,
+        Err(e) => {
+            println!("error {}", e);
+            return;
+        }
+    };
     if args.len() < 2 {
         println!("invalid argumnts");
         return;
     }
+    match args[1].as_str() {
+        "test-roms" => process::exit(test_rom::main(&args[2..])),
+        "asm" => process::exit(cpu::asm::main(&args[2..])),
+        "disasm" => process::exit(cpu::disasm::main(&args[2..])),
+        "trace-diff" => process::exit(trace_diff::main(&args[2..])),
+        _ => (),
+    }
+    let block_cache = args.iter().any(|a| a == "--block-cache");
+    let fast_ppu = args.iter().any(|a| a == "--fast-ppu");
+    let unlimited_sprites = args.iter().any(|a| a == "--unlimited-sprites");
+    args.retain(|a| a != "--block-cache" && a != "--fast-ppu" && a != "--unlimited-sprites");
+    let palette_options = match palette::parse_args(&mut args) {
+        Ok(options) => options,
+        Err(e) => {
+            println!("error {}", e);
+            return;
+        }
+    };
+    let mut model = None;
+    if let Some(i) = args.iter().position(|a| a.starts_with("--model=")) {
+        match memory::Model::parse(&args.remove(i)["--model=".len()..]) {
+            Some(m) => model = Some(m),
+            None => {
+                println!("invalid argumnts: --model=dmg|cgb|sgb");
+                return;
+            }
+        }
+    }
 
     let mut mmu = Mmu::new();
     if let Err(e) = mmu.load(&args[1]) {
         println!("error {}", e);
         return;
     }
+    mmu.set_fast_ppu(fast_ppu);
+    mmu.set_unlimited_objs(unlimited_sprites);
+    // CGB for carts with the CGB flag, else SGB for carts with the SGB flag
+    let model = model.unwrap_or(if mmu.cgb_cart() {
+        memory::Model::Cgb
+    } else if mmu.sgb_cart() {
+        memory::Model::Sgb
+    } else {
+        memory::Model::Dmg
+    });
+    mmu.set_model(model);
+    let mut io = Io::new();
+    // the CGB boot ROM reads the combos for DMG carts only
+    let dmg_on_cgb = model == memory::Model::Cgb && !mmu.cgb_cart();
+    let held_combo = || {
+        if !dmg_on_cgb {
+            return None;
+        }
+        println!("hold a direction, with A or B, to choose the palettes");
+        palette::Combo::from_keys(&io.held_keys(BOOT_LOGO))
+    };
+    let mut palettes = match palette::Palettes::new(palette_options, mmu.rom(), held_combo) {
+        Ok((palettes, initial)) => {
+            if let Some(initial) = initial {
+                mmu.set_dmg_palettes(initial);
+            }
+            palettes
+        }
+        Err(e) => {
+            println!("error {}", e);
+            return;
+        }
+    };
 
     let op_break_addr: Option<u16> = if args.len() >= 3 {
         Some(
@@ -36,19 +116,32 @@ fn main() {
         None
     };
 
-    let mut io = Io::new();
     let mut cpu = Cpu::new();
-    let mut timer = Timer::new();
+    if let Some(tracer) = tracer {
+        cpu.set_tracer(tracer);
+    }
+    cpu.set_model(model);
+    if block_cache {
+        cpu.enable_block_cache();
+    }
 
     println!("{}", cpu);
     mmu.dump(0x100);
 
     let mut f_step = false; // step execution
+    let mut io_regs = false; // decoded I/O registers instead of the memory dump
 
     loop {
         let mut key_pressed;
         loop {
             let (emu_control, _pressed) = io.get_key(&mut mmu);
+            let frame = io.take_frame();
+            io.refresh_views(mmu.ppu(), frame);
+            if io_regs && frame && !f_step {
+                print!("\x1b[1;1H");
+                print!("\x1b[2J");
+                mmu.dump_io_regs();
+            }
             key_pressed = _pressed;
             match emu_control {
                 Some(EmuControl::Quit) => return,
@@ -61,6 +154,22 @@ fn main() {
                     break;
                 }
                 Some(EmuControl::NextStep) => break,
+                Some(EmuControl::NextPalette) if mmu.sgb_mode() => {
+                    println!("no DMG palettes in SGB mode, the SGB colours the screen")
+                }
+                Some(EmuControl::NextPalette) => match palettes.next() {
+                    Ok((name, p)) => {
+                        println!("palette {}", name);
+                        mmu.set_dmg_palettes(p);
+                    }
+                    Err(e) => println!("error {}", e),
+                },
+                Some(EmuControl::Debug(toggle)) => {
+                    let layer_debug = mmu.layer_debug_mut();
+                    layer_debug.toggle(toggle);
+                    println!("{:?}", layer_debug);
+                }
+                Some(EmuControl::IoRegs) => io_regs = !io_regs,
                 _ => (),
             }
             if !f_step {
@@ -81,7 +190,11 @@ fn main() {
             print!("\x1b[1;1H");
             print!("\x1b[2J");
             print!("{}", cpu);
-            mmu.dump(pc);
+            if io_regs {
+                mmu.dump_io_regs();
+            } else {
+                mmu.dump(pc);
+            }
         }
     }
 }
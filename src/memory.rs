@@ -1,6 +1,9 @@
 /// I/O Rregisters
 // Joypad
 pub const JOYP: u16 = 0xff00;
+// Serial transfer
+pub const SB: u16 = 0xff01;
+pub const SC: u16 = 0xff02;
 // Timer and Divider
 pub const DIV: u16 = 0xff04;
 pub const TIMA: u16 = 0xff05;
@@ -19,10 +22,51 @@ pub const OBP0: u16 = 0xff48;
 pub const OBP1: u16 = 0xff49;
 pub const WY: u16 = 0xff4a;
 pub const WX: u16 = 0xff4b;
+// CGB LCD
+pub const VBK: u16 = 0xff4f;
+pub const BCPS: u16 = 0xff68;
+pub const BCPD: u16 = 0xff69;
+pub const OCPS: u16 = 0xff6a;
+pub const OCPD: u16 = 0xff6b;
+pub const OPRI: u16 = 0xff6c;
+// CGB speed switch, VRAM DMA and WRAM bank
+pub const KEY1: u16 = 0xff4d;
+pub const HDMA1: u16 = 0xff51;
+pub const HDMA5: u16 = 0xff55;
+pub const SVBK: u16 = 0xff70;
 /// Interrupt
 pub const IF: u16 = 0xff0f; // Interrupt flag
 pub const IE: u16 = 0xffff; // Interrupt enable
 
+/// Hardware model being emulated
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Model {
+    Dmg,
+    Cgb,
+    // DMG with the SGB features for carts with the SGB flag
+    Sgb,
+}
+
+impl Model {
+    pub fn parse(s: &str) -> Option<Model> {
+        match s {
+            "dmg" => Some(Model::Dmg),
+            "cgb" => Some(Model::Cgb),
+            "sgb" => Some(Model::Sgb),
+            _ => None,
+        }
+    }
+}
+
+/// CPU bus activity that corrupts OAM during mode 2 on DMG
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OamAccess {
+    Write,
+    Read,
+    // read and 16-bit increment / decrement in the same M-cycle
+    ReadIncDec,
+}
+
 pub trait MemoryIF {
     fn read_byte(&self, addr: u16) -> u8;
     fn read_word(&self, addr: u16) -> u16 {
@@ -37,4 +81,25 @@ pub trait MemoryIF {
         self.write_byte(addr, l);
         self.write_byte(addr + 1, h);
     }
+    /// ROM bank mapped at 0x4000..=0x7fff
+    fn rom_bank(&self) -> usize {
+        1
+    }
+    /// Only HRAM and the I/O registers are readable (OAM DMA in progress)
+    fn bus_conflict(&self) -> bool {
+        false
+    }
+    /// The CPU puts `addr` on the bus `cycle` M-cycles after the opcode
+    /// fetch without a plain read or write (16-bit INC/DEC, PUSH, POP, ...).
+    fn oam_bug(&mut self, _addr: u16, _access: OamAccess, _cycle: usize) {}
+    /// The CPU is paused in this M-cycle (CGB VRAM DMA, speed switch)
+    fn cpu_paused(&mut self) -> bool {
+        false
+    }
+    /// STOP was executed: switches the CGB CPU speed when armed by KEY1.
+    fn speed_switch(&mut self) {}
+    /// Joypad read through JOYP, 0-3 in SGB multiplayer
+    fn joypad_player(&self) -> usize {
+        0
+    }
 }
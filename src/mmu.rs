@@ -1,16 +1,29 @@
-use crate::memory::{MemoryIF, DIV, DMA, IF};
-use crate::Io;
+use crate::io::LcdIF;
+use crate::memory::{
+    MemoryIF, Model, OamAccess, BCPS, DMA, HDMA1, HDMA5, IF, JOYP, KEY1, OPRI, SB, SC, SVBK, VBK,
+};
+use crate::palette::{self, LayerPalettes};
+use crate::ppu::LayerDebug;
+use crate::sgb::{self, Sgb};
 use crate::Ppu;
+use crate::Serial;
 use crate::Timer;
 use std::error::Error;
-use std::fs::File;
-use std::io::BufReader;
-use std::io::Read;
+use std::fs;
+use std::ops::{Range, RangeInclusive};
+
+mod io_regs;
 
 const ROM_BANK_BIT_MAX: usize = 7;
 const RAM_BANK_BIT_MAX: usize = 3;
 const ROM_BANK_MAX: usize = 1 << ROM_BANK_BIT_MAX;
 const RAM_BANK_MAX: usize = 1 << RAM_BANK_BIT_MAX;
+// M-cycles between the write to DMA and the first byte transferred
+const OAM_DMA_DELAY_M: usize = 1;
+// CPU M-cycles paused per 16 bytes of VRAM DMA, in single speed
+const VRAM_DMA_BLOCK_M: usize = 8;
+// CPU M-cycles paused by a speed switch
+const SPEED_SWITCH_M: usize = 2050;
 
 pub struct Mmu {
     ram_enable: bool,
@@ -18,14 +31,48 @@ pub struct Mmu {
     ram_bank_bit: Option<usize>,
     rom_bank: usize,
     ram_bank: usize,
-    rom: [u8; 0x4000 * ROM_BANK_MAX],  // Cartridge ROM 32k byte
-    eram: [u8; 0x2000 * RAM_BANK_MAX], // Cargridge (External) RAM 8k byte
-    wram: [u8; 0x2000],                // Working RAM 8k byte
-    ioreg: [u8; 0x0080],               // I/O Registers
-    zram: [u8; 0x0080],                // Zero-page Ram 128 byte
+    rom: Vec<u8>,        // Cartridge ROM 16k byte * ROM_BANK_MAX
+    eram: Vec<u8>,       // Cargridge (External) RAM 8k byte * RAM_BANK_MAX
+    wram: [u8; 0x8000],  // Working RAM 4k byte * 8 banks (1-7 on CGB only)
+    ioreg: [u8; 0x0080], // I/O Registers
+    zram: [u8; 0x0080],  // Zero-page Ram 128 byte
     ppu: Ppu,
     timer: Timer,
-    oam_dma: Option<(usize, u16)>,
+    serial: Serial,
+    oam_dma: Option<OamDma>,
+    // (source, M-cycles left) of a transfer in its start-up delay
+    oam_dma_start: Option<(u16, usize)>,
+    model: Model,
+    // CGB registers
+    svbk: u8,
+    // KEY1 bit 0: the next STOP switches the speed
+    key1: u8,
+    double_speed: bool,
+    // in double speed, the PPU runs every other M-cycle
+    ppu_skip: bool,
+    hdma: Hdma,
+    // M-cycles left when the CPU is paused
+    cpu_pause: usize,
+    // SGB model with an SGB cart
+    sgb: Option<Box<Sgb>>,
+}
+
+/// CGB VRAM DMA (HDMA1-5)
+struct Hdma {
+    src: u16,
+    dst: u16,
+    // HDMA5 bits 0-6: blocks of 16 bytes left - 1
+    len: u8,
+    // HBlank DMA in progress: a block is copied at the start of each HBlank
+    hblank: bool,
+}
+
+/// OAM DMA in progress, one byte per M-cycle
+struct OamDma {
+    src: u16,
+    index: u16,
+    // last byte read from the source, seen by the CPU on the blocked buses
+    byte: u8,
 }
 
 impl Mmu {
@@ -36,104 +83,219 @@ impl Mmu {
             ram_bank_bit: Some(RAM_BANK_BIT_MAX),
             rom_bank: 1,
             ram_bank: 0,
-            rom: [0; 0x4000 * ROM_BANK_MAX],
-            eram: [0; 0x2000 * RAM_BANK_MAX],
-            wram: [0; 0x2000],
+            rom: vec![0; 0x4000 * ROM_BANK_MAX],
+            eram: vec![0; 0x2000 * RAM_BANK_MAX],
+            wram: [0; 0x8000],
             ioreg: [0; 0x0080],
             zram: [0; 0x0080],
             ppu: Ppu::new(),
             timer: Timer::new(),
+            serial: Serial::new(),
             oam_dma: None,
+            oam_dma_start: None,
+            model: Model::Dmg,
+            svbk: 0,
+            key1: 0,
+            double_speed: false,
+            ppu_skip: false,
+            hdma: Hdma {
+                src: 0,
+                dst: 0,
+                len: 0x7f,
+                hblank: false,
+            },
+            cpu_pause: 0,
+            sgb: None,
         }
     }
 
     pub fn load(&mut self, filename: &str) -> Result<(), Box<dyn Error>> {
-        let buf = BufReader::new(File::open(filename)?);
-        for (i, byte_or_error) in buf.bytes().enumerate() {
-            let byte = byte_or_error.unwrap();
-            self.rom[i] = byte;
+        let rom = fs::read(filename)?;
+        self.load_rom(&rom)
+    }
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), Box<dyn Error>> {
+        if rom.len() > self.rom.len() {
+            return Err(format!("rom too large: {} bytes", rom.len()).into());
         }
+        self.rom[..rom.len()].copy_from_slice(rom);
+        self.set_model(self.model);
         Ok(())
     }
+    pub fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+    /// The cartridge header has the SGB flag and the new licensee code.
+    pub fn sgb_cart(&self) -> bool {
+        self.rom[0x0146] == 0x03 && self.rom[0x014b] == 0x33
+    }
+    /// The SGB colours the screen: SGB model and SGB cart.
+    pub fn sgb_mode(&self) -> bool {
+        self.sgb.is_some()
+    }
+    /// The cartridge header has the CGB flag (CGB enhanced or CGB only).
+    pub fn cgb_cart(&self) -> bool {
+        self.rom[0x0143] & 0x80 != 0
+    }
 
+    /// Prints the memory around `addr`, the I/O registers and HRAM.
     pub fn dump(&self, addr: u16) {
-        let addr = addr as usize;
+        self.memory_dump(addr).print();
+    }
+    /// Copy of the memory printed by `dump`
+    pub fn memory_dump(&self, addr: u16) -> MemoryDump {
         let width = 0x0020;
-        let begin = if addr > width { addr - width } else { 0 };
-        let end = if addr <= 0xffff - width {
-            addr + width
-        } else {
-            0xffff
+        let begin = (addr as usize).saturating_sub(width) / 16 * 16;
+        let end = (addr as usize + width).min(0xffff) | 0x0f;
+        let read = |range: RangeInclusive<usize>| -> Vec<u8> {
+            range.map(|a| self.read_byte(a as u16)).collect()
         };
-        // print header
-        print!("     |");
-        for i in 0..16 {
-            print!("{:3x}", i);
+        // HRAM is copied as is, it is read at every instruction by trace-diff
+        let mut high = read(0xff00..=0xff7f);
+        high.extend_from_slice(&self.zram);
+        MemoryDump {
+            addr,
+            begin,
+            around: read(begin..=end),
+            high,
         }
-        println!();
-
-        for row in (begin / 16)..=(end / 16) {
-            let offset = row * 16;
-            print!("{:04x} |", offset);
-            for i in 0..16 {
-                let a = offset + i;
-                if a == addr {
-                    print!("\x1b[7m");
-                    print!(" {:02x}", self.read_byte(a as u16));
-                    print!("\x1b[0m");
-                } else {
-                    print!(" {:02x}", self.read_byte(a as u16));
+    }
+    /// CGB mode is used on CGB for carts with the CGB flag.
+    /// DMG carts on CGB are colourised as by the CGB boot ROM. The SGB
+    /// features are used on SGB for carts with the SGB flag.
+    pub fn set_model(&mut self, model: Model) {
+        self.model = model;
+        self.ppu.set_model(model);
+        self.ppu.set_cgb(self.cgb_mode());
+        self.sgb = (model == Model::Sgb && self.sgb_cart()).then(|| Box::new(Sgb::new()));
+        self.ppu.set_dmg_palettes(match model {
+            Model::Cgb => palette::cgb_compat(&self.rom, palette::GAMES),
+            Model::Sgb if self.sgb.is_some() => LayerPalettes::uniform(sgb::SHADES),
+            _ => LayerPalettes::dmg(),
+        });
+    }
+    /// See `Ppu::set_dmg_palettes`. No effect in SGB mode, where the SGB
+    /// colours the shades.
+    pub fn set_dmg_palettes(&mut self, palettes: LayerPalettes) {
+        if self.sgb.is_none() {
+            self.ppu.set_dmg_palettes(palettes);
+        }
+    }
+    fn cgb_mode(&self) -> bool {
+        self.model == Model::Cgb && self.cgb_cart()
+    }
+    /// See `Ppu::set_unlimited_objs`.
+    pub fn set_unlimited_objs(&mut self, unlimited: bool) {
+        self.ppu.set_unlimited_objs(unlimited);
+    }
+    /// See `Ppu::layer_debug_mut`.
+    pub fn layer_debug_mut(&mut self) -> &mut LayerDebug {
+        self.ppu.layer_debug_mut()
+    }
+    /// The PPU, for the debug views
+    pub fn ppu(&self) -> &Ppu {
+        &self.ppu
+    }
+    /// See `Ppu::set_fast`.
+    pub fn set_fast_ppu(&mut self, fast: bool) {
+        self.ppu.set_fast(fast);
+    }
+    /// Runs the PPU for one CPU M-cycle: 4 dots, 2 in double speed.
+    pub fn run_ppu(&mut self, io: &mut impl LcdIF) -> Result<(), String> {
+        self.ppu_skip = self.double_speed && !self.ppu_skip;
+        if !self.ppu_skip {
+            let mut i_flg = self.read_byte(IF);
+            match &mut self.sgb {
+                Some(sgb) => self.ppu.run(&mut sgb.lcd(io), &mut i_flg)?,
+                None => self.ppu.run(io, &mut i_flg)?,
+            }
+            self.write_byte(IF, i_flg);
+            if self.ppu.take_hblank() && self.hdma.hblank {
+                self.hdma_block();
+                if self.hdma.len == 0 {
+                    self.hdma.hblank = false;
                 }
+                self.hdma.len = self.hdma.len.wrapping_sub(1) & 0x7f;
             }
-            println!();
         }
 
-        println!("I/O registers:");
-        for row in (0xff00 / 16)..=(0xff80 / 16) {
-            let offset = row * 16;
-            print!("{:04x} |", offset);
-            for i in 0..16 {
-                let a = offset + i;
-                print!(" {:02x}", self.read_byte(a as u16));
-            }
-            println!();
+        self.run_oam_dma();
+        Ok(())
+    }
+    /// Copies 16 bytes to VRAM and pauses the CPU meanwhile.
+    fn hdma_block(&mut self) {
+        for _ in 0..16 {
+            // 0xe000.. is mapped to the working RAM, as for the OAM DMA
+            let src = match self.hdma.src {
+                addr @ 0xe000..=0xffff => addr - 0x2000,
+                addr => addr,
+            };
+            let val = self.read_bus(src);
+            self.ppu.write_vram((self.hdma.dst & 0x1fff) as usize, val);
+            self.hdma.src = self.hdma.src.wrapping_add(1);
+            self.hdma.dst = self.hdma.dst.wrapping_add(1);
         }
-        println!("hram:");
-        for row in (0xff80 / 16)..=(0xffff / 16) {
-            let offset = row * 16;
-            print!("{:04x} |", offset);
-            for i in 0..16 {
-                let a = offset + i;
-                print!(" {:02x}", self.read_byte(a as u16));
-            }
-            println!();
+        self.cpu_pause += VRAM_DMA_BLOCK_M << self.double_speed as usize;
+    }
+    fn read_hdma(&self, addr: u16) -> u8 {
+        match addr {
+            HDMA5 if self.hdma.hblank => self.hdma.len,
+            HDMA5 => 0x80 | self.hdma.len,
+            _ => 0xff,
         }
     }
-    pub fn run_ppu(&mut self, io: &mut Io) -> Result<(), String> {
-        let mut i_flg = self.read_byte(IF);
-        self.ppu.run(io, &mut i_flg)?;
-        self.write_byte(IF, i_flg);
-
-        // dma
-        let dma = self.read_byte(DMA);
-        if dma != 0 {
-            let addr = (dma as u16) * 0x0100;
-            self.write_byte(DMA, 0);
-            self.oam_dma = Some((0, addr))
-        } else if let Some((clc, addr)) = self.oam_dma {
-            let clc = clc + 1;
-            if clc >= 160 {
-                for i in 0..0xa0 {
-                    let val = self.read_byte(addr + i);
-                    self.write_byte(0xfe00 + i, val);
+    fn write_hdma(&mut self, addr: u16, val: u8) {
+        let hdma = &mut self.hdma;
+        match addr - HDMA1 {
+            0 => hdma.src = (hdma.src & 0x00ff) | (val as u16) << 8,
+            1 => hdma.src = (hdma.src & 0xff00) | (val & 0xf0) as u16,
+            2 => hdma.dst = (hdma.dst & 0x00ff) | ((val & 0x1f) as u16) << 8,
+            3 => hdma.dst = (hdma.dst & 0xff00) | (val & 0xf0) as u16,
+            _ if hdma.hblank && val & 0x80 == 0 => {
+                // stops the HBlank DMA, the length is kept
+                hdma.hblank = false;
+            }
+            _ if val & 0x80 != 0 => {
+                hdma.len = val & 0x7f;
+                hdma.hblank = true;
+            }
+            _ => {
+                // general purpose DMA: all the blocks at once
+                for _ in 0..=val & 0x7f {
+                    self.hdma_block();
                 }
-                self.oam_dma = None;
-            } else {
-                self.oam_dma = Some((clc, addr));
+                self.hdma.len = 0x7f;
             }
         }
-
-        Ok(())
+    }
+    fn run_oam_dma(&mut self) {
+        if let Some(dma) = &self.oam_dma {
+            // 0xe000.. is mapped to the working RAM
+            let src = match dma.src + dma.index {
+                addr @ 0xe000..=0xffff => addr - 0x2000,
+                addr => addr,
+            };
+            let byte = self.read_bus(src);
+            self.ppu.write_oam_dma(dma.index as usize, byte);
+            let index = dma.index + 1;
+            self.oam_dma = (index < 0xa0).then_some(OamDma {
+                src: dma.src,
+                index,
+                byte,
+            });
+        }
+        // a new transfer replaces the current one after the start-up delay
+        match self.oam_dma_start {
+            Some((src, 0)) => {
+                self.oam_dma_start = None;
+                self.oam_dma = Some(OamDma {
+                    src,
+                    index: 0,
+                    byte: 0xff,
+                });
+            }
+            Some((src, delay)) => self.oam_dma_start = Some((src, delay - 1)),
+            None => (),
+        }
     }
     pub fn run_timer(&mut self, stop: bool) -> Result<(), String> {
         let mut i_flg = self.read_byte(IF);
@@ -141,10 +303,72 @@ impl Mmu {
         self.write_byte(IF, i_flg);
         Ok(())
     }
+    pub fn serial_output(&self) -> &[u8] {
+        self.serial.output()
+    }
 }
 
 impl MemoryIF for Mmu {
     fn read_byte(&self, addr: u16) -> u8 {
+        match &self.oam_dma {
+            // the CPU can only use HRAM and the I/O registers during OAM DMA
+            Some(_) if (0xfe00..=0xfeff).contains(&addr) => 0xff,
+            Some(dma) if addr < 0xff00 => dma.byte,
+            _ => self.read_bus(addr),
+        }
+    }
+    fn write_byte(&mut self, addr: u16, val: u8) {
+        if self.oam_dma.is_none() || addr >= 0xff00 {
+            self.write_bus(addr, val);
+        }
+    }
+    fn rom_bank(&self) -> usize {
+        self.rom_bank
+    }
+    fn bus_conflict(&self) -> bool {
+        self.oam_dma.is_some()
+    }
+    fn cpu_paused(&mut self) -> bool {
+        if self.cpu_pause > 0 {
+            self.cpu_pause -= 1;
+            true
+        } else {
+            false
+        }
+    }
+    fn speed_switch(&mut self) {
+        if self.cgb_mode() && self.key1 & 0x01 != 0 {
+            self.key1 = 0;
+            self.double_speed = !self.double_speed;
+            self.timer.write_timer_reg(0, 0);
+            self.cpu_pause += SPEED_SWITCH_M;
+        }
+    }
+    fn joypad_player(&self) -> usize {
+        self.sgb.as_ref().map_or(0, |sgb| sgb.player())
+    }
+    fn oam_bug(&mut self, addr: u16, access: OamAccess, cycle: usize) {
+        if self.model != Model::Cgb && (0xfe00..=0xfeff).contains(&addr) {
+            self.ppu.oam_bug(access, cycle);
+        }
+    }
+}
+
+impl Mmu {
+    fn lcd_reg_written(&mut self, addr: u16, val: u8) {
+        let index = (addr - 0xff40) as usize;
+        let i_flg = &mut self.ioreg[(IF - 0xff00) as usize];
+        self.ppu.write_lcd_reg(index, val, i_flg);
+    }
+    /// Index in `wram` of 0xc000..=0xdfff, 0xd000.. is the bank selected by
+    /// SVBK (0 selects 1).
+    fn wram_index(&self, addr: u16) -> usize {
+        match addr {
+            0xc000..=0xcfff => (addr - 0xc000) as usize,
+            _ => (self.svbk.max(1) as usize) * 0x1000 + (addr - 0xd000) as usize,
+        }
+    }
+    fn read_bus(&self, addr: u16) -> u8 {
         match addr {
             // BIOS / ROM0
             //0x0000..=0x3fff => {
@@ -179,15 +403,9 @@ impl MemoryIF for Mmu {
                 }
             }
             // Working RAM 8k
-            0xc000..=0xdfff => {
-                let index = (addr - 0xc000) as usize;
-                self.wram[index]
-            }
+            0xc000..=0xdfff => self.wram[self.wram_index(addr)],
             // Working RAM (shadow)
-            0xe000..=0xfdff => {
-                let index = (addr - 0xe000) as usize;
-                self.wram[index]
-            }
+            0xe000..=0xfdff => self.wram[self.wram_index(addr - 0x2000)],
             // Graphics: sprite information
             0xfe00..=0xfe9f => {
                 let index = (addr - 0xfe00) as usize;
@@ -197,6 +415,17 @@ impl MemoryIF for Mmu {
             0xfea0..=0xfeff => panic!("not usable"),
             // I/O Register
             0xff00..=0xff7f => match addr {
+                // both lines high: the ID of the SGB joypad
+                JOYP => match &self.sgb {
+                    Some(sgb) if self.ioreg[0] & 0x30 == 0x30 => {
+                        self.ioreg[0] & 0xf0 | sgb.joypad_id()
+                    }
+                    _ => self.ioreg[0],
+                },
+                SB..=SC => {
+                    let index = (addr - SB) as usize;
+                    self.serial.read_serial_reg(index)
+                }
                 0xff04..=0xff07 => {
                     let index = (addr - 0xff04) as usize;
                     self.timer.read_timer_reg(index)
@@ -205,6 +434,10 @@ impl MemoryIF for Mmu {
                     let index = (addr - 0xff40) as usize;
                     self.ppu.read_lcd_reg(index)
                 }
+                VBK | BCPS..=OPRI => self.ppu.read_cgb_reg(addr),
+                KEY1 if self.cgb_mode() => (self.double_speed as u8) << 7 | self.key1 | 0x7e,
+                HDMA1..=HDMA5 if self.cgb_mode() => self.read_hdma(addr),
+                SVBK if self.cgb_mode() => self.svbk | 0xf8,
                 _ => {
                     let index = (addr - 0xff00) as usize;
                     self.ioreg[index]
@@ -217,7 +450,7 @@ impl MemoryIF for Mmu {
             }
         }
     }
-    fn write_byte(&mut self, addr: u16, val: u8) {
+    fn write_bus(&mut self, addr: u16, val: u8) {
         match addr {
             // ROM1 (unbanked) 16k
             // 0x0000..=0x7fff => {
@@ -264,7 +497,7 @@ impl MemoryIF for Mmu {
             }
             // Working RAM 8k
             0xc000..=0xdfff => {
-                let index = (addr - 0xc000) as usize;
+                let index = self.wram_index(addr);
                 self.wram[index] = val;
             }
             // Working RAM (shadow)
@@ -282,14 +515,33 @@ impl MemoryIF for Mmu {
             0xfea0..=0xfeff => (), //panic!("not usable"),
             // I/O Register
             0xff00..=0xff7f => match addr {
+                JOYP => {
+                    if let Some(sgb) = &mut self.sgb {
+                        sgb.write_joyp(val);
+                    }
+                    self.ioreg[0] = val;
+                }
+                SB..=SC => {
+                    let index = (addr - SB) as usize;
+                    self.serial.write_serial_reg(
+                        index,
+                        val,
+                        &mut self.ioreg[(IF - 0xff00) as usize],
+                    );
+                }
                 0xff04..=0xff07 => {
                     let index = (addr - 0xff04) as usize;
                     self.timer.write_timer_reg(index, val);
                 }
-                0xff40..=0xff4b => {
-                    let index = (addr - 0xff40) as usize;
-                    self.ppu.write_lcd_reg(index, val);
+                DMA => {
+                    self.lcd_reg_written(DMA, val);
+                    self.oam_dma_start = Some(((val as u16) << 8, OAM_DMA_DELAY_M));
                 }
+                0xff40..=0xff4b => self.lcd_reg_written(addr, val),
+                VBK | BCPS..=OPRI => self.ppu.write_cgb_reg(addr, val),
+                KEY1 if self.cgb_mode() => self.key1 = val & 0x01,
+                HDMA1..=HDMA5 if self.cgb_mode() => self.write_hdma(addr, val),
+                SVBK if self.cgb_mode() => self.svbk = val & 0x07,
                 _ => {
                     let index = (addr - 0xff00) as usize;
                     self.ioreg[index] = val;
@@ -303,3 +555,270 @@ impl MemoryIF for Mmu {
         }
     }
 }
+
+/// Memory printed by `Mmu::dump`, copied to be printed later
+pub struct MemoryDump {
+    addr: u16,
+    // rows of 16 bytes around `addr`, from `begin`
+    begin: usize,
+    around: Vec<u8>,
+    // 0xff00-0xffff
+    high: Vec<u8>,
+}
+
+impl MemoryDump {
+    /// Byte at `addr`, if it was copied
+    pub fn read(&self, addr: u16) -> Option<u8> {
+        let addr = addr as usize;
+        match addr.checked_sub(0xff00) {
+            Some(i) => Some(self.high[i]),
+            None => self.around.get(addr.wrapping_sub(self.begin)).copied(),
+        }
+    }
+    pub fn print(&self) {
+        let rows = |range: Range<usize>| {
+            for offset in range.step_by(16) {
+                print!("{:04x} |", offset);
+                for a in offset..offset + 16 {
+                    let byte = self.read(a as u16).unwrap_or(0);
+                    if a == self.addr as usize {
+                        print!("\x1b[7m {:02x}\x1b[0m", byte);
+                    } else {
+                        print!(" {:02x}", byte);
+                    }
+                }
+                println!();
+            }
+        };
+        // print header
+        print!("     |");
+        for i in 0..16 {
+            print!("{:3x}", i);
+        }
+        println!();
+        rows(self.begin..self.begin + self.around.len());
+
+        println!("I/O registers:");
+        rows(0xff00..0xff90);
+        println!("hram:");
+        rows(0xff80..0x10000);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::Screen;
+    use crate::memory::{LCDC, LY};
+
+    fn run(mmu: &mut Mmu, m: usize) {
+        let mut screen = Screen::new();
+        for _ in 0..m {
+            mmu.run_ppu(&mut screen).unwrap();
+        }
+    }
+
+    #[test]
+    fn oam_dma() {
+        let mut mmu = Mmu::new();
+        // OAM is accessible with the LCD off
+        mmu.write_byte(LCDC, 0x00);
+        let rom: Vec<u8> = (0..0xa0).map(|i| i as u8 + 1).collect();
+        mmu.load_rom(&rom).unwrap();
+        mmu.write_byte(0xff80, 0x12);
+
+        // from page 0x00
+        mmu.write_byte(DMA, 0x00);
+        assert_eq!(0x00, mmu.read_byte(DMA));
+        // start-up delay
+        run(&mut mmu, 1);
+        assert_eq!(0x00, mmu.read_byte(0xc000));
+        run(&mut mmu, 1);
+        assert_eq!(0xff, mmu.read_byte(0xc000));
+        // the CPU sees the byte being transferred, except in HRAM
+        run(&mut mmu, 3);
+        assert_eq!(3, mmu.read_byte(0xc000));
+        assert_eq!(0xff, mmu.read_byte(0xfe00));
+        assert_eq!(0x12, mmu.read_byte(0xff80));
+        mmu.write_byte(0xc000, 0x34);
+        run(&mut mmu, 156);
+        assert_eq!(0x9f, mmu.read_byte(0x4000));
+        run(&mut mmu, 1);
+        assert_eq!(0x00, mmu.read_byte(0xc000));
+        assert_eq!(1, mmu.read_byte(0xfe00));
+        assert_eq!(0xa0, mmu.read_byte(0xfe9f));
+
+        // restart from 0xe000, mapped to 0xc000
+        mmu.write_byte(0xc000, 0x56);
+        mmu.write_byte(0xc09f, 0x78);
+        mmu.write_byte(DMA, 0x00);
+        run(&mut mmu, 10);
+        mmu.write_byte(DMA, 0xe0);
+        run(&mut mmu, 2);
+        // the first transfer continues during the delay
+        assert_eq!(0xff, mmu.read_byte(0xfe00));
+        run(&mut mmu, 160);
+        assert_eq!(0x56, mmu.read_byte(0xfe00));
+        assert_eq!(0x78, mmu.read_byte(0xfe9f));
+    }
+    #[test]
+    fn oam_bug_model() {
+        // mode 2 of line 0: row 1 is corrupted with row 0 (all zeros)
+        let oam_bug = |model, addr| {
+            let mut mmu = Mmu::new();
+            mmu.set_model(model);
+            mmu.ppu.write_oam_dma(8, 0x55);
+            mmu.oam_bug(addr, OamAccess::Write, 1);
+            mmu.write_byte(LCDC, 0x00);
+            mmu.read_byte(0xfe08)
+        };
+        assert_eq!(0x00, oam_bug(Model::Dmg, 0xfe10));
+        assert_eq!(0x55, oam_bug(Model::Dmg, 0xff00));
+        assert_eq!(0x55, oam_bug(Model::Cgb, 0xfe10));
+    }
+    #[test]
+    fn cgb_mode() {
+        let mut rom = vec![0; 0x8000];
+        let mut mmu = Mmu::new();
+        mmu.set_model(Model::Cgb);
+        mmu.load_rom(&rom).unwrap();
+        // DMG cart: DMG mode
+        assert_eq!(0xff, mmu.read_byte(VBK));
+        rom[0x0143] = 0x80;
+        mmu.load_rom(&rom).unwrap();
+        assert_eq!(0xfe, mmu.read_byte(VBK));
+        // VRAM bank 1
+        mmu.write_byte(0x8000, 0x12);
+        mmu.write_byte(VBK, 0x01);
+        assert_eq!(0x00, mmu.read_byte(0x8000));
+        mmu.write_byte(0x8000, 0x34);
+        mmu.write_byte(VBK, 0x00);
+        assert_eq!(0x12, mmu.read_byte(0x8000));
+        mmu.set_model(Model::Dmg);
+        assert_eq!(0xff, mmu.read_byte(VBK));
+    }
+    fn cgb_mmu() -> Mmu {
+        let mut rom = vec![0; 0x8000];
+        rom[0x0143] = 0x80;
+        let mut mmu = Mmu::new();
+        mmu.load_rom(&rom).unwrap();
+        mmu.set_model(Model::Cgb);
+        mmu
+    }
+    fn paused_m(mmu: &mut Mmu) -> usize {
+        let mut m = 0;
+        while mmu.cpu_paused() {
+            m += 1;
+        }
+        m
+    }
+    #[test]
+    fn wram_banks() {
+        let mut mmu = cgb_mmu();
+        for bank in 0..8 {
+            mmu.write_byte(SVBK, bank);
+            mmu.write_byte(0xd000, bank + 0x10);
+        }
+        assert_eq!(0xff, mmu.read_byte(SVBK));
+        // bank 0 selects bank 1
+        mmu.write_byte(SVBK, 0);
+        assert_eq!(0x11, mmu.read_byte(0xd000));
+        mmu.write_byte(SVBK, 5);
+        assert_eq!(0x15, mmu.read_byte(0xd000));
+        assert_eq!(0x15, mmu.read_byte(0xf000));
+    }
+    #[test]
+    fn vram_dma() {
+        let mut mmu = cgb_mmu();
+        for i in 0..0x40 {
+            mmu.write_byte(0xc000 + i, i as u8 + 1);
+        }
+        mmu.write_byte(LCDC, 0x00);
+        // general purpose DMA of 2 blocks from $c000 to $8100
+        mmu.write_byte(HDMA1, 0xc0);
+        mmu.write_byte(HDMA1 + 1, 0x00);
+        mmu.write_byte(HDMA1 + 2, 0x81);
+        mmu.write_byte(HDMA1 + 3, 0x00);
+        mmu.write_byte(HDMA5, 0x01);
+        assert_eq!(2 * VRAM_DMA_BLOCK_M, paused_m(&mut mmu));
+        assert_eq!(0xff, mmu.read_byte(HDMA5));
+        assert_eq!(0x20, mmu.read_byte(0x811f));
+
+        // HBlank DMA of 2 blocks, one at the end of each mode 3
+        mmu.write_byte(LCDC, 0x80);
+        // the source continues from $c020
+        mmu.write_byte(HDMA1 + 2, 0x82);
+        mmu.write_byte(HDMA1 + 3, 0x00);
+        mmu.write_byte(HDMA5, 0x81);
+        assert_eq!(0x01, mmu.read_byte(HDMA5));
+        run(&mut mmu, 70);
+        assert_eq!(0x00, mmu.read_byte(HDMA5));
+        assert_eq!(VRAM_DMA_BLOCK_M, paused_m(&mut mmu));
+        run(&mut mmu, 114);
+        assert_eq!(0xff, mmu.read_byte(HDMA5));
+        mmu.write_byte(LCDC, 0x00);
+        assert_eq!(0x30, mmu.read_byte(0x820f));
+        assert_eq!(0x40, mmu.read_byte(0x821f));
+
+        // stopped by writing bit 7 = 0
+        mmu.write_byte(HDMA5, 0x83);
+        mmu.write_byte(HDMA5, 0x00);
+        assert_eq!(0x83, mmu.read_byte(HDMA5));
+
+        // a source from $fe00 reads the working RAM at $de00
+        mmu.write_byte(LCDC, 0x00);
+        mmu.write_byte(0xdea0, 0x55);
+        mmu.write_byte(HDMA1, 0xfe);
+        mmu.write_byte(HDMA1 + 1, 0xa0);
+        mmu.write_byte(HDMA1 + 2, 0x83);
+        mmu.write_byte(HDMA1 + 3, 0x00);
+        mmu.write_byte(HDMA5, 0x00);
+        assert_eq!(0x55, mmu.read_byte(0x8300));
+    }
+    #[test]
+    fn speed_switch() {
+        let mut mmu = cgb_mmu();
+        // not armed
+        mmu.speed_switch();
+        assert_eq!(0x7e, mmu.read_byte(KEY1));
+        mmu.write_byte(KEY1, 0x01);
+        assert_eq!(0x7f, mmu.read_byte(KEY1));
+        mmu.speed_switch();
+        assert_eq!(0xfe, mmu.read_byte(KEY1));
+        assert_eq!(SPEED_SWITCH_M, paused_m(&mut mmu));
+        // the PPU runs at half the rate: a line is 228 M-cycles
+        run(&mut mmu, 227);
+        assert_eq!(0, mmu.read_byte(LY));
+        run(&mut mmu, 1);
+        assert_eq!(1, mmu.read_byte(LY));
+    }
+    #[test]
+    fn sgb_joypads() {
+        let mut rom = vec![0; 0x8000];
+        rom[0x0146] = 0x03;
+        rom[0x014b] = 0x33;
+        let mut mmu = Mmu::new();
+        mmu.load_rom(&rom).unwrap();
+        mmu.set_model(Model::Sgb);
+        assert!(mmu.sgb_mode());
+        // MLT_REQ with 2 players, sent through JOYP
+        let packet = [0x11 << 3 | 1, 0x01];
+        mmu.write_byte(JOYP, 0x30);
+        mmu.write_byte(JOYP, 0x00);
+        for bit in 0..=128 {
+            let one = bit < 128 && packet.get(bit / 8).is_some_and(|b| b >> (bit % 8) & 1 != 0);
+            mmu.write_byte(JOYP, 0x30);
+            mmu.write_byte(JOYP, if one { 0x10 } else { 0x20 });
+        }
+        mmu.write_byte(JOYP, 0x30);
+        assert_eq!(0x3f, mmu.read_byte(JOYP));
+        mmu.write_byte(JOYP, 0x10);
+        mmu.write_byte(JOYP, 0x30);
+        assert_eq!((0x3e, 1), (mmu.read_byte(JOYP), mmu.joypad_player()));
+        // no SGB features on DMG
+        mmu.set_model(Model::Dmg);
+        assert!(!mmu.sgb_mode());
+        mmu.write_byte(JOYP, 0x30);
+        assert_eq!((0x30, 0), (mmu.read_byte(JOYP), mmu.joypad_player()));
+    }
+}
@@ -0,0 +1,204 @@
+//! The I/O registers decoded into their fields, for the debugger.
+
+use super::Mmu;
+use crate::io::Rgb;
+use crate::memory::{
+    MemoryIF, BGP, DIV, IE, IF, JOYP, LCDC, LY, LYC, OBP0, OBP1, SB, SC, SCX, SCY, STAT, TAC, TIMA,
+    TMA, VBK, WX, WY,
+};
+
+const INTERRUPTS: [&str; 5] = ["VBlank", "STAT", "timer", "serial", "joypad"];
+
+/// Names of the set bits, "-" for none
+fn flags(val: u8, names: &[(u8, &str)]) -> String {
+    let set: Vec<&str> = names
+        .iter()
+        .filter(|(bit, _)| val & bit != 0)
+        .map(|(_, name)| *name)
+        .collect();
+    if set.is_empty() {
+        "-".to_string()
+    } else {
+        set.join(", ")
+    }
+}
+
+fn joyp(val: u8) -> String {
+    let keys = match (val & 0x20 == 0, val & 0x10 == 0) {
+        (false, false) => return "nothing selected".to_string(),
+        (true, false) => ("buttons", ["A", "B", "Select", "Start"]),
+        (false, true) => ("d-pad", ["Right", "Left", "Up", "Down"]),
+        (true, true) => ("buttons and d-pad", ["0", "1", "2", "3"]),
+    };
+    let pressed: Vec<(u8, &str)> = (0..4).map(|i| (1 << i, keys.1[i])).collect();
+    format!(
+        "{} selected, pressed {}",
+        keys.0,
+        flags(!val & 0x0f, &pressed)
+    )
+}
+
+fn sc(val: u8) -> String {
+    let transfer = if val & 0x80 != 0 { "transfer" } else { "idle" };
+    let clock = if val & 0x01 != 0 {
+        "internal"
+    } else {
+        "external"
+    };
+    format!("{}, {} clock", transfer, clock)
+}
+
+fn tac(val: u8) -> String {
+    let rate = ["4096", "262144", "65536", "16384"][(val & 0x03) as usize];
+    let enable = if val & 0x04 != 0 { "on" } else { "off" };
+    format!("{}, {} Hz", enable, rate)
+}
+
+fn interrupts(val: u8) -> String {
+    let names: Vec<(u8, &str)> = (0..5).map(|i| (1 << i, INTERRUPTS[i])).collect();
+    flags(val, &names)
+}
+
+fn lcdc(val: u8) -> String {
+    let bit =
+        |n: u8, on: &'static str, off: &'static str| if val >> n & 0x01 != 0 { on } else { off };
+    format!(
+        "LCD {}, window {} map {}, tiles {}, BG map {}, OBJ {} {}, BG/window {}",
+        bit(7, "on", "off"),
+        bit(5, "on", "off"),
+        bit(6, "0x9c00", "0x9800"),
+        bit(4, "0x8000", "0x8800"),
+        bit(3, "0x9c00", "0x9800"),
+        bit(1, "on", "off"),
+        bit(2, "8x16", "8x8"),
+        bit(0, "on", "off"),
+    )
+}
+
+fn stat(val: u8) -> String {
+    let sources = [
+        (0x08, "HBlank"),
+        (0x10, "VBlank"),
+        (0x20, "OAM"),
+        (0x40, "LYC"),
+    ];
+    let lyc = if val & 0x04 != 0 { "LY=LYC" } else { "LY!=LYC" };
+    format!(
+        "mode {}, {}, interrupts {}",
+        val & 0x03,
+        lyc,
+        flags(val, &sources)
+    )
+}
+
+/// Four blocks of colour, with 24-bit ANSI escapes
+fn swatch(colors: &[Rgb; 4]) -> String {
+    colors
+        .iter()
+        .map(|Rgb(r, g, b)| format!("\x1b[48;2;{};{};{}m  \x1b[0m", r, g, b))
+        .collect()
+}
+
+impl Mmu {
+    /// The I/O registers, one line per register or group of registers
+    fn io_regs(&self) -> Vec<String> {
+        let reg = |addr: u16| self.read_byte(addr);
+        let mut lines = vec![
+            format!("JOYP 0x{:02x}: {}", reg(JOYP), joyp(reg(JOYP))),
+            format!("SB 0x{:02x} SC 0x{:02x}: {}", reg(SB), reg(SC), sc(reg(SC))),
+            format!(
+                "DIV 0x{:02x} TIMA 0x{:02x} TMA 0x{:02x} TAC 0x{:02x}: {}",
+                reg(DIV),
+                reg(TIMA),
+                reg(TMA),
+                reg(TAC),
+                tac(reg(TAC))
+            ),
+            format!("IF 0x{:02x}: {}", reg(IF), interrupts(reg(IF))),
+            format!("IE 0x{:02x}: {}", reg(IE), interrupts(reg(IE))),
+            format!("LCDC 0x{:02x}: {}", reg(LCDC), lcdc(reg(LCDC))),
+            format!("STAT 0x{:02x}: {}", reg(STAT), stat(reg(STAT))),
+            format!(
+                "LY 0x{:02x} LYC 0x{:02x} SCY 0x{:02x} SCX 0x{:02x} WY 0x{:02x} WX 0x{:02x}",
+                reg(LY),
+                reg(LYC),
+                reg(SCY),
+                reg(SCX),
+                reg(WY),
+                reg(WX)
+            ),
+            format!(
+                "BGP 0x{:02x} OBP0 0x{:02x} OBP1 0x{:02x}",
+                reg(BGP),
+                reg(OBP0),
+                reg(OBP1)
+            ),
+        ];
+        let swatches: Vec<String> = self
+            .ppu
+            .palette_swatches()
+            .iter()
+            .map(|(name, colors)| format!("{:4} {}", name, swatch(colors)))
+            .collect();
+        // 4 palettes per line
+        lines.extend(swatches.chunks(4).map(|row| row.join("  ")));
+        let ram = if self.ram_enable { "on" } else { "off" };
+        lines.push(format!(
+            "MBC: ROM bank 0x{:02x}, RAM bank {} ({})",
+            self.rom_bank, self.ram_bank, ram
+        ));
+        if self.cgb_mode() {
+            lines.push(format!(
+                "CGB: VRAM bank {}, WRAM bank {}, {} speed",
+                reg(VBK) & 0x01,
+                self.svbk.max(1),
+                if self.double_speed {
+                    "double"
+                } else {
+                    "single"
+                }
+            ));
+        }
+        lines
+    }
+    /// Prints the decoded I/O registers, see `Mmu::dump` for the raw bytes.
+    pub fn dump_io_regs(&self) {
+        println!("I/O registers:");
+        for line in self.io_regs() {
+            println!("{}", line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode() {
+        assert_eq!("d-pad selected, pressed Left, Down", joyp(0xe5));
+        assert_eq!("nothing selected", joyp(0xff));
+        assert_eq!("on, 262144 Hz", tac(0xfd));
+        assert_eq!("VBlank, timer", interrupts(0xe5));
+        assert_eq!("-", interrupts(0xe0));
+        assert_eq!(
+            "LCD on, window off map 0x9c00, tiles 0x8000, BG map 0x9800, OBJ off 8x8, BG/window on",
+            lcdc(0xd1)
+        );
+        assert_eq!("mode 1, LY=LYC, interrupts VBlank, LYC", stat(0xd5));
+    }
+
+    #[test]
+    fn io_regs() {
+        let mut mmu = Mmu::new();
+        mmu.write_byte(LCDC, 0x91);
+        mmu.write_byte(IE, 0x01);
+        let lines = mmu.io_regs();
+        assert!(lines.contains(&"IE 0x01: VBlank".to_string()));
+        assert!(lines.iter().any(|l| l.starts_with("LCDC 0x91: LCD on")));
+        assert_eq!(
+            "MBC: ROM bank 0x01, RAM bank 0 (off)",
+            lines.last().unwrap()
+        );
+    }
+}
@@ -0,0 +1,713 @@
+//! Colours of the DMG shades. Each layer (BG, OBJ0, OBJ1) has its own 4
+//! colours, so DMG games can be colourised as by the CGB boot ROM or with
+//! user palettes.
+//!
+//! The CGB boot ROM picks the palettes of a DMG cart from a table keyed by
+//! the title checksum (and the 4th title letter when checksums collide),
+//! for carts licensed by Nintendo. Other carts get the default palettes, and
+//! the player can override the choice with a button combo held during the
+//! boot logo, or given with `--cgb-palette`.
+//!
+//! User palettes are named by a preset (`dmg`, `mgb`, `contrast`) or a
+//! palette file, or by `<bg>,<obj0>,<obj1>` for a palette per layer. A
+//! palette file is a JASC `.pal` file or a list of hex colours (`#e0f8d0`),
+//! with 4 colours for all the layers or 12 for BG, OBJ0 and OBJ1. The
+//! palette chosen for a game is remembered by the ROM checksum.
+use crate::io::{GfxColor, Joypad, Rgb};
+use std::fs;
+
+/// Colours of shades 0 (lightest) to 3
+pub type Palette = [Rgb; 4];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LayerPalettes {
+    pub bg: Palette,
+    pub obj0: Palette,
+    pub obj1: Palette,
+}
+
+impl LayerPalettes {
+    /// Same palette for all the layers
+    pub const fn uniform(palette: Palette) -> LayerPalettes {
+        LayerPalettes {
+            bg: palette,
+            obj0: palette,
+            obj1: palette,
+        }
+    }
+    /// DMG greenish shades
+    pub fn dmg() -> LayerPalettes {
+        LayerPalettes::uniform([
+            GfxColor::W.rgb(),
+            GfxColor::LG.rgb(),
+            GfxColor::DG.rgb(),
+            GfxColor::B.rgb(),
+        ])
+    }
+}
+
+const fn hex(rgb: u32) -> Rgb {
+    Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+}
+const fn palette(colors: [u32; 4]) -> Palette {
+    [
+        hex(colors[0]),
+        hex(colors[1]),
+        hex(colors[2]),
+        hex(colors[3]),
+    ]
+}
+
+const MGB: Palette = palette([0xe0dbcd, 0xa89f94, 0x706b66, 0x2b2b26]);
+const CONTRAST: Palette = palette([0xffffff, 0xaaaaaa, 0x555555, 0x000000]);
+
+/// Built-in palettes, in the order of the palette hotkey
+pub fn presets() -> [(&'static str, LayerPalettes); 3] {
+    [
+        ("dmg", LayerPalettes::dmg()),
+        ("mgb", LayerPalettes::uniform(MGB)),
+        ("contrast", LayerPalettes::uniform(CONTRAST)),
+    ]
+}
+
+const GREEN: Palette = palette([0xffffff, 0x7bff31, 0x0063c5, 0x000000]);
+const RED: Palette = palette([0xffffff, 0xff8484, 0x943a3a, 0x000000]);
+const BROWN: Palette = palette([0xffffff, 0xffad63, 0x843100, 0x000000]);
+const DARK_BROWN: Palette = palette([0xffe6c5, 0xce9c84, 0x846b29, 0x5a3108]);
+const BLUE: Palette = palette([0xffffff, 0x63a5ff, 0x0000ff, 0x000000]);
+const DARK_GREEN: Palette = palette([0xffffff, 0x7bff31, 0x008400, 0x000000]);
+const DARK_BLUE: Palette = palette([0xffffff, 0x8c8cde, 0x52528c, 0x000000]);
+const GRAYSCALE: Palette = palette([0xffffff, 0xa5a5a5, 0x525252, 0x000000]);
+const PASTEL: Palette = palette([0xffffa5, 0xff9494, 0x9494ff, 0x000000]);
+const ORANGE: Palette = palette([0xffffff, 0xffff00, 0xff0000, 0x000000]);
+const YELLOW: Palette = palette([0xffffff, 0xffff00, 0x7b4a00, 0x000000]);
+const LIME: Palette = palette([0xffffff, 0x52ff00, 0xff4200, 0x000000]);
+const INVERTED: Palette = palette([0x000000, 0x008484, 0xffde00, 0xffffff]);
+
+/// Button combos held during the CGB boot logo: a direction, optionally
+/// with A or B
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Combo {
+    Up,
+    UpA,
+    UpB,
+    Left,
+    LeftA,
+    LeftB,
+    Down,
+    DownA,
+    DownB,
+    Right,
+    RightA,
+    RightB,
+}
+
+impl Combo {
+    /// `up`, `up+a`, `left+b`, ...
+    pub fn parse(s: &str) -> Option<Combo> {
+        let combo = match s.to_ascii_lowercase().as_str() {
+            "up" => Combo::Up,
+            "up+a" => Combo::UpA,
+            "up+b" => Combo::UpB,
+            "left" => Combo::Left,
+            "left+a" => Combo::LeftA,
+            "left+b" => Combo::LeftB,
+            "down" => Combo::Down,
+            "down+a" => Combo::DownA,
+            "down+b" => Combo::DownB,
+            "right" => Combo::Right,
+            "right+a" => Combo::RightA,
+            "right+b" => Combo::RightB,
+            _ => return None,
+        };
+        Some(combo)
+    }
+    /// Combo of the held keys: the first direction of right, left, up and
+    /// down, with A or else B
+    pub fn from_keys(keys: &[Joypad]) -> Option<Combo> {
+        let held = |key: Joypad| keys.contains(&key);
+        let button = if held(Joypad::A) {
+            1
+        } else if held(Joypad::B) {
+            2
+        } else {
+            0
+        };
+        let combos = match () {
+            _ if held(Joypad::Right) => [Combo::Right, Combo::RightA, Combo::RightB],
+            _ if held(Joypad::Left) => [Combo::Left, Combo::LeftA, Combo::LeftB],
+            _ if held(Joypad::Up) => [Combo::Up, Combo::UpA, Combo::UpB],
+            _ if held(Joypad::Down) => [Combo::Down, Combo::DownA, Combo::DownB],
+            _ => return None,
+        };
+        Some(combos[button])
+    }
+    pub fn palettes(self) -> LayerPalettes {
+        let layers = |bg, obj0, obj1| LayerPalettes { bg, obj0, obj1 };
+        match self {
+            Combo::Up => LayerPalettes::uniform(BROWN),
+            Combo::UpA => layers(RED, DARK_GREEN, BLUE),
+            Combo::UpB => LayerPalettes::uniform(DARK_BROWN),
+            Combo::Left => layers(BLUE, RED, DARK_GREEN),
+            Combo::LeftA => layers(DARK_BLUE, RED, BROWN),
+            Combo::LeftB => LayerPalettes::uniform(GRAYSCALE),
+            Combo::Down => LayerPalettes::uniform(PASTEL),
+            Combo::DownA => LayerPalettes::uniform(ORANGE),
+            Combo::DownB => layers(YELLOW, BLUE, DARK_GREEN),
+            Combo::Right => layers(GREEN, RED, RED),
+            Combo::RightA => LayerPalettes::uniform(LIME),
+            Combo::RightB => LayerPalettes::uniform(INVERTED),
+        }
+    }
+}
+
+/// Entry of the boot ROM table
+#[derive(Clone, Copy)]
+pub struct GamePalettes {
+    pub checksum: u8,
+    // 4th letter of the title, for checksums shared by several games
+    pub fourth: Option<u8>,
+    pub palettes: LayerPalettes,
+}
+
+// Tables of the CGB boot ROM. Games are found by the title checksum; the
+// last ones share their checksum and are told apart by the 4th letter.
+const TITLE_CHECKSUMS: [u8; 94] = [
+    0x00, 0x88, 0x16, 0x36, 0xd1, 0xdb, 0xf2, 0x3c, 0x8c, 0x92, 0x3d, 0x5c, 0x58, 0xc9, 0x3e, 0x70,
+    0x1d, 0x59, 0x69, 0x19, 0x35, 0xa8, 0x14, 0xaa, 0x75, 0x95, 0x99, 0x34, 0x6f, 0x15, 0xff, 0x97,
+    0x4b, 0x90, 0x17, 0x10, 0x39, 0xf7, 0xf6, 0xa2, 0x49, 0x4e, 0x43, 0x68, 0xe0, 0x8b, 0xf0, 0xce,
+    0x0c, 0x29, 0xe8, 0xb7, 0x86, 0x9a, 0x52, 0x01, 0x9d, 0x71, 0x9c, 0xbd, 0x5d, 0x6d, 0x67, 0x3f,
+    0x6b, // with a 4th letter
+    0xb3, 0x46, 0x28, 0xa5, 0xc6, 0xd3, 0x27, 0x61, 0x18, 0x66, 0x6a, 0xbf, 0x0d, 0xf4, 0xb3, 0x46,
+    0x28, 0xa5, 0xc6, 0xd3, 0x27, 0x61, 0x18, 0x66, 0x6a, 0xbf, 0x0d, 0xf4, 0xb3,
+];
+const FOURTH_LETTERS: &[u8; 29] = b"BEFAARBEKEK R-URAR INAILICE R";
+// combination of each checksum
+const PALETTE_INDEX: [u8; 94] = [
+    0, 4, 5, 35, 34, 3, 31, 15, 10, 5, 19, 36, 7, 37, 30, 44, 21, 32, 31, 20, 5, 33, 13, 14, 5, 29,
+    5, 18, 9, 3, 2, 26, 25, 25, 41, 42, 26, 45, 42, 45, 36, 38, 26, 42, 30, 41, 34, 34, 5, 42, 6,
+    5, 33, 25, 42, 42, 40, 2, 16, 25, 42, 42, 5, 0, 39, 36, 22, 25, 6, 32, 12, 36, 11, 39, 18, 39,
+    24, 31, 50, 17, 46, 6, 27, 0, 47, 41, 41, 0, 0, 19, 34, 23, 18, 29,
+];
+// OBJ0, OBJ1 and BG: offsets in BOOT_COLORS, mostly at the start of a
+// palette
+const fn comb(obj0: u8, obj1: u8, bg: u8) -> (u8, u8, u8) {
+    (obj0 * 4, obj1 * 4, bg * 4)
+}
+const COMBINATIONS: [(u8, u8, u8); 51] = [
+    comb(4, 4, 29),
+    comb(18, 18, 18),
+    comb(20, 20, 20),
+    comb(24, 24, 24),
+    comb(9, 9, 9),
+    comb(0, 0, 0),
+    comb(27, 27, 27),
+    comb(5, 5, 5),
+    comb(12, 12, 12),
+    comb(26, 26, 26),
+    comb(16, 8, 8),
+    comb(4, 28, 28),
+    comb(4, 2, 2),
+    comb(3, 4, 4),
+    comb(4, 29, 29),
+    comb(28, 4, 28),
+    comb(2, 17, 2),
+    comb(16, 16, 8),
+    comb(4, 4, 7),
+    comb(4, 4, 18),
+    comb(4, 4, 20),
+    comb(19, 19, 9),
+    (4 * 4 - 1, 4 * 4 - 1, 11 * 4),
+    comb(17, 17, 2),
+    comb(4, 4, 2),
+    comb(4, 4, 3),
+    comb(28, 28, 0),
+    comb(3, 3, 0),
+    comb(0, 0, 1),
+    comb(18, 22, 18),
+    comb(20, 22, 20),
+    comb(24, 22, 24),
+    comb(16, 22, 8),
+    comb(17, 4, 13),
+    (28 * 4 - 1, 0, 14 * 4),
+    (28 * 4 - 1, 4 * 4, 15 * 4),
+    comb(19, 22, 9),
+    comb(16, 28, 10),
+    comb(4, 23, 28),
+    comb(17, 22, 2),
+    comb(4, 0, 2),
+    comb(4, 28, 3),
+    comb(28, 3, 0),
+    comb(3, 28, 4),
+    comb(21, 28, 4),
+    comb(3, 28, 0),
+    comb(25, 3, 28),
+    comb(0, 28, 8),
+    comb(4, 3, 28),
+    comb(28, 3, 6),
+    comb(4, 28, 29),
+];
+// 30 palettes of 4 CGB colours
+const BOOT_COLORS: [u16; 120] = [
+    0x7fff, 0x32bf, 0x00d0, 0x0000, 0x639f, 0x4279, 0x15b0, 0x04cb, 0x7fff, 0x6e31, 0x454a, 0x0000,
+    0x7fff, 0x1bef, 0x0200, 0x0000, 0x7fff, 0x421f, 0x1cf2, 0x0000, 0x7fff, 0x5294, 0x294a, 0x0000,
+    0x7fff, 0x03ff, 0x012f, 0x0000, 0x7fff, 0x03ef, 0x01d6, 0x0000, 0x7fff, 0x42b5, 0x3dc8, 0x0000,
+    0x7e74, 0x03ff, 0x0180, 0x0000, 0x67ff, 0x77ac, 0x1a13, 0x2d6b, 0x7ed6, 0x4bff, 0x2175, 0x0000,
+    0x53ff, 0x4a5f, 0x7e52, 0x0000, 0x4fff, 0x7ed2, 0x3a4c, 0x1ce0, 0x03ed, 0x7fff, 0x255f, 0x0000,
+    0x036a, 0x021f, 0x03ff, 0x7fff, 0x7fff, 0x01df, 0x0112, 0x0000, 0x231f, 0x035f, 0x00f2, 0x0009,
+    0x7fff, 0x03ea, 0x011f, 0x0000, 0x299f, 0x001a, 0x000c, 0x0000, 0x7fff, 0x027f, 0x001f, 0x0000,
+    0x7fff, 0x03e0, 0x0206, 0x0120, 0x7fff, 0x7eeb, 0x001f, 0x7c00, 0x7fff, 0x3fff, 0x7e00, 0x001f,
+    0x7fff, 0x03ff, 0x001f, 0x0000, 0x03ff, 0x001f, 0x000c, 0x0000, 0x7fff, 0x033f, 0x0193, 0x0000,
+    0x0000, 0x4200, 0x037f, 0x7fff, 0x7fff, 0x7e8c, 0x7c00, 0x0000, 0x7fff, 0x1bef, 0x6180, 0x0000,
+];
+
+/// `Rgb::from_rgb555` for the tables
+const fn rgb555(color: u16) -> Rgb {
+    const fn channel(color: u16, shift: u16) -> u8 {
+        let c = ((color >> shift) & 0x1f) as u8;
+        (c << 3) | (c >> 2)
+    }
+    Rgb(channel(color, 0), channel(color, 5), channel(color, 10))
+}
+const fn boot_palette(offset: u8) -> Palette {
+    let i = offset as usize;
+    [
+        rgb555(BOOT_COLORS[i]),
+        rgb555(BOOT_COLORS[i + 1]),
+        rgb555(BOOT_COLORS[i + 2]),
+        rgb555(BOOT_COLORS[i + 3]),
+    ]
+}
+const fn boot_games() -> [GamePalettes; 94] {
+    let dup = TITLE_CHECKSUMS.len() - FOURTH_LETTERS.len();
+    let mut games = [GamePalettes {
+        checksum: 0,
+        fourth: None,
+        palettes: LayerPalettes::uniform([Rgb(0, 0, 0); 4]),
+    }; 94];
+    let mut i = 0;
+    while i < games.len() {
+        let (obj0, obj1, bg) = COMBINATIONS[PALETTE_INDEX[i] as usize];
+        games[i] = GamePalettes {
+            checksum: TITLE_CHECKSUMS[i],
+            fourth: if i < dup {
+                None
+            } else {
+                Some(FOURTH_LETTERS[i - dup])
+            },
+            palettes: LayerPalettes {
+                bg: boot_palette(bg),
+                obj0: boot_palette(obj0),
+                obj1: boot_palette(obj1),
+            },
+        };
+        i += 1;
+    }
+    games
+}
+
+/// Per-game entries of the CGB boot ROM
+pub const GAMES: &[GamePalettes] = &boot_games();
+
+/// Palettes chosen by the CGB boot ROM for a DMG cart
+pub fn cgb_compat(rom: &[u8], games: &[GamePalettes]) -> LayerPalettes {
+    lookup(rom, games).unwrap_or_else(|| Combo::Right.palettes())
+}
+
+fn lookup(rom: &[u8], games: &[GamePalettes]) -> Option<LayerPalettes> {
+    let header = rom.get(0x0134..0x0150)?;
+    let at = |addr: usize| header[addr - 0x0134];
+    // old licensee code 0x01, or 0x33 and the new licensee code "01"
+    let nintendo = match at(0x014b) {
+        0x01 => true,
+        0x33 => (at(0x0144), at(0x0145)) == (b'0', b'1'),
+        _ => false,
+    };
+    if !nintendo {
+        return None;
+    }
+    let title = &header[..0x0144 - 0x0134];
+    let checksum = title.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+    games
+        .iter()
+        .find(|g| g.checksum == checksum && g.fourth.is_none_or(|c| c == title[3]))
+        .map(|g| g.palettes)
+}
+
+/// Palettes named by a preset, a palette file, or `<bg>,<obj0>,<obj1>`
+pub fn resolve(name: &str) -> Result<LayerPalettes, String> {
+    if let Some((bg, objs)) = name.split_once(',') {
+        let (obj0, obj1) = objs
+            .split_once(',')
+            .ok_or(format!("{}: expected <bg>,<obj0>,<obj1>", name))?;
+        return Ok(LayerPalettes {
+            bg: resolve(bg)?.bg,
+            obj0: resolve(obj0)?.bg,
+            obj1: resolve(obj1)?.bg,
+        });
+    }
+    if let Some((_, palettes)) = presets().into_iter().find(|(n, _)| *n == name) {
+        return Ok(palettes);
+    }
+    let text = fs::read_to_string(name).map_err(|e| format!("{}: {}", name, e))?;
+    parse_file(&text).map_err(|e| format!("{}: {}", name, e))
+}
+
+/// JASC `.pal` file or hex colours, 4 or 12 colours
+pub fn parse_file(text: &str) -> Result<LayerPalettes, String> {
+    let mut lines = text
+        .lines()
+        .map(|l| l.split(';').next().unwrap().trim())
+        .filter(|l| !l.is_empty())
+        .peekable();
+    let mut colors = Vec::new();
+    if lines.peek() == Some(&"JASC-PAL") {
+        // version and number of colours
+        let count = lines.nth(2).and_then(|n| n.parse::<usize>().ok());
+        for line in lines {
+            let rgb = line
+                .split_whitespace()
+                .map(|c| c.parse::<u8>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| format!("invalid colour: {}", line))?;
+            match rgb[..] {
+                [r, g, b] => colors.push(Rgb(r, g, b)),
+                _ => return Err(format!("invalid colour: {}", line)),
+            }
+        }
+        if count != Some(colors.len()) {
+            return Err("wrong number of colours".to_string());
+        }
+    } else {
+        for c in lines.flat_map(|l| l.split_whitespace()) {
+            let hex = c.trim_start_matches('#').trim_start_matches("0x");
+            match u32::from_str_radix(hex, 16) {
+                Ok(rgb) if hex.len() == 6 => colors.push(self::hex(rgb)),
+                _ => return Err(format!("invalid colour: {}", c)),
+            }
+        }
+    }
+    let palette = |i: usize| [colors[i], colors[i + 1], colors[i + 2], colors[i + 3]];
+    match colors.len() {
+        4 => Ok(LayerPalettes::uniform(palette(0))),
+        12 => Ok(LayerPalettes {
+            bg: palette(0),
+            obj0: palette(4),
+            obj1: palette(8),
+        }),
+        n => Err(format!("{} colours, expected 4 or 12", n)),
+    }
+}
+
+/// Key of a game in the palette settings: header and global checksums
+pub fn rom_key(rom: &[u8]) -> String {
+    format!("{:02x}{:02x}{:02x}", rom[0x014d], rom[0x014e], rom[0x014f])
+}
+
+/// Palettes chosen for each game, saved as `<rom key> <palette name>` lines
+pub struct GameSettings {
+    path: String,
+    games: Vec<(String, String)>,
+}
+
+impl GameSettings {
+    /// A missing file has no settings.
+    pub fn load(path: &str) -> Result<GameSettings, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format!("{}: {}", path, e)),
+        };
+        let games = text
+            .lines()
+            .filter_map(|l| l.split_once(' '))
+            .map(|(key, name)| (key.to_string(), name.to_string()))
+            .collect();
+        Ok(GameSettings {
+            path: path.to_string(),
+            games,
+        })
+    }
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.games
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, name)| name.as_str())
+    }
+    /// Sets the palette of a game and saves the file.
+    pub fn set(&mut self, key: &str, name: &str) -> Result<(), String> {
+        match self.games.iter_mut().find(|(k, _)| k == key) {
+            Some(game) => game.1 = name.to_string(),
+            None => self.games.push((key.to_string(), name.to_string())),
+        }
+        let text: String = self
+            .games
+            .iter()
+            .map(|(key, name)| format!("{} {}\n", key, name))
+            .collect();
+        fs::write(&self.path, text).map_err(|e| format!("{}: {}", self.path, e))
+    }
+}
+
+/// Palettes cycled by the palette hotkey: the presets, then the palettes
+/// given on the command line
+pub struct PaletteCycle {
+    names: Vec<String>,
+    current: Option<usize>,
+}
+
+impl PaletteCycle {
+    pub fn new(extra: &[String]) -> PaletteCycle {
+        let mut cycle = PaletteCycle {
+            names: presets().iter().map(|(n, _)| n.to_string()).collect(),
+            current: None,
+        };
+        for name in extra {
+            cycle.add(name);
+        }
+        cycle
+    }
+    fn add(&mut self, name: &str) {
+        if !self.names.iter().any(|n| n == name) {
+            self.names.push(name.to_string());
+        }
+    }
+    /// Makes `name` the current palette, so the cycle goes on from it.
+    pub fn select(&mut self, name: &str) {
+        self.add(name);
+        self.current = self.names.iter().position(|n| n == name);
+    }
+    pub fn next(&mut self) -> &str {
+        let i = self.current.map_or(0, |i| (i + 1) % self.names.len());
+        self.current = Some(i);
+        &self.names[i]
+    }
+}
+
+const USAGE: &str = "\
+palette options:
+  --palette=<name>         DMG palette: dmg, mgb, contrast, a palette file
+                           or <bg>,<obj0>,<obj1>; repeat to add to the cycle
+  --palette-settings=<f>   palettes chosen with F2, per game
+                           (rs-gb-palettes.txt)
+  --cgb-palette=<combo>    CGB boot combo, instead of holding the keys at
+                           start: <up|left|down|right>[+a|+b]";
+
+pub struct PaletteOptions {
+    names: Vec<String>,
+    combo: Option<Combo>,
+    settings: String,
+}
+
+/// Removes the palette options from `args`.
+pub fn parse_args(args: &mut Vec<String>) -> Result<PaletteOptions, String> {
+    let mut options = PaletteOptions {
+        names: Vec::new(),
+        combo: None,
+        settings: "rs-gb-palettes.txt".to_string(),
+    };
+    let mut rest = Vec::new();
+    for arg in args.drain(..) {
+        match arg.split_once('=') {
+            Some(("--palette", val)) => options.names.push(val.to_string()),
+            Some(("--palette-settings", val)) => options.settings = val.to_string(),
+            Some(("--cgb-palette", val)) => {
+                options.combo = Some(Combo::parse(val).ok_or(format!("{}\n{}", arg, USAGE))?)
+            }
+            _ if arg.starts_with("--palette") || arg.starts_with("--cgb-palette") => {
+                return Err(format!("{}\n{}", arg, USAGE))
+            }
+            _ => rest.push(arg),
+        }
+    }
+    *args = rest;
+    Ok(options)
+}
+
+/// Palette selection of the running game
+pub struct Palettes {
+    cycle: PaletteCycle,
+    settings: GameSettings,
+    key: String,
+}
+
+impl Palettes {
+    /// Also returns the palettes to start with: the first `--palette`, the
+    /// palette remembered for the game, or the CGB boot combo of
+    /// `--cgb-palette` or else of `held_combo`. `None` keeps the palettes of
+    /// the model. Only the choices of `next` are remembered.
+    pub fn new(
+        options: PaletteOptions,
+        rom: &[u8],
+        held_combo: impl FnOnce() -> Option<Combo>,
+    ) -> Result<(Palettes, Option<LayerPalettes>), String> {
+        let mut palettes = Palettes {
+            cycle: PaletteCycle::new(&options.names),
+            settings: GameSettings::load(&options.settings)?,
+            key: rom_key(rom),
+        };
+        let initial = match options.names.first() {
+            Some(name) => {
+                let initial = resolve(name)?;
+                palettes.cycle.select(name);
+                Some(initial)
+            }
+            None => match palettes.settings.get(&palettes.key).map(str::to_string) {
+                Some(name) => {
+                    palettes.cycle.select(&name);
+                    Some(resolve(&name)?)
+                }
+                None => options.combo.or_else(held_combo).map(Combo::palettes),
+            },
+        };
+        Ok((palettes, initial))
+    }
+    /// Switches to the next palette of the cycle and remembers it.
+    pub fn next(&mut self) -> Result<(String, LayerPalettes), String> {
+        let name = self.cycle.next().to_string();
+        let palettes = resolve(&name)?;
+        self.settings.set(&self.key, &name)?;
+        Ok((name, palettes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compat_lookup() {
+        // 'T' + 'E' + 'S' + 'T' = 0x40, 'T' + 'E' + 'S' + 'X' = 0x44
+        let games = [
+            GamePalettes {
+                checksum: 0x44,
+                fourth: None,
+                palettes: Combo::Down.palettes(),
+            },
+            GamePalettes {
+                checksum: 0x40,
+                fourth: Some(b'X'),
+                palettes: Combo::Down.palettes(),
+            },
+            GamePalettes {
+                checksum: 0x40,
+                fourth: Some(b'T'),
+                palettes: Combo::UpA.palettes(),
+            },
+        ];
+        let mut rom = vec![0; 0x8000];
+        rom[0x0134..0x0138].copy_from_slice(b"TEST");
+        // not licensed by Nintendo
+        assert_eq!(Combo::Right.palettes(), cgb_compat(&rom, &games));
+        rom[0x014b] = 0x01;
+        assert_eq!(Combo::UpA.palettes(), cgb_compat(&rom, &games));
+        rom[0x014b] = 0x33;
+        rom[0x0144..0x0146].copy_from_slice(b"01");
+        assert_eq!(Combo::UpA.palettes(), cgb_compat(&rom, &games));
+        // no match
+        rom[0x0137] = b'Y';
+        assert_eq!(Combo::Right.palettes(), cgb_compat(&rom, &games));
+    }
+    #[test]
+    fn boot_table() {
+        let title = |title: &[u8], licensee: u8| {
+            let mut rom = vec![0; 0x8000];
+            rom[0x0134..0x0134 + title.len()].copy_from_slice(title);
+            rom[0x014b] = licensee;
+            cgb_compat(&rom, GAMES)
+        };
+        let layers = |bg, obj0, obj1| LayerPalettes { bg, obj0, obj1 };
+        // the colours of the boot ROM, as displayed
+        let red = [0x7fff, 0x421f, 0x1cf2, 0x0000].map(rgb555);
+        let green = [0x7fff, 0x1bef, 0x6180, 0x0000].map(rgb555);
+        assert_eq!(LayerPalettes::uniform(ORANGE), title(b"TETRIS", 0x01));
+        assert_eq!(layers(red, DARK_GREEN, red), title(b"POKEMON RED", 0x01));
+        assert_eq!(layers(BLUE, red, BLUE), title(b"POKEMON BLUE", 0x01));
+        assert_eq!(layers(green, red, green), title(b"POKEMON GREEN", 0x01));
+        assert_eq!(Combo::Right.palettes(), title(b"TETRIS", 0x00));
+        // checksum 0x46: SUPER MARIOLAND, whose OBJ palettes start at the
+        // last colour of a palette, or another game with R as 4th letter
+        let mario = title(b"SUPER MARIOLAND", 0x01);
+        assert_eq!(rgb555(0x0000), mario.obj0[0]);
+        assert_eq!(red[..3], mario.obj0[1..]);
+        assert_eq!(rgb555(0x7ed6), mario.bg[0]);
+        assert_eq!(BLUE, title(b"SUPRE MARIOLAND", 0x01).bg);
+        assert_eq!(Combo::Right.palettes(), title(b"SURPE MARIOLAND", 0x01));
+    }
+    #[test]
+    fn combos() {
+        assert_eq!(Some(Combo::LeftB), Combo::parse("Left+B"));
+        assert_eq!(None, Combo::parse("up+start"));
+        let keys = [Joypad::B, Joypad::Up, Joypad::Start];
+        assert_eq!(Some(Combo::UpB), Combo::from_keys(&keys));
+        let keys = [Joypad::B, Joypad::A, Joypad::Down, Joypad::Right];
+        assert_eq!(Some(Combo::RightA), Combo::from_keys(&keys));
+        assert_eq!(None, Combo::from_keys(&[Joypad::A]));
+        let p = Combo::UpA.palettes();
+        assert_eq!(Rgb(0xff, 0x84, 0x84), p.bg[1]);
+        assert_eq!(Rgb(0x00, 0x84, 0x00), p.obj0[2]);
+        assert_eq!(Rgb(0x00, 0x00, 0xff), p.obj1[2]);
+    }
+    #[test]
+    fn palette_files() {
+        let jasc = "JASC-PAL\n0100\n4\n255 255 255\n170 170 170\n85 85 85\n0 0 0\n";
+        assert_eq!(Ok(LayerPalettes::uniform(CONTRAST)), parse_file(jasc));
+        let hex = "; bg\n#ffffff #aaaaaa #555555 #000000\n\
+                   e0dbcd a89f94 706b66 2b2b26\n0xffffff 0x7bff31 0x0063c5 0x000000\n";
+        let p = parse_file(hex).unwrap();
+        assert_eq!((CONTRAST, MGB, GREEN), (p.bg, p.obj0, p.obj1));
+        assert!(parse_file("#ffffff #aaaaaa #555555").is_err());
+        assert!(parse_file("JASC-PAL\n0100\n5\n0 0 0\n0 0 0\n0 0 0\n0 0 0\n").is_err());
+        assert!(parse_file("#fffff #aaaaaa #555555 #000000").is_err());
+
+        let p = resolve("mgb,contrast,dmg").unwrap();
+        assert_eq!((MGB, CONTRAST), (p.bg, p.obj0));
+        assert_eq!(LayerPalettes::dmg().bg, p.obj1);
+        assert!(resolve("mgb,contrast").is_err());
+    }
+    #[test]
+    fn game_settings() {
+        let path = std::env::temp_dir().join(format!("rs-gb-palettes-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut settings = GameSettings::load(path).unwrap();
+        assert_eq!(None, settings.get("123456"));
+        settings.set("123456", "mgb").unwrap();
+        settings.set("abcdef", "dmg,contrast,contrast").unwrap();
+        settings.set("123456", "contrast").unwrap();
+        let settings = GameSettings::load(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(Some("contrast"), settings.get("123456"));
+        assert_eq!(Some("dmg,contrast,contrast"), settings.get("abcdef"));
+    }
+    #[test]
+    fn remembered() {
+        let path =
+            std::env::temp_dir().join(format!("rs-gb-remembered-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        let options = |names: &[&str]| PaletteOptions {
+            names: names.iter().map(|n| n.to_string()).collect(),
+            combo: None,
+            settings: path.to_string(),
+        };
+        let rom = vec![0; 0x8000];
+        // --palette is not saved
+        let (mut palettes, initial) = Palettes::new(options(&["mgb"]), &rom, || None).unwrap();
+        assert_eq!(Some(LayerPalettes::uniform(MGB)), initial);
+        assert!(fs::metadata(path).is_err());
+        // the hotkey is
+        let (name, _) = palettes.next().unwrap();
+        let (_, initial) = Palettes::new(options(&[]), &rom, || None).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(Some(resolve(&name).unwrap()), initial);
+    }
+    #[test]
+    fn palette_cycle() {
+        let mut cycle = PaletteCycle::new(&["my.pal".to_string(), "mgb".to_string()]);
+        assert_eq!("dmg", cycle.next());
+        cycle.select("contrast");
+        assert_eq!("my.pal", cycle.next());
+        assert_eq!("dmg", cycle.next());
+        cycle.select("other.pal");
+        assert_eq!("dmg", cycle.next());
+    }
+}
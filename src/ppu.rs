@@ -1,18 +1,100 @@
-use crate::io::{GfxColor, Io, GFX_SIZE_X, GFX_SIZE_Y};
-use crate::memory::{BGP, LCDC, LY, LYC, OBP0, OBP1, SCX, SCY, STAT, WX, WY};
+use crate::io::{GfxColor, LcdIF, Rgb, GFX_SIZE_X, GFX_SIZE_Y};
+use crate::memory::{
+    Model, OamAccess, BCPD, BCPS, BGP, LCDC, LY, LYC, OBP0, OBP1, OCPD, OCPS, OPRI, SCX, SCY, STAT,
+    VBK, WX, WY,
+};
+use crate::palette::LayerPalettes;
+
+mod debug;
+mod fifo;
+mod viewer;
+
+use debug::Layer;
+pub use debug::{DebugToggle, LayerDebug, Mark};
+pub use viewer::{ViewPalette, MAPS_SIZE, OBJS_SIZE};
 
 const VRAM: u16 = 0x8000;
+const VRAM_BANK_SIZE: usize = 0x2000;
+// dots (T-cycles) per line
+const LINE_DOTS: usize = 456;
+const OAM_SCAN_DOTS: usize = 80;
+// length of mode 3 with the scanline renderer
+const DRAWING_DOTS: usize = 172;
+// LY reads 0 from this dot of line 153
+const LINE_153_LY_DOTS: usize = 4;
+// objects selected by the OAM scan on each line
+const OBJS_PER_LINE: usize = 10;
 
 pub struct Ppu {
     mode: Mode,
-    clock_m: usize,
+    // dots since the start of the line
+    dot: usize,
     line: usize,
     window_internal_line: Option<usize>,
     stat_int_prev: bool,
     set_blank: bool,
-    vram: [u8; 0x2000], // Graphics RAM 8k byte
+    // first line after the LCD is turned on: no OAM scan, mode 0 until mode 3
+    lcd_on_line: bool,
+    // the first frame after the LCD is turned on is not shown
+    blank_frame: bool,
+    // the window started at WX = 166, it covers the next line
+    window_wrap: bool,
+    vram: [u8; 0x4000], // Graphics RAM 8k byte * 2 banks (bank 1 on CGB only)
     oam: [u8; 0x00a0],  // Object Attribute Memory
     lcd_regs: [u8; 0xc],
+    // draw each line at the end of a fixed-length mode 3
+    fast: bool,
+    fifo: fifo::Fifo,
+    // CGB mode
+    cgb: Option<Cgb>,
+    // mode 3 ended since the last `take_hblank`
+    hblank: bool,
+    // colours of the DMG shades
+    dmg_palettes: LayerPalettes,
+    layer_debug: LayerDebug,
+    // enhancement: all the objects of a line are drawn
+    unlimited_objs: bool,
+    // hardware model, also for DMG carts on CGB
+    model: Model,
+}
+
+/// CGB mode registers and palette RAM
+struct Cgb {
+    vbk: u8,
+    // BCPS / OCPS: bit 7 auto-increment, bits 0-5 address
+    bcps: u8,
+    ocps: u8,
+    // 8 palettes * 4 colours, 15-bit little-endian
+    bg_palettes: [u8; 64],
+    obj_palettes: [u8; 64],
+    // bit 0 set: objects are prioritized by X as on DMG
+    opri: u8,
+}
+
+impl Cgb {
+    fn new() -> Cgb {
+        Cgb {
+            vbk: 0,
+            bcps: 0,
+            ocps: 0,
+            bg_palettes: [0xff; 64],
+            obj_palettes: [0xff; 64],
+            opri: 0,
+        }
+    }
+    fn color(palettes: &[u8; 64], palette: u8, color_id: u8) -> Rgb {
+        let i = (palette as usize & 0x07) * 8 + color_id as usize * 2;
+        Rgb::from_rgb555(u16::from_le_bytes([palettes[i], palettes[i + 1]]))
+    }
+    fn write_palette(palettes: &mut [u8; 64], spec: &mut u8, val: u8, locked: bool) {
+        if !locked {
+            palettes[(*spec & 0x3f) as usize] = val;
+        }
+        // the address is incremented even when the write is blocked
+        if *spec & 0x80 != 0 {
+            *spec = 0x80 | ((*spec + 1) & 0x3f);
+        }
+    }
 }
 
 #[derive(Copy, Clone, PartialEq)]
@@ -29,17 +111,68 @@ impl Ppu {
         lcd_regs[0] = 0x80;
         Ppu {
             mode: Mode::Mode2,
-            clock_m: 0,
+            dot: 0,
             line: 0,
             window_internal_line: None,
             stat_int_prev: false,
             set_blank: false,
-            vram: [0; 0x2000],
+            lcd_on_line: false,
+            blank_frame: false,
+            window_wrap: false,
+            vram: [0; 0x4000],
             oam: [0; 0x00a0],
             lcd_regs,
+            fast: false,
+            fifo: fifo::Fifo::new(),
+            cgb: None,
+            hblank: false,
+            dmg_palettes: LayerPalettes::dmg(),
+            layer_debug: LayerDebug::default(),
+            unlimited_objs: false,
+            model: Model::Dmg,
         }
     }
+    /// Model of the hardware quirks, see `set_cgb` for CGB mode.
+    pub fn set_model(&mut self, model: Model) {
+        self.model = model;
+    }
+    /// CGB mode: colour palettes, VRAM bank 1 and BG map attributes.
+    pub fn set_cgb(&mut self, cgb: bool) {
+        self.cgb = cgb.then(Cgb::new);
+    }
+    /// Colours of the shades of BGP, OBP0 and OBP1 in DMG mode
+    pub fn set_dmg_palettes(&mut self, palettes: LayerPalettes) {
+        self.dmg_palettes = palettes;
+    }
+    /// Debug views of the layers, see `LayerDebug`
+    pub fn layer_debug_mut(&mut self) -> &mut LayerDebug {
+        &mut self.layer_debug
+    }
+    /// Draws all the objects of a line instead of the first 10, against
+    /// flicker. Mode 3 still takes the time of fetching 10 objects.
+    pub fn set_unlimited_objs(&mut self, unlimited: bool) {
+        self.unlimited_objs = unlimited;
+    }
+    fn obj_limit(&self) -> usize {
+        if self.unlimited_objs {
+            self.oam.len() / 4
+        } else {
+            OBJS_PER_LINE
+        }
+    }
+    /// Offset of the VRAM bank selected by VBK
+    fn vram_bank(&self) -> usize {
+        self.cgb
+            .as_ref()
+            .map_or(0, |cgb| (cgb.vbk & 0x01) as usize * VRAM_BANK_SIZE)
+    }
+    /// Uses the scanline renderer instead of the pixel FIFO: faster, but
+    /// mid-line register writes are not visible and mode 3 has a fixed length.
+    pub fn set_fast(&mut self, fast: bool) {
+        self.fast = fast;
+    }
     pub fn read_vram(&self, index: usize) -> u8 {
+        let index = index + self.vram_bank();
         if self.is_enable() {
             match self.mode {
                 Mode::Mode3 => 0xff,   // inaccessible
@@ -50,6 +183,7 @@ impl Ppu {
         }
     }
     pub fn write_vram(&mut self, index: usize, val: u8) {
+        let index = index + self.vram_bank();
         if self.is_enable() {
             match self.mode {
                 Mode::Mode3 => (),           // inaccessible
@@ -79,262 +213,507 @@ impl Ppu {
             self.oam[index] = val // accessible
         }
     }
+    /// OAM DMA writes regardless of the mode.
+    pub fn write_oam_dma(&mut self, index: usize, val: u8) {
+        self.oam[index] = val;
+    }
+    /// DMG OAM corruption bug: a CPU access to 0xfe00-0xfeff during the OAM
+    /// scan corrupts the row (8 bytes) being read by the PPU `cycle` M-cycles
+    /// later. The first row is never corrupted.
+    pub fn oam_bug(&mut self, access: OamAccess, cycle: usize) {
+        if !self.is_enable() || self.mode != Mode::Mode2 {
+            return;
+        }
+        let row = self.dot / 4 + cycle;
+        if row == 0 || row >= OAM_SCAN_DOTS / 4 {
+            return;
+        }
+        if access == OamAccess::ReadIncDec && (4..19).contains(&row) {
+            let a = self.oam_word(row - 2, 0);
+            let b = self.oam_word(row - 1, 0);
+            let c = self.oam_word(row, 0);
+            let d = self.oam_word(row - 2, 2);
+            self.set_oam_word(row - 1, 0, (b & (a | c | d)) | (a & c & d));
+            let prev = (row - 1) * 8;
+            self.oam.copy_within(prev..prev + 8, row * 8);
+            self.oam.copy_within(prev..prev + 8, (row - 2) * 8);
+        }
+        let a = self.oam_word(row, 0);
+        let b = self.oam_word(row - 1, 0);
+        let c = self.oam_word(row - 1, 2);
+        let word = match access {
+            OamAccess::Write => ((a ^ c) & (b ^ c)) ^ c,
+            OamAccess::Read | OamAccess::ReadIncDec => b | (a & c),
+        };
+        self.set_oam_word(row, 0, word);
+        // the other 3 words are copied from the previous row
+        let prev = (row - 1) * 8;
+        self.oam.copy_within(prev + 2..prev + 8, row * 8 + 2);
+    }
+    fn oam_word(&self, row: usize, word: usize) -> u16 {
+        let i = row * 8 + word * 2;
+        u16::from_le_bytes([self.oam[i], self.oam[i + 1]])
+    }
+    fn set_oam_word(&mut self, row: usize, word: usize, val: u16) {
+        let i = row * 8 + word * 2;
+        self.oam[i..i + 2].copy_from_slice(&val.to_le_bytes());
+    }
+    /// VBK, the palette registers and OPRI; unmapped in DMG mode. The
+    /// palette RAM is inaccessible in mode 3.
+    pub fn read_cgb_reg(&self, addr: u16) -> u8 {
+        let locked = self.is_enable() && self.mode == Mode::Mode3;
+        let cgb = match &self.cgb {
+            Some(cgb) => cgb,
+            None => return 0xff,
+        };
+        match addr {
+            VBK => cgb.vbk | 0xfe,
+            BCPS => cgb.bcps | 0x40,
+            OCPS => cgb.ocps | 0x40,
+            BCPD | OCPD if locked => 0xff,
+            BCPD => cgb.bg_palettes[(cgb.bcps & 0x3f) as usize],
+            OCPD => cgb.obj_palettes[(cgb.ocps & 0x3f) as usize],
+            OPRI => cgb.opri | 0xfe,
+            _ => 0xff,
+        }
+    }
+    pub fn write_cgb_reg(&mut self, addr: u16, val: u8) {
+        let locked = self.is_enable() && self.mode == Mode::Mode3;
+        let cgb = match &mut self.cgb {
+            Some(cgb) => cgb,
+            None => return,
+        };
+        match addr {
+            VBK => cgb.vbk = val & 0x01,
+            BCPS => cgb.bcps = val & 0xbf,
+            OCPS => cgb.ocps = val & 0xbf,
+            BCPD => Cgb::write_palette(&mut cgb.bg_palettes, &mut cgb.bcps, val, locked),
+            OCPD => Cgb::write_palette(&mut cgb.obj_palettes, &mut cgb.ocps, val, locked),
+            OPRI => cgb.opri = val & 0x01,
+            _ => (),
+        }
+    }
     pub fn read_lcd_reg(&self, index: usize) -> u8 {
-        self.lcd_regs[index]
+        match index {
+            1 /* STAT - LCDC */ => self.lcd_regs[index] | 0x80,
+            _ => self.lcd_regs[index],
+        }
     }
-    pub fn write_lcd_reg(&mut self, index: usize, val: u8) {
-        self.lcd_regs[index] = val;
-        if index == 0 && val & 0x80 != 0 {
-            self.set_blank = true;
+    pub fn write_lcd_reg(&mut self, index: usize, val: u8, i_flg: &mut u8) {
+        match index {
+            0 /* LCDC - LCDC */ => {
+                let enabled = self.is_enable();
+                self.lcd_regs[index] = val;
+                match (enabled, self.is_enable()) {
+                    (true, false) => self.lcd_off(),
+                    (false, true) => self.lcd_on(),
+                    _ => (),
+                }
+            }
+            1 /* STAT - LCDC */ => {
+                // the mode and the coincidence flag are read-only
+                let stat = self.lcd_regs[index];
+                self.lcd_regs[index] = (val & 0x78) | (stat & 0x07);
+                // DMG: every source is enabled during the write, so a STAT
+                // interrupt is requested in HBlank, VBlank or on LY=LYC
+                if self.model != Model::Cgb
+                    && self.is_enable()
+                    && (matches!(self.mode, Mode::Mode0 | Mode::Mode1) || stat & 0x04 != 0)
+                {
+                    if !self.stat_int_prev {
+                        *i_flg |= 0x02;
+                    }
+                    self.stat_int_prev = true;
+                }
+            }
+            4 /* LY - LCDC */ => (), // read-only
+            _ => self.lcd_regs[index] = val,
         }
     }
+    fn lcd_off(&mut self) {
+        self.mode = Mode::Mode0;
+        self.dot = 0;
+        self.line = 0;
+        self.window_internal_line = None;
+        self.window_wrap = false;
+        self.stat_int_prev = false;
+        self.lcd_regs[(LY - LCDC) as usize] = 0;
+        self.lcd_regs[(STAT - LCDC) as usize] &= 0xf8;
+        self.set_blank = true;
+    }
+    fn lcd_on(&mut self) {
+        self.mode = Mode::Mode0;
+        self.dot = 0;
+        self.line = 0;
+        self.lcd_on_line = true;
+        self.blank_frame = true;
+    }
     fn is_enable(&self) -> bool {
         self.lcd_regs[0 /*(LCDC - LCDC)*/] & 0x80 != 0x00
     }
-    pub fn run(&mut self, io: &mut Io, i_flg: &mut u8) -> Result<(), String> {
+    /// HBlank started on a visible line since the last call (HBlank DMA).
+    pub fn take_hblank(&mut self) -> bool {
+        std::mem::take(&mut self.hblank)
+    }
+    pub fn run(&mut self, io: &mut impl LcdIF, i_flg: &mut u8) -> Result<(), String> {
         if self.is_enable() {
-            self.clock_m += 1;
-            let stat = self.lcd_regs[(STAT - LCDC) as usize];
-            match self.mode {
-                // OAM scan
-                Mode::Mode2 => {
-                    if self.clock_m == 1 {
-                        let wy = self.lcd_regs[(WY - LCDC) as usize] as usize;
-                        if self.line == wy {
-                            self.window_internal_line = Some(0);
-                        }
-                    }
-                    if self.clock_m >= 20 {
-                        self.clock_m = 0;
-                        self.mode = Mode::Mode3;
-                    }
+            self.check_wy();
+            for _ in 0..4 {
+                self.run_dot(io, i_flg);
+            }
+            self.update_stat(i_flg);
+        } else if self.set_blank {
+            self.set_blank = false;
+            for ly in 0..GFX_SIZE_Y {
+                Ppu::write_blank(ly, io, self.white());
+            }
+        }
+        Ok(())
+    }
+
+    /// Updates the mode and the coincidence flag, and requests a STAT
+    /// interrupt on a rising edge of the OR of the enabled sources, so a
+    /// source does not interrupt while another one keeps the line high.
+    fn update_stat(&mut self, i_flg: &mut u8) {
+        let ly = self.lcd_regs[(LY - LCDC) as usize];
+        let mut stat = self.lcd_regs[(STAT - LCDC) as usize] & 0xf8;
+        if ly == self.lcd_regs[(LYC - LCDC) as usize] {
+            stat |= 0x04
+        }
+        stat += self.mode as u8;
+        self.lcd_regs[(STAT - LCDC) as usize] = stat;
+        // the mode 2 source is also checked when VBlank starts
+        let oam_at_vblank = self.line == 144 && self.dot <= 4 && stat & 0x20 != 0;
+        let stat_int = set_stat_int(stat) || oam_at_vblank;
+        if stat_int && !self.stat_int_prev {
+            *i_flg |= 0x02;
+        }
+        self.stat_int_prev = stat_int;
+    }
+
+    fn run_dot(&mut self, io: &mut impl LcdIF, i_flg: &mut u8) {
+        self.dot += 1;
+        match self.mode {
+            // OAM scan
+            Mode::Mode2 => {
+                if self.dot >= OAM_SCAN_DOTS {
+                    let size = self.lcd_regs[0 /* LCDC - LCDC */] & 0x04 != 0;
+                    let objs = Obj::set_objs(size, &self.oam);
+                    let objs = Obj::filter_objs(objs, self.line, size, self.obj_limit());
+                    self.start_drawing(objs);
                 }
-                // Drawing pixels
-                Mode::Mode3 => {
-                    if self.clock_m >= 43 {
-                        self.clock_m = 0;
+            }
+            // Drawing pixels
+            Mode::Mode3 => {
+                if self.fast {
+                    if self.dot >= OAM_SCAN_DOTS + DRAWING_DOTS {
                         self.mode = Mode::Mode0;
-
+                        self.hblank = true;
                         // write a scanline to the framebuffer
                         self.write_a_scanline(io);
                     }
-                }
-                // Horizontal blank
-                Mode::Mode0 => {
-                    if self.clock_m >= 51 {
-                        self.clock_m = 0;
-                        self.line += 1;
-
-                        if self.line >= 144 {
-                            io.present();
-                            self.mode = Mode::Mode1;
-                            // VBlank interrupt
-                            *i_flg |= 0x01
-                        } else {
-                            self.mode = Mode::Mode2;
-                        }
-                        self.lcd_regs[(LY - LCDC) as usize] = self.line as u8;
+                } else if self.fifo.tick(
+                    &self.vram,
+                    &self.lcd_regs,
+                    self.cgb.as_ref(),
+                    &self.dmg_palettes,
+                    io,
+                ) {
+                    self.mode = Mode::Mode0;
+                    self.hblank = true;
+                    // the debug views are drawn by the scanline renderer
+                    self.write_debug_line(io);
+                    // the window line advances only on lines showing the window
+                    if self.fifo.window_drawn() {
+                        self.window_internal_line = self.window_internal_line.map(|l| l + 1);
                     }
+                    self.window_wrap = self.fifo.wx166();
                 }
-                // Vertical blank
-                Mode::Mode1 => {
-                    if self.clock_m >= 114 {
-                        self.clock_m = 0;
-                        self.line += 1;
-                        if self.line > 153 {
-                            self.mode = Mode::Mode2;
-                            self.line = 0;
-                            self.window_internal_line = None;
+            }
+            // Horizontal blank
+            Mode::Mode0 => {
+                if self.lcd_on_line {
+                    if self.dot >= OAM_SCAN_DOTS {
+                        self.lcd_on_line = false;
+                        self.start_drawing(Vec::new());
+                    }
+                } else if self.dot >= LINE_DOTS {
+                    self.dot = 0;
+                    self.line += 1;
+
+                    if self.line >= 144 {
+                        if self.blank_frame {
+                            self.blank_frame = false;
+                            for ly in 0..GFX_SIZE_Y {
+                                Ppu::write_blank(ly, io, self.white());
+                            }
                         }
-                        self.lcd_regs[(LY - LCDC) as usize] = self.line as u8;
+                        io.present();
+                        self.mode = Mode::Mode1;
+                        // VBlank interrupt
+                        *i_flg |= 0x01
+                    } else {
+                        self.mode = Mode::Mode2;
                     }
+                    self.lcd_regs[(LY - LCDC) as usize] = self.line as u8;
                 }
             }
-            // Update LCD status
-            let mut stat = stat & 0xf8; // masked
-            if self.line as u8 == self.lcd_regs[(LYC - LCDC) as usize] {
-                stat |= 0x04
-            }
-            stat += self.mode as u8;
-            self.lcd_regs[(STAT - LCDC) as usize] = stat;
-            // STAT interrupt
-            let stat_int = set_stat_int(stat);
-            if stat_int && !self.stat_int_prev {
-                *i_flg |= 0x02;
+            // Vertical blank
+            Mode::Mode1 => {
+                if self.line == 153 && self.dot == LINE_153_LY_DOTS {
+                    self.lcd_regs[(LY - LCDC) as usize] = 0;
+                }
+                if self.dot >= LINE_DOTS {
+                    self.dot = 0;
+                    self.line += 1;
+                    if self.line > 153 {
+                        self.mode = Mode::Mode2;
+                        self.line = 0;
+                        self.window_internal_line = None;
+                        self.window_wrap = false;
+                    }
+                    self.lcd_regs[(LY - LCDC) as usize] = self.line as u8;
+                }
             }
-            self.stat_int_prev = stat_int;
-            //
-        } else if self.set_blank {
-            self.mode = Mode::Mode2;
-            self.clock_m = 0;
-            self.line = 0;
-            self.window_internal_line = None;
-            self.stat_int_prev = false;
-            self.set_blank = false;
+        }
+    }
 
-            for ly in 0..GFX_SIZE_Y {
-                Ppu::write_blank(ly, io, GfxColor::W);
-            }
+    /// WY is compared with LY all through the frame, the window can start on
+    /// the following lines once they matched.
+    fn check_wy(&mut self) {
+        let wy = self.lcd_regs[(WY - LCDC) as usize] as usize;
+        if self.window_internal_line.is_none() && self.line == wy && self.line < GFX_SIZE_Y {
+            self.window_internal_line = Some(0);
+        }
+    }
+    fn start_drawing(&mut self, objs: Vec<ObjAttr>) {
+        self.mode = Mode::Mode3;
+        if !self.fast {
+            let scx = self.lcd_regs[(SCX - LCDC) as usize];
+            let win_line = self.window_internal_line;
+            let full_window = std::mem::take(&mut self.window_wrap);
+            self.fifo.start(self.line, win_line, full_window, objs, scx);
         }
-        Ok(())
     }
 
-    fn write_a_scanline(&mut self, io: &mut Io) {
+    fn write_a_scanline(&mut self, io: &mut impl LcdIF) {
+        let (line, _, window_drawn) =
+            self.render_line(self.window_internal_line, LayerDebug::default());
+        io.gfx()[self.line * GFX_SIZE_X..][..GFX_SIZE_X].copy_from_slice(&line);
+        self.write_debug_line(io);
+        if window_drawn {
+            self.window_internal_line = self.window_internal_line.map(|l| l + 1);
+        }
+    }
+    /// Sends the current line with the debug views, over the clean one in
+    /// `gfx`.
+    fn write_debug_line(&self, io: &mut impl LcdIF) {
+        if self.layer_debug.active() {
+            let (line, marks, _) = self.render_line(self.window_internal_line, self.layer_debug);
+            io.debug_line(self.line, &line, &marks);
+        }
+    }
+    /// Pixels of the current line, with the window at line `win_line`, the
+    /// overlays of `debug`, and whether the window is on the line
+    fn render_line(
+        &self,
+        win_line: Option<usize>,
+        debug: LayerDebug,
+    ) -> ([Rgb; GFX_SIZE_X], [Mark; GFX_SIZE_X], bool) {
         let lcdc = self.lcd_regs[0 /* LCDC - LCDC */];
+        let ly = self.line;
 
-        if lcdc & 0x01 != 0 {
-            let color_id = 0;
-            let palette_data = self.lcd_regs[(BGP - LCDC) as usize];
-            let color = id2color(palette_data, color_id);
-            Ppu::write_blank(self.line, io, color); // both background and window bcome blank (white)
-        } else {
-            Ppu::write_blank(self.line, io, GfxColor::W); // both background and window bcome blank (white)
+        let mut bg = [BgPixel::default(); GFX_SIZE_X];
+        let mut window_drawn = false;
+        // first pixel of the window
+        let mut window_x = GFX_SIZE_X;
+        // LCDC bit 0 is the BG & window enable on DMG, the BG priority on CGB
+        if lcdc & 0x01 != 0 || self.cgb.is_some() {
+            let wx = self.lcd_regs[(WX - LCDC) as usize] as usize;
+            // wx - 7 <= GFX_SIZE_X - 1, Window enable: ON
+            let window = win_line.filter(|_| wx <= GFX_SIZE_X - 1 + 7 && lcdc & 0x20 != 0);
+            window_drawn = window.is_some();
+            let window = window.filter(|_| !debug.hide_window);
+            // the window hides the background right of wx - 7
+            let bg_end = match window {
+                Some(_) => wx.saturating_sub(7),
+                None => GFX_SIZE_X,
+            };
+            if !debug.hide_bg {
+                self.write_bg(bg_end, &mut bg);
+            }
+            if let Some(win_line) = window {
+                self.write_window(win_line, &mut bg);
+                window_x = bg_end;
+            }
         }
-
-        let ly = self.line;
-        let obj = Obj::new(ly, &self.vram, &self.oam, &self.lcd_regs);
-        if lcdc & 0x02 != 0 {
+        let obj = if lcdc & 0x02 != 0 && !debug.hide_obj {
             // OBJ enable: ON
-            obj.write_obj_before_gb(ly, io);
-        }
-        if lcdc & 0x01 != 0 {
-            // BG & Window enable priority: ON
-            self.write_bg(io);
-
-            if let Some(win_line) = self.window_internal_line {
-                let wx = self.lcd_regs[(WX - LCDC) as usize] as usize;
-                if wx <= GFX_SIZE_X - 1 + 7 && lcdc & 0x20 != 0 {
-                    // wx - 7 <= GFX_SIZE_X - 1
-                    // Window enable: ON
-                    self.write_window(win_line, io);
-                    self.window_internal_line = Some(win_line + 1);
-                }
+            let cgb = self.cgb.as_ref();
+            Obj::new(
+                ly,
+                &self.vram,
+                &self.oam,
+                &self.lcd_regs,
+                cgb,
+                self.obj_limit(),
+            )
+            .line
+        } else {
+            [ObjPixel::default(); GFX_SIZE_X]
+        };
+        let mut line = [Rgb(0, 0, 0); GFX_SIZE_X];
+        let mut marks = [Mark::None; GFX_SIZE_X];
+        for lx in 0..GFX_SIZE_X {
+            line[lx] = mix(
+                bg[lx],
+                obj[lx],
+                &self.lcd_regs,
+                self.cgb.as_ref(),
+                &self.dmg_palettes,
+            );
+            if debug.highlight {
+                let layer = if obj_shown(bg[lx], obj[lx], lcdc) {
+                    Layer::Obj
+                } else if lx >= window_x {
+                    Layer::Window
+                } else {
+                    Layer::Bg
+                };
+                marks[lx] = Mark::Tint(layer);
+            }
+            if debug.priority && obj[lx].color_id != 0 && bg_over_obj(bg[lx], obj[lx], lcdc) {
+                marks[lx] = Mark::Color(debug::HIDDEN);
             }
         }
-        if lcdc & 0x02 != 0 {
-            // OBJ enable: ON
-            obj.write_obj_after_gb(ly, io);
+        if debug.obj_boxes {
+            debug::obj_boxes(&mut marks, &self.oam, ly, lcdc & 0x04 != 0);
         }
+        (line, marks, window_drawn)
     }
 
-    fn write_blank(ly: usize, io: &mut Io, color: GfxColor) {
+    fn write_blank(ly: usize, io: &mut impl LcdIF, color: Rgb) {
         for lx in 0..GFX_SIZE_X {
-            io.gfx[ly * GFX_SIZE_X + lx] = color
+            io.gfx()[ly * GFX_SIZE_X + lx] = color
+        }
+    }
+    /// Color of the blank screen
+    fn white(&self) -> Rgb {
+        match self.cgb {
+            Some(_) => Rgb::from_rgb555(0x7fff),
+            None => self.dmg_palettes.bg[0],
         }
     }
 
-    fn write_bg(&self, io: &mut Io) {
+    fn write_bg(&self, end: usize, line: &mut [BgPixel]) {
         let lcdc = self.lcd_regs[0 /* LCDC - LCDC */];
-        let tile_map_area_addr = if lcdc & 0x08 == 0x08 { 0x9c00 } else { 0x9800 };
+        let tile_map_index = if lcdc & 0x08 == 0x08 { 0x1c00 } else { 0x1800 };
         let scy = self.lcd_regs[(SCY - LCDC) as usize] as usize;
         let scx = self.lcd_regs[(SCX - LCDC) as usize] as usize;
         let ly = self.line;
 
-        for lx in 0..GFX_SIZE_X {
+        for (lx, pixel) in line.iter_mut().enumerate().take(end) {
             let y = (scy + ly) % 256;
             let x = (scx + lx) % 256;
-
-            // get tile map address
-            let y_tile = y / 8;
-            let x_tile = x / 8;
-            let tile_map_addr = tile_map_area_addr + (y_tile * 32 + x_tile) as u16;
-            // get tile ID
-            let tile_id = self.vram[(tile_map_addr - VRAM) as usize] as u16;
-            // get tile data address
-            let tile_data_addr = if lcdc & 0x10 == 0x10 {
-                0x8000 + tile_id * 16
-            } else if tile_id < 128 {
-                0x9000 + tile_id * 16
-            } else {
-                0x8800 + (tile_id - 128) * 16
-            };
-            // get color ID
-            let j = (y % 8) as u16;
-            let i = (x % 8) as u16;
-            let tile_data_index = (tile_data_addr - VRAM) as usize;
-            let color_id = get_a_color_id(i, j, &self.vram[tile_data_index..tile_data_index + 16]);
-
-            if color_id != 0 {
-                // set color for gfx array
-                let palette_data = self.lcd_regs[(BGP - LCDC) as usize];
-                let color = id2color(palette_data, color_id);
-                io.gfx[ly * GFX_SIZE_X + lx] = color;
-            }
+            let map_index = tile_map_index + (y / 8) * 32 + x / 8;
+            *pixel = self.bg_pixel(lcdc, map_index, x % 8, y);
         }
     }
 
-    fn write_window(&self, win_line: usize, io: &mut Io) {
+    fn write_window(&self, win_line: usize, line: &mut [BgPixel]) {
         let lcdc = self.lcd_regs[0 /* LCDC - LCDC */];
-        let tile_map_area_addr = if lcdc & 0x40 == 0x40 { 0x9c00 } else { 0x9800 };
+        let tile_map_index = if lcdc & 0x40 == 0x40 { 0x1c00 } else { 0x1800 };
         let wx = self.lcd_regs[(WX - LCDC) as usize] as usize;
-        let ly = self.line;
 
-        for lx in 0..GFX_SIZE_X {
-            if lx + 7 < wx {
-                // lx < wx - 7
-                continue;
-            }
+        // lx >= wx - 7
+        for (lx, pixel) in line.iter_mut().enumerate().skip(wx.saturating_sub(7)) {
             let x = lx + 7 - wx; // x = lx - (wx - 7)
+            let map_index = tile_map_index + (win_line / 8) * 32 + x / 8;
+            *pixel = self.bg_pixel(lcdc, map_index, x % 8, win_line);
+        }
+    }
 
-            // get tile map address
-            let y_tile = win_line / 8;
-            let x_tile = x / 8;
-            let tile_map_addr = tile_map_area_addr + (y_tile * 32 + x_tile) as u16;
-            // get tile ID
-            let tile_id = self.vram[(tile_map_addr - VRAM) as usize] as u16;
-            // get tile data address
-            let tile_data_addr = if lcdc & 0x10 == 0x10 {
-                0x8000 + tile_id * 16
-            } else if tile_id < 128 {
-                0x9000 + tile_id * 16
-            } else {
-                0x8800 + (tile_id - 128) * 16
-            };
-            // get color ID
-            let j = (win_line % 8) as u16;
-            let i = (x % 8) as u16;
-            let tile_data_index = (tile_data_addr - VRAM) as usize;
-            let color_id = get_a_color_id(i, j, &self.vram[tile_data_index..tile_data_index + 16]);
-
-            if color_id != 0 {
-                // set color for gfx array
-                let palette_data = self.lcd_regs[(BGP - LCDC) as usize];
-                let color = id2color(palette_data, color_id);
-                io.gfx[ly * GFX_SIZE_X + lx] = color;
-            }
+    /// Pixel (x, y % 8) of the tile at `map_index` in a tile map
+    fn bg_pixel(&self, lcdc: u8, map_index: usize, x: usize, y: usize) -> BgPixel {
+        let attr = bg_attr(&self.vram, self.cgb.is_some(), map_index);
+        let index = bg_row_index(lcdc, self.vram[map_index], attr, y);
+        let x = if attr & 0x20 != 0 { 7 - x } else { x };
+        BgPixel {
+            color_id: get_a_color_id(x as u16, 0, &self.vram[index..index + 2]),
+            attr,
         }
     }
 }
-#[derive(Copy, Clone)]
-struct PixelInfo {
-    bg_over_obj: bool,
-    color: GfxColor,
+
+/// Background or window pixel
+#[derive(Copy, Clone, Default)]
+struct BgPixel {
+    color_id: u8,
+    // CGB tile map attributes
+    attr: u8,
+}
+
+/// Object pixel, transparent with color ID 0
+#[derive(Copy, Clone, Default)]
+struct ObjPixel {
+    color_id: u8,
+    // OAM attributes
+    attr: u8,
+    // index in the objects of the line
+    order: usize,
 }
+
 struct Obj {
-    line: [Option<PixelInfo>; GFX_SIZE_X],
+    line: [ObjPixel; GFX_SIZE_X],
 }
 
 struct ObjAttr {
+    // in OAM
+    index: usize,
     y: u8,
     x: u8,
     tile_index: u8, // if 8 * 16, indices are tile_index & tile_index +1
     attr: u8,
 }
 
+impl ObjAttr {
+    /// Lines of the screen covered by the object, maybe none
+    fn lines(&self, size: bool) -> std::ops::Range<isize> {
+        let y_min = self.y as isize - 16;
+        y_min..y_min + if size { 16 } else { 8 }
+    }
+    fn on_line(&self, ly: usize, size: bool) -> bool {
+        self.lines(size).contains(&(ly as isize))
+    }
+}
+
 impl Obj {
-    fn new(ly: usize, vram: &[u8], oam: &[u8], lcd_regs: &[u8]) -> Obj {
+    fn new(
+        ly: usize,
+        vram: &[u8],
+        oam: &[u8],
+        lcd_regs: &[u8],
+        cgb: Option<&Cgb>,
+        limit: usize,
+    ) -> Obj {
         let size = {
             let lcdc = lcd_regs[0 /* LCDC - LCDC */];
             lcdc & 0x04 != 0
         };
         let objs = Obj::set_objs(size, oam);
-        let objs = Obj::filter_objs(objs, ly, size);
-        let mut objs = Obj::sort_objs(objs);
+        let objs = Obj::filter_objs(objs, ly, size, limit);
+        // the objects drawn last have priority: the smallest X on DMG, the
+        // first in OAM on CGB
+        let mut objs = if x_priority(cgb) {
+            Obj::sort_objs(objs)
+        } else {
+            objs
+        };
         objs.reverse();
 
-        let mut line = [None; GFX_SIZE_X];
+        let mut line = [ObjPixel::default(); GFX_SIZE_X];
         for obj in objs {
-            Obj::write_a_obj(&mut line, obj, ly, size, vram, lcd_regs);
+            Obj::write_a_obj(&mut line, obj, ly, size, vram, cgb.is_some());
         }
         Obj { line }
     }
@@ -354,6 +733,7 @@ impl Obj {
             };
             let attr = oam[index + 3];
             objs.push(ObjAttr {
+                index: i,
                 y,
                 x,
                 tile_index,
@@ -362,15 +742,13 @@ impl Obj {
         }
         objs
     }
-    fn filter_objs(objs: Vec<ObjAttr>, ly: usize, size: bool) -> Vec<ObjAttr> {
-        let ly = ly as isize;
+    /// The first `limit` objects on line `ly`, in OAM order
+    fn filter_objs(objs: Vec<ObjAttr>, ly: usize, size: bool, limit: usize) -> Vec<ObjAttr> {
         let mut filterd = Vec::new();
         for obj in objs {
-            let y_min = obj.y as isize - 16;
-            let y_max = if size { y_min + 16 } else { y_min + 8 } - 1;
-            if ly >= y_min && ly <= y_max {
+            if obj.on_line(ly, size) {
                 filterd.push(obj);
-                if filterd.len() >= 10 {
+                if filterd.len() >= limit {
                     break;
                 }
             }
@@ -379,16 +757,16 @@ impl Obj {
     }
     fn sort_objs(objs: Vec<ObjAttr>) -> Vec<ObjAttr> {
         let mut sorted = objs;
-        sorted.sort_by(|a, b| a.x.cmp(&b.x));
+        sorted.sort_by_key(|a| a.x);
         sorted
     }
     fn write_a_obj(
-        line: &mut [Option<PixelInfo>],
+        line: &mut [ObjPixel],
         obj: ObjAttr,
         ly: usize,
         size: bool,
         vram: &[u8],
-        lcd_regs: &[u8],
+        cgb: bool,
     ) {
         let ly = ly as isize;
         // j & tile adder
@@ -423,8 +801,7 @@ impl Obj {
             };
             (j, index)
         };
-        let tile_data_index = tile_data_index as usize;
-        let bg_over_obj = obj.attr & 0x80 != 0;
+        let tile_data_index = tile_data_index as usize + obj_bank(obj.attr, cgb);
         // i
         let x = obj.x as isize - 8;
         for i0 in 0..8 {
@@ -435,7 +812,10 @@ impl Obj {
                 i0
             };
             let lx = x + i0;
-            if lx < 0 || lx > GFX_SIZE_X as isize - 1 {
+            if lx < 0 {
+                continue;
+            }
+            if lx > GFX_SIZE_X as isize - 1 {
                 break;
             }
             let lx = lx as usize;
@@ -449,35 +829,72 @@ impl Obj {
 
             // set color to line[]
             if color_id != 0 {
-                // color id -> color
-                let palette_data = if obj.attr & 0x10 != 0 {
-                    lcd_regs[(OBP1 - LCDC) as usize]
-                } else {
-                    lcd_regs[(OBP0 - LCDC) as usize]
+                line[lx] = ObjPixel {
+                    color_id,
+                    attr: obj.attr,
+                    order: 0,
                 };
-                let color = id2color(palette_data, color_id);
-                line[lx] = Some(PixelInfo { bg_over_obj, color });
             }
         }
     }
+}
 
-    fn write_obj_before_gb(&self, ly: usize, io: &mut Io) {
-        for lx in 0..GFX_SIZE_X {
-            if let Some(pixel_info) = self.line[lx] {
-                if pixel_info.bg_over_obj {
-                    io.gfx[ly * GFX_SIZE_X + lx] = pixel_info.color;
-                }
-            }
+/// Color of a pixel from the background and the object pixels at its position
+fn mix(bg: BgPixel, obj: ObjPixel, lcd_regs: &[u8], cgb: Option<&Cgb>, dmg: &LayerPalettes) -> Rgb {
+    let reg = |addr: u16| lcd_regs[(addr - LCDC) as usize];
+    let lcdc = reg(LCDC);
+    let obj_shown = obj_shown(bg, obj, lcdc);
+    match cgb {
+        Some(cgb) if obj_shown => Cgb::color(&cgb.obj_palettes, obj.attr, obj.color_id),
+        Some(cgb) => Cgb::color(&cgb.bg_palettes, bg.attr, bg.color_id),
+        None if obj_shown => {
+            let (obp, colors) = if obj.attr & 0x10 != 0 {
+                (reg(OBP1), &dmg.obj1)
+            } else {
+                (reg(OBP0), &dmg.obj0)
+            };
+            colors[id2color(obp, obj.color_id) as usize]
         }
+        None if lcdc & 0x01 != 0 => dmg.bg[id2color(reg(BGP), bg.color_id) as usize],
+        None => dmg.bg[0],
     }
-    fn write_obj_after_gb(&self, ly: usize, io: &mut Io) {
-        for lx in 0..GFX_SIZE_X {
-            if let Some(pixel_info) = self.line[lx] {
-                if !pixel_info.bg_over_obj {
-                    io.gfx[ly * GFX_SIZE_X + lx] = pixel_info.color;
-                }
-            }
-        }
+}
+/// The object pixel is drawn over the background pixel
+fn obj_shown(bg: BgPixel, obj: ObjPixel, lcdc: u8) -> bool {
+    obj.color_id != 0 && lcdc & 0x02 != 0 && !bg_over_obj(bg, obj, lcdc)
+}
+/// The BG-over-OBJ bit of the object or (CGB) of the tile hides the object
+/// behind BG colours 1-3. With LCDC bit 0 cleared, objects are always on top
+/// of the background.
+fn bg_over_obj(bg: BgPixel, obj: ObjPixel, lcdc: u8) -> bool {
+    let bg_id = if lcdc & 0x01 != 0 { bg.color_id } else { 0 };
+    bg_id != 0 && (obj.attr | bg.attr) & 0x80 != 0
+}
+/// Objects are prioritized by X, instead of by OAM index (CGB mode)
+fn x_priority(cgb: Option<&Cgb>) -> bool {
+    cgb.is_none_or(|cgb| cgb.opri & 0x01 != 0)
+}
+/// CGB attributes of the tile map entry at `map_index`, 0 in DMG mode
+fn bg_attr(vram: &[u8], cgb: bool, map_index: usize) -> u8 {
+    if cgb {
+        vram[VRAM_BANK_SIZE + map_index]
+    } else {
+        0
+    }
+}
+/// Index in VRAM of row `y % 8` of a background tile, in the bank and with
+/// the Y flip of the CGB attributes
+fn bg_row_index(lcdc: u8, tile_id: u8, attr: u8, y: usize) -> usize {
+    let y = if attr & 0x40 != 0 { 7 - y % 8 } else { y % 8 };
+    let bank = if attr & 0x08 != 0 { VRAM_BANK_SIZE } else { 0 };
+    bank + tile_data_index(lcdc, tile_id) + y * 2
+}
+/// Offset of the VRAM bank of an object's tile
+fn obj_bank(attr: u8, cgb: bool) -> usize {
+    if cgb && attr & 0x08 != 0 {
+        VRAM_BANK_SIZE
+    } else {
+        0
     }
 }
 
@@ -489,6 +906,18 @@ fn set_stat_int(stat: u8) -> bool {
         || ((stat & 0x20 != 0) && (mode == 2))
         || ((stat & 0x40 != 0) && (eq != 0))
 }
+/// Index in VRAM of the tile data for `tile_id`, addressed as selected by
+/// LCDC bit 4
+fn tile_data_index(lcdc: u8, tile_id: u8) -> usize {
+    let tile_id = tile_id as usize;
+    if lcdc & 0x10 != 0 {
+        tile_id * 16
+    } else if tile_id < 128 {
+        0x1000 + tile_id * 16
+    } else {
+        0x0800 + (tile_id - 128) * 16
+    }
+}
 fn get_a_color_id(i: u16, j: u16, tile_data: &[u8]) -> u8 {
     let b0 = (tile_data[(2 * j) as usize] >> (7 - i)) & 0x01;
     let b1 = (tile_data[(2 * j + 1) as usize] >> (7 - i)) & 0x01;
@@ -508,3 +937,273 @@ fn id2color(palette_data: u8, color_id: u8) -> GfxColor {
         _ => panic!(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::Screen;
+    use crate::palette::Combo;
+
+    fn set_reg(ppu: &mut Ppu, addr: u16, val: u8, i_flg: &mut u8) {
+        ppu.write_lcd_reg((addr - LCDC) as usize, val, i_flg);
+    }
+    fn reg(ppu: &Ppu, addr: u16) -> u8 {
+        ppu.read_lcd_reg((addr - LCDC) as usize)
+    }
+    /// Runs M-cycles until line `ly` dot `dot`, returns IF.
+    fn run_to(ppu: &mut Ppu, screen: &mut Screen, ly: usize, dot: usize) -> u8 {
+        let mut i_flg = 0;
+        while !(ppu.line == ly && ppu.dot >= dot) {
+            ppu.run(screen, &mut i_flg).unwrap();
+        }
+        i_flg
+    }
+
+    #[test]
+    fn line_153() {
+        let mut screen = Screen::new();
+        let mut ppu = Ppu::new();
+        set_reg(&mut ppu, LYC, 0, &mut 0);
+        set_reg(&mut ppu, STAT, 0x40, &mut 0);
+        run_to(&mut ppu, &mut screen, 152, 452);
+        let i_flg = run_to(&mut ppu, &mut screen, 153, 4);
+        assert_eq!(0, reg(&ppu, LY));
+        // LY=LYC=0 interrupt on line 153, not again on line 0
+        assert_eq!(0x02, i_flg & 0x02);
+        assert_eq!(0x04, reg(&ppu, STAT) & 0x04);
+        let i_flg = run_to(&mut ppu, &mut screen, 0, 8);
+        assert_eq!(0, i_flg & 0x02);
+    }
+    #[test]
+    fn lcd_on() {
+        let mut screen = Screen::new();
+        let mut ppu = Ppu::new();
+        set_reg(&mut ppu, LCDC, 0x00, &mut 0);
+        assert_eq!(0x80, reg(&ppu, STAT));
+        assert_eq!(0, reg(&ppu, LY));
+        set_reg(&mut ppu, LCDC, 0x93, &mut 0);
+        // no OAM scan on the first line
+        run_to(&mut ppu, &mut screen, 0, 4);
+        assert_eq!(0, reg(&ppu, STAT) & 0x03);
+        run_to(&mut ppu, &mut screen, 0, OAM_SCAN_DOTS + 4);
+        assert_eq!(3, reg(&ppu, STAT) & 0x03);
+        // the first frame is blank
+        ppu.vram[..16].fill(0xff);
+        set_reg(&mut ppu, BGP, 0xff, &mut 0);
+        run_to(&mut ppu, &mut screen, 144, 4);
+        assert_eq!(1, screen.frames);
+        assert!(screen.gfx.iter().all(|c| *c == GfxColor::W.rgb()));
+        run_to(&mut ppu, &mut screen, 0, 4);
+        run_to(&mut ppu, &mut screen, 144, 4);
+        assert!(screen.gfx.iter().all(|c| *c == GfxColor::B.rgb()));
+    }
+    #[test]
+    fn stat_irq() {
+        let mut screen = Screen::new();
+        let mut ppu = Ppu::new();
+        set_reg(&mut ppu, LYC, 0x80, &mut 0);
+        // the STAT write glitch in HBlank, not in mode 2
+        run_to(&mut ppu, &mut screen, 1, 4);
+        let mut i_flg = 0;
+        set_reg(&mut ppu, STAT, 0x00, &mut i_flg);
+        assert_eq!(0, i_flg);
+        run_to(&mut ppu, &mut screen, 1, 400);
+        set_reg(&mut ppu, STAT, 0x00, &mut i_flg);
+        assert_eq!(0x02, i_flg);
+        // the mode 2 source at the start of VBlank
+        set_reg(&mut ppu, STAT, 0x20, &mut 0);
+        run_to(&mut ppu, &mut screen, 143, 400);
+        assert_eq!(0x02, run_to(&mut ppu, &mut screen, 144, 4) & 0x02);
+        // HBlank then mode 2 keeps the line high: one interrupt per line
+        set_reg(&mut ppu, STAT, 0x28, &mut 0);
+        run_to(&mut ppu, &mut screen, 10, 200);
+        assert_eq!(0x02, run_to(&mut ppu, &mut screen, 10, 300) & 0x02);
+        assert_eq!(0, run_to(&mut ppu, &mut screen, 11, 8) & 0x02);
+        // no glitch on CGB, DMG carts included
+        ppu.set_model(Model::Cgb);
+        set_reg(&mut ppu, STAT, 0x00, &mut 0);
+        run_to(&mut ppu, &mut screen, 12, 400);
+        let mut i_flg = 0;
+        set_reg(&mut ppu, STAT, 0x00, &mut i_flg);
+        assert_eq!(0, i_flg);
+        ppu.set_cgb(true);
+        run_to(&mut ppu, &mut screen, 13, 400);
+        set_reg(&mut ppu, STAT, 0x00, &mut i_flg);
+        assert_eq!(0, i_flg);
+    }
+    #[test]
+    fn oam_bug() {
+        let mut ppu = Ppu::new();
+        let init = |ppu: &mut Ppu| {
+            ppu.oam.fill(0);
+            ppu.set_oam_word(0, 0, 0x00f0);
+            ppu.set_oam_word(0, 1, 0x1234);
+            ppu.set_oam_word(0, 2, 0x0f0f);
+            ppu.set_oam_word(1, 0, 0x3333);
+        };
+        // mode 2, dot 0: the access is in the next M-cycle, row 1
+        init(&mut ppu);
+        ppu.oam_bug(OamAccess::Write, 1);
+        assert_eq!(0x0333, ppu.oam_word(1, 0));
+        assert_eq!(0x1234, ppu.oam_word(1, 1));
+        assert_eq!(0x0f0f, ppu.oam_word(1, 2));
+        init(&mut ppu);
+        ppu.oam_bug(OamAccess::Read, 1);
+        assert_eq!(0x03f3, ppu.oam_word(1, 0));
+        // row 0 is never corrupted, nor outside mode 2
+        init(&mut ppu);
+        ppu.oam_bug(OamAccess::Write, 0);
+        assert_eq!(0x3333, ppu.oam_word(1, 0));
+        ppu.mode = Mode::Mode0;
+        ppu.oam_bug(OamAccess::Write, 1);
+        assert_eq!(0x3333, ppu.oam_word(1, 0));
+
+        // read with increment at row 5: row 4 is corrupted with rows 3 and 5,
+        // then copied to them
+        ppu.mode = Mode::Mode2;
+        ppu.dot = 16;
+        ppu.oam.fill(0);
+        ppu.set_oam_word(3, 0, 0x00ff);
+        ppu.set_oam_word(3, 2, 0x0ff0);
+        ppu.set_oam_word(4, 0, 0x1111);
+        ppu.set_oam_word(4, 3, 0xabcd);
+        ppu.set_oam_word(5, 0, 0x0f0f);
+        ppu.oam_bug(OamAccess::ReadIncDec, 1);
+        // (b & (a | c | d)) | (a & c & d)
+        assert_eq!(0x0111, ppu.oam_word(4, 0));
+        assert_eq!(ppu.oam[32..40], ppu.oam[24..32]);
+        assert_eq!(ppu.oam[32..40], ppu.oam[40..48]);
+    }
+    #[test]
+    fn cgb_mode() {
+        let mut screen = Screen::new();
+        let mut ppu = Ppu::new();
+        // unmapped in DMG mode
+        assert_eq!(0xff, ppu.read_cgb_reg(BCPS));
+        ppu.set_cgb(true);
+        set_reg(&mut ppu, LCDC, 0x93, &mut 0);
+        // tile 0 in bank 1: color 1 at the left of row 0
+        ppu.write_cgb_reg(VBK, 0x01);
+        assert_eq!(0xff, ppu.read_cgb_reg(VBK));
+        ppu.write_vram(0x0000, 0x80);
+        // tile map attributes: palette 3, bank 1, X flip
+        ppu.write_vram(0x1800, 0x2b);
+        ppu.write_cgb_reg(VBK, 0x00);
+        // object 0 with tile 1 (bank 0) in color 3
+        ppu.vram[0x10..0x20].fill(0xff);
+        ppu.oam[..4].copy_from_slice(&[16, 8, 1, 0]);
+        // BG palette 3: red, green; OBJ palette 0, color 3: white
+        ppu.write_cgb_reg(BCPS, 0x98);
+        for b in [0x1f, 0x00, 0xe0, 0x03] {
+            ppu.write_cgb_reg(BCPD, b);
+        }
+        assert_eq!(0xdc, ppu.read_cgb_reg(BCPS));
+        ppu.write_cgb_reg(OCPS, 0x86);
+        ppu.write_cgb_reg(OCPD, 0xff);
+        ppu.write_cgb_reg(OCPD, 0x7f);
+        ppu.oam[1] = 0; // hidden
+        run_to(&mut ppu, &mut screen, 1, 0);
+        let (red, green, white) = (Rgb(0xff, 0, 0), Rgb(0, 0xff, 0), Rgb(0xff, 0xff, 0xff));
+        assert_eq!((red, green), (screen.gfx[0], screen.gfx[7]));
+
+        // the object is over color 1, unless the tile has priority
+        ppu.oam[1] = 8;
+        run_to(&mut ppu, &mut screen, 0, 4);
+        run_to(&mut ppu, &mut screen, 1, 0);
+        assert_eq!((white, white), (screen.gfx[0], screen.gfx[7]));
+        ppu.write_cgb_reg(VBK, 0x01);
+        ppu.write_vram(0x1800, 0xab);
+        run_to(&mut ppu, &mut screen, 0, 4);
+        run_to(&mut ppu, &mut screen, 1, 0);
+        assert_eq!((white, green), (screen.gfx[0], screen.gfx[7]));
+
+        // palette RAM is locked in mode 3, the address still increments
+        run_to(&mut ppu, &mut screen, 1, OAM_SCAN_DOTS + 4);
+        ppu.write_cgb_reg(BCPS, 0x80);
+        ppu.write_cgb_reg(BCPD, 0x12);
+        assert_eq!(0xff, ppu.read_cgb_reg(BCPD));
+        assert_eq!(0xc1, ppu.read_cgb_reg(BCPS));
+        run_to(&mut ppu, &mut screen, 2, 0);
+        ppu.write_cgb_reg(BCPS, 0x00);
+        assert_eq!(0xff, ppu.read_cgb_reg(BCPD));
+    }
+    #[test]
+    fn dmg_palettes() {
+        let mut screen = Screen::new();
+        let mut ppu = Ppu::new();
+        ppu.set_dmg_palettes(Combo::UpA.palettes());
+        set_reg(&mut ppu, LCDC, 0x93, &mut 0);
+        set_reg(&mut ppu, BGP, 0xe4, &mut 0);
+        set_reg(&mut ppu, OBP0, 0xe4, &mut 0);
+        set_reg(&mut ppu, OBP1, 0xe4, &mut 0);
+        // BG shade 1 at the left, objects 0 and 1 in shade 2 with OBP0, OBP1
+        ppu.write_vram(0x0000, 0xff);
+        ppu.vram[0x11] = 0xff;
+        ppu.oam[..8].copy_from_slice(&[16, 16, 1, 0, 16, 24, 1, 0x10]);
+        run_to(&mut ppu, &mut screen, 1, 0);
+        let p = Combo::UpA.palettes();
+        assert_eq!(p.bg[1], screen.gfx[0]);
+        assert_eq!(p.obj0[2], screen.gfx[8]);
+        assert_eq!(p.obj1[2], screen.gfx[16]);
+    }
+    #[test]
+    fn layer_debug() {
+        let (lg, dg, b) = (GfxColor::LG.rgb(), GfxColor::DG.rgb(), GfxColor::B.rgb());
+        for fast in [true, false] {
+            let line = |debug: LayerDebug| {
+                let mut screen = Screen::new();
+                let mut ppu = Ppu::new();
+                ppu.set_fast(fast);
+                *ppu.layer_debug_mut() = debug;
+                // BG tile 0 in color 1, window tile 2 in color 3 from x = 80
+                for (addr, val) in [(LCDC, 0xf3), (BGP, 0xe4), (OBP0, 0xe4), (WX, 87)] {
+                    set_reg(&mut ppu, addr, val, &mut 0);
+                }
+                ppu.vram[0x00] = 0xff;
+                ppu.vram[0x20..0x22].fill(0xff);
+                ppu.vram[0x1c00..0x1c20].fill(2);
+                // objects in color 2 at x = 16 (behind the BG) and x = 32
+                ppu.vram[0x11] = 0xff;
+                ppu.oam[..8].copy_from_slice(&[16, 24, 1, 0x80, 16, 40, 1, 0x00]);
+                run_to(&mut ppu, &mut screen, 1, 0);
+                assert_eq!(Some(1), ppu.window_internal_line);
+                [0, 16, 32, 100].map(|x| screen.gfx[x])
+            };
+            let debug = LayerDebug::default();
+            assert_eq!([lg, lg, dg, b], line(debug));
+            let priority = LayerDebug {
+                priority: true,
+                ..debug
+            };
+            assert_eq!(debug::HIDDEN, line(priority)[1]);
+            let hidden = LayerDebug {
+                hide_bg: true,
+                hide_window: true,
+                hide_obj: true,
+                ..debug
+            };
+            assert_eq!([GfxColor::W.rgb(); 4], line(hidden));
+            let hidden = LayerDebug {
+                hide_window: true,
+                ..debug
+            };
+            assert_eq!(lg, line(hidden)[3]);
+            let highlight = LayerDebug {
+                highlight: true,
+                ..debug
+            };
+            let expected = [
+                debug::highlight(lg, Layer::Bg),
+                debug::highlight(lg, Layer::Bg),
+                debug::highlight(dg, Layer::Obj),
+                debug::highlight(b, Layer::Window),
+            ];
+            assert_eq!(expected, line(highlight));
+            let boxes = LayerDebug {
+                obj_boxes: true,
+                ..debug
+            };
+            assert_eq!(Rgb(0xff, 0x00, 0xff), line(boxes)[1]);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Debug views of the layers: hiding the BG, the window or the objects,
+//! false colours per layer, the boxes of the objects with their OAM index,
+//! and marks where the BG-over-OBJ priority hid an object. They only change
+//! the pixels shown: the overlays are `Mark`s applied after the colouring,
+//! and the SGB still gets the clean shades for its transfers.
+
+use crate::io::{Rgb, GFX_SIZE_X};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LayerDebug {
+    pub hide_bg: bool,
+    pub hide_window: bool,
+    pub hide_obj: bool,
+    // BG in red, window in green, objects in blue
+    pub highlight: bool,
+    pub obj_boxes: bool,
+    pub priority: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DebugToggle {
+    Bg,
+    Window,
+    Obj,
+    Highlight,
+    ObjBoxes,
+    Priority,
+}
+
+impl LayerDebug {
+    pub fn toggle(&mut self, toggle: DebugToggle) {
+        let flag = match toggle {
+            DebugToggle::Bg => &mut self.hide_bg,
+            DebugToggle::Window => &mut self.hide_window,
+            DebugToggle::Obj => &mut self.hide_obj,
+            DebugToggle::Highlight => &mut self.highlight,
+            DebugToggle::ObjBoxes => &mut self.obj_boxes,
+            DebugToggle::Priority => &mut self.priority,
+        };
+        *flag = !*flag;
+    }
+    pub fn active(&self) -> bool {
+        *self != LayerDebug::default()
+    }
+}
+
+/// Layer a pixel comes from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Layer {
+    Bg,
+    Window,
+    Obj,
+}
+
+/// Overlay of a debug view on a pixel
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Mark {
+    #[default]
+    None,
+    // false colour of the layer
+    Tint(Layer),
+    Color(Rgb),
+}
+
+impl Mark {
+    /// Colour shown for a pixel of colour `color`
+    pub fn apply(self, color: Rgb) -> Rgb {
+        match self {
+            Mark::None => color,
+            Mark::Tint(layer) => highlight(color, layer),
+            Mark::Color(mark) => mark,
+        }
+    }
+}
+
+const BOX: Rgb = Rgb(0xff, 0x00, 0xff);
+const INDEX: Rgb = Rgb(0xff, 0xff, 0x00);
+// an object pixel hidden by the BG-over-OBJ priority
+pub(super) const HIDDEN: Rgb = Rgb(0x00, 0xff, 0xff);
+// 3x5 digits, bit 2 is the left column
+const DIGITS: [[u8; 5]; 10] = [
+    [7, 5, 5, 5, 7],
+    [2, 6, 2, 2, 7],
+    [7, 1, 7, 4, 7],
+    [7, 1, 3, 1, 7],
+    [5, 5, 7, 1, 1],
+    [7, 4, 7, 1, 7],
+    [7, 4, 7, 5, 7],
+    [7, 1, 1, 1, 1],
+    [7, 5, 7, 5, 7],
+    [7, 5, 7, 1, 7],
+];
+
+/// Tints a pixel with the false colour of its layer.
+pub(super) fn highlight(Rgb(r, g, b): Rgb, layer: Layer) -> Rgb {
+    let tint = |c: u8, on: bool| if on { c / 2 + 0x80 } else { c / 2 };
+    match layer {
+        Layer::Bg => Rgb(tint(r, true), tint(g, false), tint(b, false)),
+        Layer::Window => Rgb(tint(r, false), tint(g, true), tint(b, false)),
+        Layer::Obj => Rgb(tint(r, false), tint(g, false), tint(b, true)),
+    }
+}
+
+/// Draws the outline of the objects on line `ly`, all 40 of them, with
+/// their OAM index in the top left corner.
+pub(super) fn obj_boxes(line: &mut [Mark; GFX_SIZE_X], oam: &[u8], ly: usize, tall: bool) {
+    let height = if tall { 16 } else { 8 };
+    for (i, obj) in oam.chunks_exact(4).enumerate() {
+        let row = ly as isize + 16 - obj[0] as isize;
+        if !(0..height).contains(&row) {
+            continue;
+        }
+        for col in 0..8 {
+            let Ok(lx) = usize::try_from(obj[1] as isize - 8 + col) else {
+                continue;
+            };
+            if lx >= GFX_SIZE_X {
+                break;
+            }
+            if row == 0 || row == height - 1 || col == 0 || col == 7 {
+                line[lx] = Mark::Color(BOX);
+            } else if row <= 5 {
+                // tens in columns 1-3, units in columns 4-6
+                let digit = if col <= 3 { i / 10 } else { i % 10 };
+                let bit = 2 - (col - 1) % 3;
+                if DIGITS[digit][row as usize - 1] >> bit & 0x01 != 0 {
+                    line[lx] = Mark::Color(INDEX);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boxes() {
+        let mut oam = [0; 0xa0];
+        // object 12 at the left edge of the screen, on line 0
+        oam[48..52].copy_from_slice(&[16, 8, 0, 0]);
+        let (none, b, i) = (Mark::None, Mark::Color(BOX), Mark::Color(INDEX));
+        let mut line = [none; GFX_SIZE_X];
+        obj_boxes(&mut line, &oam, 0, false);
+        assert_eq!([b; 8], line[..8]);
+        assert_eq!(none, line[8]);
+        // "12" on row 1: 010 111
+        line.fill(none);
+        obj_boxes(&mut line, &oam, 1, false);
+        let row = [b, none, i, none, i, i, i, b];
+        assert_eq!(row, line[..8]);
+        // 8x16 objects are 16 lines tall
+        line.fill(none);
+        obj_boxes(&mut line, &oam, 15, true);
+        assert_eq!([b; 8], line[..8]);
+    }
+}
@@ -0,0 +1,508 @@
+//! Mode 3 renderer producing one pixel per dot from a background FIFO and an
+//! object FIFO, like the hardware.
+//!
+//! The registers are read while the line is drawn, so mid-line writes to
+//! SCX, SCY, BGP, OBP0/1, LCDC, WX and VRAM are visible. Mode 3 takes 172
+//! dots plus the SCX % 8 pixels discarded at the start of the line, 6 dots
+//! when the window starts and 6 to 11 dots for each object fetch.
+//!
+//! The window starts when the pixel counter matches WX - 7. Clearing LCDC
+//! bit 5 switches the fetcher back to the background, and setting it again
+//! restarts the window at a later match on the same line. With WX = 0 the
+//! window is shifted by SCX % 8 as well, and with WX = 166 it also covers
+//! the whole next line.
+//!
+//! In CGB mode the fetcher also reads the tile attributes from VRAM bank 1,
+//! and objects overlap in OAM order unless OPRI selects the DMG X order.
+//!
+//! Objects past the 10 of the hardware (unlimited objects enhancement) are
+//! drawn without taking any dots.
+
+use super::{
+    bg_attr, bg_row_index, get_a_color_id, mix, obj_bank, x_priority, BgPixel, Cgb, ObjAttr,
+    ObjPixel, OBJS_PER_LINE, VRAM,
+};
+use crate::io::{LcdIF, GFX_SIZE_X};
+use crate::memory::{LCDC, SCX, SCY, WX};
+use crate::palette::LayerPalettes;
+use std::collections::VecDeque;
+
+// dots spent on the first tile fetch, whose pixels are thrown away
+const WARMUP_DOTS: usize = 6;
+// dots to fetch the tile of an object
+const OBJ_FETCH_DOTS: usize = 6;
+// the fetcher has read the tile data and waits to push
+const FETCH_DONE: usize = 6;
+
+pub struct Fifo {
+    ly: usize,
+    // pixels written to the line
+    lx: usize,
+    // pixels popped and thrown away (SCX % 8, window with WX < 7)
+    discard: usize,
+    fine_x: usize,
+    warmup: usize,
+    bg: VecDeque<BgPixel>,
+    obj: VecDeque<ObjPixel>,
+    // background fetcher
+    step: usize,
+    tile_x: usize,
+    tile_id: u8,
+    attr: u8,
+    data: (u8, u8),
+    // window line when WY was matched in this frame; `window` is true while
+    // the window is fetched
+    win_line: Option<usize>,
+    window: bool,
+    window_drawn: bool,
+    // pixel counter when the window last started
+    window_at: Option<usize>,
+    wx166: bool,
+    // objects on this line, `true` once fetched
+    objs: Vec<(ObjAttr, bool)>,
+    // object being fetched and the dots left
+    obj_fetch: Option<(usize, usize)>,
+}
+
+impl Fifo {
+    pub fn new() -> Fifo {
+        Fifo {
+            ly: 0,
+            lx: 0,
+            discard: 0,
+            fine_x: 0,
+            warmup: 0,
+            bg: VecDeque::with_capacity(16),
+            obj: VecDeque::with_capacity(8),
+            step: 0,
+            tile_x: 0,
+            tile_id: 0,
+            attr: 0,
+            data: (0, 0),
+            win_line: None,
+            window: false,
+            window_drawn: false,
+            window_at: None,
+            wx166: false,
+            objs: Vec::new(),
+            obj_fetch: None,
+        }
+    }
+    /// Starts mode 3 of line `ly`. `win_line` is the window line when WY was
+    /// matched in this frame, `full_window` draws the window from the left
+    /// edge (WX = 166 on the previous line).
+    pub fn start(
+        &mut self,
+        ly: usize,
+        win_line: Option<usize>,
+        full_window: bool,
+        objs: Vec<ObjAttr>,
+        scx: u8,
+    ) {
+        let full_window = full_window && win_line.is_some();
+        *self = Fifo {
+            ly,
+            discard: if full_window { 0 } else { (scx % 8) as usize },
+            fine_x: (scx % 8) as usize,
+            warmup: WARMUP_DOTS,
+            win_line,
+            window: full_window,
+            window_drawn: full_window,
+            objs: objs.into_iter().map(|o| (o, false)).collect(),
+            bg: std::mem::take(&mut self.bg),
+            obj: std::mem::take(&mut self.obj),
+            ..Fifo::new()
+        };
+        self.bg.clear();
+        self.obj.clear();
+    }
+    /// The window was drawn on this line.
+    pub fn window_drawn(&self) -> bool {
+        self.window_drawn
+    }
+    /// The window started at WX = 166 on this line.
+    pub fn wx166(&self) -> bool {
+        self.wx166
+    }
+    /// Runs one dot, returns true when the line is complete.
+    pub fn tick(
+        &mut self,
+        vram: &[u8],
+        lcd_regs: &[u8],
+        cgb: Option<&Cgb>,
+        dmg: &LayerPalettes,
+        io: &mut impl LcdIF,
+    ) -> bool {
+        if self.warmup > 0 {
+            self.warmup -= 1;
+            return false;
+        }
+        let reg = |addr: u16| lcd_regs[(addr - LCDC) as usize];
+        let lcdc = reg(LCDC);
+
+        if let Some((i, dots)) = self.obj_fetch {
+            if dots > 1 {
+                self.obj_fetch = Some((i, dots - 1));
+            } else {
+                self.obj_fetch = None;
+                self.fetch_obj(i, lcdc, vram, cgb);
+            }
+            return false;
+        }
+
+        if self.window && lcdc & 0x20 == 0 {
+            // back to the background, from the next pixel to fetch
+            self.window = false;
+            self.step = 0;
+            self.tile_x = (self.fine_x + self.lx + self.bg.len()) / 8;
+        }
+        // the window starts when the pixel counter matches WX - 7
+        let wx = reg(WX) as usize;
+        let at_wx = match wx {
+            0..=6 => self.lx == 0,
+            _ => self.lx + 7 == wx,
+        };
+        if !self.window
+            && at_wx
+            && self.window_at != Some(self.lx)
+            && self.win_line.is_some()
+            && lcdc & 0x20 != 0
+            // LCDC bit 0 disables the window on DMG only
+            && (lcdc & 0x01 != 0 || cgb.is_some())
+            && wx <= GFX_SIZE_X - 1 + 7
+        {
+            self.window = true;
+            self.window_drawn = true;
+            self.window_at = Some(self.lx);
+            self.wx166 = wx == GFX_SIZE_X - 1 + 7;
+            self.bg.clear();
+            self.step = 0;
+            self.tile_x = 0;
+            self.discard = match wx {
+                0 => 7 + self.fine_x,
+                _ => 7usize.saturating_sub(wx),
+            };
+        }
+
+        // object at the current pixel: the background fetch is finished
+        // first, then the object is fetched while the output is stopped
+        if lcdc & 0x02 != 0 {
+            if let Some(i) = self.obj_hit(0..OBJS_PER_LINE) {
+                if self.step >= FETCH_DONE - 1 && !self.bg.is_empty() {
+                    self.objs[i].1 = true;
+                    self.obj_fetch = Some((i, OBJ_FETCH_DOTS));
+                } else {
+                    self.fetch_bg(lcdc, vram, lcd_regs, cgb.is_some());
+                }
+                return false;
+            }
+            while let Some(i) = self.obj_hit(OBJS_PER_LINE..self.objs.len()) {
+                self.objs[i].1 = true;
+                self.fetch_obj(i, lcdc, vram, cgb);
+            }
+        }
+
+        self.fetch_bg(lcdc, vram, lcd_regs, cgb.is_some());
+        if self.bg.is_empty() {
+            return false;
+        }
+        let bg = self.bg.pop_front().unwrap_or_default();
+        if self.discard > 0 {
+            self.discard -= 1;
+            return false;
+        }
+        let obj = self.obj.pop_front().unwrap_or_default();
+
+        io.gfx()[self.ly * GFX_SIZE_X + self.lx] = mix(bg, obj, lcd_regs, cgb, dmg);
+        self.lx += 1;
+        self.lx == GFX_SIZE_X
+    }
+
+    /// First object in `range` not fetched yet at the current pixel
+    fn obj_hit(&self, range: std::ops::Range<usize>) -> Option<usize> {
+        let end = range.end.min(self.objs.len());
+        (range.start..end).find(|&i| {
+            let (obj, fetched) = &self.objs[i];
+            !fetched && (obj.x as usize) <= self.lx + 8
+        })
+    }
+    /// Background / window fetcher: 2 dots for each of the tile ID and the
+    /// two bytes of tile data, then pushes 8 pixels when the FIFO has room.
+    fn fetch_bg(&mut self, lcdc: u8, vram: &[u8], lcd_regs: &[u8], cgb: bool) {
+        let reg = |addr: u16| lcd_regs[(addr - LCDC) as usize] as usize;
+        match self.step {
+            1 => {
+                let (map, x, y) = match (self.window, self.win_line) {
+                    (true, Some(win_line)) => {
+                        let map = if lcdc & 0x40 != 0 { 0x9c00 } else { 0x9800 };
+                        (map, self.tile_x, win_line)
+                    }
+                    _ => {
+                        let map = if lcdc & 0x08 != 0 { 0x9c00 } else { 0x9800 };
+                        (map, reg(SCX) / 8 + self.tile_x, reg(SCY) + self.ly)
+                    }
+                };
+                let index = (map - VRAM) as usize + (y / 8) % 32 * 32 + x % 32;
+                self.tile_id = vram[index];
+                self.attr = bg_attr(vram, cgb, index);
+            }
+            3 | 5 => {
+                let y = match (self.window, self.win_line) {
+                    (true, Some(win_line)) => win_line,
+                    _ => reg(SCY) + self.ly,
+                };
+                let index = bg_row_index(lcdc, self.tile_id, self.attr, y);
+                if self.step == 3 {
+                    self.data.0 = vram[index];
+                } else {
+                    self.data.1 = vram[index + 1];
+                }
+            }
+            FETCH_DONE => {
+                if self.bg.len() > 8 {
+                    return;
+                }
+                let data = [self.data.0, self.data.1];
+                for i in 0..8 {
+                    // X flip
+                    let i = if self.attr & 0x20 != 0 { 7 - i } else { i };
+                    self.bg.push_back(BgPixel {
+                        color_id: get_a_color_id(i, 0, &data),
+                        attr: self.attr,
+                    });
+                }
+                self.tile_x += 1;
+                self.step = 0;
+                return;
+            }
+            _ => (),
+        }
+        self.step += 1;
+    }
+
+    fn fetch_obj(&mut self, i: usize, lcdc: u8, vram: &[u8], cgb: Option<&Cgb>) {
+        let (obj, _) = &self.objs[i];
+        let tall = lcdc & 0x04 != 0;
+        let height = if tall { 16 } else { 8 };
+        let mut row = self.ly + 16 - obj.y as usize;
+        if obj.attr & 0x40 != 0 {
+            // Y flip
+            row = height - 1 - row;
+        }
+        let tile = if tall {
+            obj.tile_index & 0xfe
+        } else {
+            obj.tile_index
+        };
+        let index = obj_bank(obj.attr, cgb.is_some()) + tile as usize * 16 + row * 2;
+        let data = &vram[index..index + 2];
+        // pixels left of the screen are dropped
+        let skip = (self.lx + 8).saturating_sub(obj.x as usize);
+        for x in skip..8 {
+            let px = if obj.attr & 0x20 != 0 { 7 - x } else { x };
+            let pixel = ObjPixel {
+                color_id: get_a_color_id(px as u16, 0, data),
+                attr: obj.attr,
+                order: i,
+            };
+            let slot = x - skip;
+            if slot >= self.obj.len() {
+                self.obj.push_back(pixel);
+            } else if self.obj[slot].color_id == 0
+                || (!x_priority(cgb) && pixel.color_id != 0 && i < self.obj[slot].order)
+            {
+                // the object fetched first has priority, or the first in OAM
+                self.obj[slot] = pixel;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{GfxColor, Screen};
+    use crate::memory::{BCPD, BCPS, BGP, OBP0, OBP1, OCPD, OCPS, SCX, WY};
+    use crate::ppu::{Mode, Ppu};
+
+    fn set_reg(ppu: &mut Ppu, addr: u16, val: u8) {
+        ppu.write_lcd_reg((addr - LCDC) as usize, val, &mut 0);
+    }
+    fn run_dot(ppu: &mut Ppu, screen: &mut Screen) {
+        ppu.check_wy();
+        ppu.run_dot(screen, &mut 0);
+    }
+    /// Runs to the end of mode 3 and returns its length in dots.
+    fn mode3_dots(ppu: &mut Ppu, screen: &mut Screen) -> usize {
+        while ppu.mode != Mode::Mode3 {
+            run_dot(ppu, screen);
+        }
+        let mut dots = 0;
+        while ppu.mode == Mode::Mode3 {
+            run_dot(ppu, screen);
+            dots += 1;
+        }
+        dots
+    }
+
+    #[test]
+    fn mode3_length() {
+        let mut screen = Screen::new();
+        let mut ppu = Ppu::new();
+        assert_eq!(172, mode3_dots(&mut ppu, &mut screen));
+
+        let mut ppu = Ppu::new();
+        set_reg(&mut ppu, SCX, 3);
+        assert_eq!(175, mode3_dots(&mut ppu, &mut screen));
+
+        // window from x = 80
+        let mut ppu = Ppu::new();
+        set_reg(&mut ppu, LCDC, 0xa1);
+        set_reg(&mut ppu, WY, 0);
+        set_reg(&mut ppu, WX, 87);
+        assert_eq!(178, mode3_dots(&mut ppu, &mut screen));
+
+        // objects at x = 0 (fetcher ready) and x = 84
+        let mut ppu = Ppu::new();
+        set_reg(&mut ppu, LCDC, 0x83);
+        ppu.oam[..8].copy_from_slice(&[16, 8, 0, 0, 16, 92, 0, 0]);
+        let dots = mode3_dots(&mut ppu, &mut screen);
+        assert!((172 + 12..=172 + 22).contains(&dots), "{}", dots);
+    }
+    #[test]
+    fn unlimited_objs() {
+        let mut dots = Vec::new();
+        for (unlimited, fast) in [(false, false), (true, false), (true, true)] {
+            let mut screen = Screen::new();
+            let mut ppu = Ppu::new();
+            ppu.set_fast(fast);
+            ppu.set_unlimited_objs(unlimited);
+            set_reg(&mut ppu, LCDC, 0x83);
+            set_reg(&mut ppu, OBP0, 0xe4);
+            // 12 objects in color 3, 12 pixels apart
+            ppu.vram[..16].fill(0xff);
+            for i in 0..12 {
+                ppu.oam[i * 4..][..2].copy_from_slice(&[16, 8 + i as u8 * 12]);
+            }
+            dots.push(mode3_dots(&mut ppu, &mut screen));
+            let drawn = (0..12)
+                .filter(|i| screen.gfx[i * 12] == GfxColor::B.rgb())
+                .count();
+            assert_eq!(if unlimited { 12 } else { 10 }, drawn);
+        }
+        // mode 3 still fetches 10 objects
+        assert_eq!(dots[0], dots[1]);
+    }
+    #[test]
+    fn mid_line_palette_change() {
+        let mut screen = Screen::new();
+        let mut ppu = Ppu::new();
+        set_reg(&mut ppu, LCDC, 0x91);
+        // tile 0 is color 3
+        ppu.vram[..16].fill(0xff);
+        set_reg(&mut ppu, BGP, 0xff);
+        while ppu.mode != Mode::Mode3 {
+            run_dot(&mut ppu, &mut screen);
+        }
+        for _ in 0..WARMUP_DOTS + 80 {
+            run_dot(&mut ppu, &mut screen);
+        }
+        set_reg(&mut ppu, BGP, 0x00);
+        mode3_dots(&mut ppu, &mut screen);
+        let line = &screen.gfx[..GFX_SIZE_X];
+        assert!(line[0] == GfxColor::B.rgb() && line[GFX_SIZE_X - 1] == GfxColor::W.rgb());
+        let changed = line.iter().position(|c| *c == GfxColor::W.rgb()).unwrap();
+        assert!((70..=80).contains(&changed), "{}", changed);
+    }
+    #[test]
+    fn same_as_scanline_renderer() {
+        for cgb in [false, true] {
+            let mut screens = [Screen::new(), Screen::new()];
+            for (fast, screen) in [true, false].into_iter().zip(screens.iter_mut()) {
+                let mut ppu = Ppu::new();
+                ppu.set_fast(fast);
+                ppu.set_cgb(cgb);
+                for (i, b) in ppu.vram.iter_mut().enumerate() {
+                    *b = (i * 7 + i / 16) as u8;
+                }
+                for (i, b) in ppu.oam.iter_mut().enumerate() {
+                    *b = (i * 13 + 5) as u8;
+                }
+                for i in 0..64 {
+                    ppu.write_cgb_reg(BCPS, 0x80);
+                    ppu.write_cgb_reg(BCPD, (i * 37) as u8);
+                    ppu.write_cgb_reg(OCPS, 0x80);
+                    ppu.write_cgb_reg(OCPD, (i * 91) as u8);
+                }
+                set_reg(&mut ppu, LCDC, 0xf3);
+                set_reg(&mut ppu, SCX, 13);
+                set_reg(&mut ppu, SCY, 7);
+                set_reg(&mut ppu, WY, 40);
+                set_reg(&mut ppu, WX, 50);
+                set_reg(&mut ppu, BGP, 0xe4);
+                set_reg(&mut ppu, OBP0, 0x1b);
+                set_reg(&mut ppu, OBP1, 0x93);
+                let mut i_flg = 0;
+                while screen.frames == 0 {
+                    ppu.run(screen, &mut i_flg).unwrap();
+                }
+            }
+            for ly in 0..144 {
+                let line = ly * GFX_SIZE_X..(ly + 1) * GFX_SIZE_X;
+                assert!(
+                    screens[0].gfx[line.clone()] == screens[1].gfx[line],
+                    "cgb {} line {}",
+                    cgb,
+                    ly
+                );
+            }
+        }
+    }
+    #[test]
+    fn window_quirks() {
+        let mut screen = Screen::new();
+        let mut ppu = Ppu::new();
+        // window tile: color 3, except row 1 in color 1
+        ppu.vram[0x10..0x20].fill(0xff);
+        ppu.vram[0x13] = 0x00;
+        ppu.vram[0x1c00..].fill(1);
+        set_reg(&mut ppu, LCDC, 0xf1);
+        set_reg(&mut ppu, BGP, 0xe4);
+        set_reg(&mut ppu, WY, 200);
+        set_reg(&mut ppu, WX, 7);
+        let run_to = |ppu: &mut Ppu, screen: &mut Screen, ly: usize, dot: usize| {
+            while !(ppu.line == ly && ppu.dot >= dot) {
+                ppu.run(screen, &mut 0).unwrap();
+            }
+        };
+        let pixel = |screen: &Screen, ly: usize, lx: usize| screen.gfx[ly * GFX_SIZE_X + lx];
+
+        // WY is matched after mode 2 of line 20
+        run_to(&mut ppu, &mut screen, 20, 300);
+        set_reg(&mut ppu, WY, 20);
+        run_to(&mut ppu, &mut screen, 22, 4);
+        assert!(pixel(&screen, 20, 0) == GfxColor::W.rgb());
+        assert!(pixel(&screen, 21, 0) == GfxColor::B.rgb());
+        // the window line does not advance while the window is disabled
+        set_reg(&mut ppu, LCDC, 0xd1);
+        run_to(&mut ppu, &mut screen, 23, 4);
+        set_reg(&mut ppu, LCDC, 0xf1);
+        run_to(&mut ppu, &mut screen, 24, 4);
+        assert!(pixel(&screen, 22, 0) == GfxColor::W.rgb());
+        assert!(pixel(&screen, 23, 0) == GfxColor::LG.rgb());
+        // WX = 166: the last pixel, then the whole next line
+        set_reg(&mut ppu, WX, 166);
+        run_to(&mut ppu, &mut screen, 26, 4);
+        assert!(pixel(&screen, 24, 158) == GfxColor::W.rgb());
+        assert!(pixel(&screen, 24, 159) == GfxColor::B.rgb());
+        assert!(pixel(&screen, 25, 0) == GfxColor::B.rgb());
+        assert!(pixel(&screen, 26, 0) == GfxColor::W.rgb());
+        // disabled in the middle of the line
+        set_reg(&mut ppu, WX, 7);
+        run_to(&mut ppu, &mut screen, 27, 160);
+        set_reg(&mut ppu, LCDC, 0xd1);
+        run_to(&mut ppu, &mut screen, 28, 4);
+        assert!(pixel(&screen, 27, 0) == GfxColor::B.rgb());
+        assert!(pixel(&screen, 27, GFX_SIZE_X - 1) == GfxColor::W.rgb());
+    }
+}
@@ -0,0 +1,637 @@
+//! Pictures of the PPU memory for the debug windows.
+
+use super::{bg_attr, bg_row_index, id2color, obj_bank, Cgb, Obj, Ppu, VRAM, VRAM_BANK_SIZE};
+use crate::io::Rgb;
+use crate::io::{GFX_SIZE_X, GFX_SIZE_Y};
+use crate::memory::{BGP, LCDC, OBP0, OBP1, SCX, SCY, WX, WY};
+use crate::sgb;
+use std::fmt;
+use std::ops::Range;
+
+// tiles of a VRAM bank, 16 per row in the tile sheet
+const TILES: usize = 384;
+pub const SHEET_TILES_X: usize = 16;
+// the two tile maps, 0x9800 left of 0x9c00
+pub const MAPS_SIZE: (usize, usize) = (512, 256);
+const MAP_SIZE: usize = 256;
+// outlines of the visible BG and of the window
+const VIEWPORT: Rgb = Rgb(0xff, 0x00, 0x00);
+const WINDOW: Rgb = Rgb(0x00, 0x80, 0xff);
+// the 40 objects, 8 per row, each in a 12x20 cell with a 2 pixel frame
+const OBJ_CELL: (usize, usize) = (12, 20);
+const OBJ_CELLS_X: usize = 8;
+pub const OBJS_SIZE: (usize, usize) = (OBJ_CELLS_X * OBJ_CELL.0, 40 / OBJ_CELLS_X * OBJ_CELL.1);
+const OBJ_BACKDROP: Rgb = Rgb(0x20, 0x20, 0x20);
+// colour ID 0
+const TRANSPARENT: Rgb = Rgb(0x60, 0x60, 0x60);
+// frames of the objects on the current line, drawn or dropped
+const ON_LINE: Rgb = Rgb(0x00, 0xc0, 0x00);
+const DROPPED: Rgb = Rgb(0xff, 0x00, 0x00);
+
+/// Colours of the tiles in the tile viewer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ViewPalette {
+    // DMG shades of the color IDs
+    Shades,
+    Bgp,
+    Obp0,
+    Obp1,
+    CgbBg(u8),
+    CgbObj(u8),
+}
+
+impl ViewPalette {
+    /// Next palette, the CGB palettes only in CGB mode
+    pub fn next(self, cgb: bool) -> ViewPalette {
+        match self {
+            ViewPalette::Shades => ViewPalette::Bgp,
+            ViewPalette::Bgp => ViewPalette::Obp0,
+            ViewPalette::Obp0 => ViewPalette::Obp1,
+            ViewPalette::Obp1 if cgb => ViewPalette::CgbBg(0),
+            ViewPalette::CgbBg(7) => ViewPalette::CgbObj(0),
+            ViewPalette::CgbBg(n) => ViewPalette::CgbBg(n + 1),
+            ViewPalette::CgbObj(n) if n < 7 => ViewPalette::CgbObj(n + 1),
+            _ => ViewPalette::Shades,
+        }
+    }
+}
+
+impl fmt::Display for ViewPalette {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ViewPalette::Shades => write!(f, "shades"),
+            ViewPalette::Bgp => write!(f, "BGP"),
+            ViewPalette::Obp0 => write!(f, "OBP0"),
+            ViewPalette::Obp1 => write!(f, "OBP1"),
+            ViewPalette::CgbBg(n) => write!(f, "BG{}", n),
+            ViewPalette::CgbObj(n) => write!(f, "OBJ{}", n),
+        }
+    }
+}
+
+/// Tile of the tile sheet
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileInfo {
+    pub bank: usize,
+    // 0..384, from 0x8000
+    pub index: usize,
+    // LCDC bit 4: the BG and the window use the 0x8000 addressing
+    pub bg_8000: bool,
+}
+
+impl TileInfo {
+    pub fn addr(&self) -> u16 {
+        VRAM + self.index as u16 * 16
+    }
+}
+
+impl fmt::Display for TileInfo {
+    /// `tile 0x081 bank 0 at 0x8810: 0x8000 ID 0x81 (OBJ), 0x8800 ID 0x81 (BG)`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut areas = Vec::new();
+        if self.index < 256 {
+            let bg = if self.bg_8000 { ", BG" } else { "" };
+            areas.push(format!("0x8000 ID 0x{:02x} (OBJ{})", self.index, bg));
+        }
+        if self.index >= 128 {
+            let bg = if self.bg_8000 { "" } else { " (BG)" };
+            areas.push(format!("0x8800 ID 0x{:02x}{}", self.index % 256, bg));
+        }
+        write!(
+            f,
+            "tile 0x{:03x} bank {} at 0x{:04x}: {}",
+            self.index,
+            self.bank,
+            self.addr(),
+            areas.join(", ")
+        )
+    }
+}
+
+/// Entry of a tile map
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MapTileInfo {
+    // 0x9800 or 0x9c00
+    pub map: u16,
+    pub x: usize,
+    pub y: usize,
+    pub tile_id: u8,
+    // address in its bank of the tile data, with the LCDC addressing
+    pub data: u16,
+    // CGB attributes
+    pub attr: Option<u8>,
+}
+
+impl MapTileInfo {
+    pub fn addr(&self) -> u16 {
+        self.map + (self.y * 32 + self.x) as u16
+    }
+}
+
+impl fmt::Display for MapTileInfo {
+    /// `map 0x9800 (3, 1) at 0x9823: ID 0x05 data 0x8050, attr 0x29 (palette 1, bank 1, X flip)`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "map 0x{:04x} ({}, {}) at 0x{:04x}: ID 0x{:02x} data 0x{:04x}",
+            self.map,
+            self.x,
+            self.y,
+            self.addr(),
+            self.tile_id,
+            self.data
+        )?;
+        if let Some(attr) = self.attr {
+            let mut flags = vec![
+                format!("palette {}", attr & 0x07),
+                format!("bank {}", attr >> 3 & 0x01),
+            ];
+            for (bit, name) in [(0x20, "X flip"), (0x40, "Y flip"), (0x80, "BG priority")] {
+                if attr & bit != 0 {
+                    flags.push(name.to_string());
+                }
+            }
+            write!(f, ", attr 0x{:02x} ({})", attr, flags.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Entry of OAM
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjInfo {
+    pub index: usize,
+    pub y: u8,
+    pub x: u8,
+    pub tile: u8,
+    pub attr: u8,
+    pub cgb: bool,
+    // lines of the screen
+    pub lines: Range<usize>,
+    // on the current line, drawn or dropped by the 10 objects limit
+    pub on_line: Option<bool>,
+}
+
+impl fmt::Display for ObjInfo {
+    /// `OBJ 12: Y 0x20 X 0x18 tile 0x04 attr 0x30 (OBP1, X flip), lines 16-23, dropped`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut flags = if self.cgb {
+            vec![
+                format!("palette {}", self.attr & 0x07),
+                format!("bank {}", self.attr >> 3 & 0x01),
+            ]
+        } else {
+            vec![format!("OBP{}", self.attr >> 4 & 0x01)]
+        };
+        for (bit, name) in [(0x20, "X flip"), (0x40, "Y flip"), (0x80, "BG over OBJ")] {
+            if self.attr & bit != 0 {
+                flags.push(name.to_string());
+            }
+        }
+        write!(
+            f,
+            "OBJ {}: Y 0x{:02x} X 0x{:02x} tile 0x{:02x} attr 0x{:02x} ({}), ",
+            self.index,
+            self.y,
+            self.x,
+            self.tile,
+            self.attr,
+            flags.join(", ")
+        )?;
+        if self.lines.is_empty() {
+            write!(f, "off screen")?;
+        } else {
+            write!(f, "lines {}-{}", self.lines.start, self.lines.end - 1)?;
+        }
+        match self.on_line {
+            Some(true) => write!(f, ", on line"),
+            Some(false) => write!(f, ", dropped"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Ppu {
+    fn reg(&self, addr: u16) -> u8 {
+        self.lcd_regs[(addr - LCDC) as usize]
+    }
+    /// Colours shown for the color IDs 0-3 through BGP, OBP0 or OBP1. In
+    /// SGB mode the PPU writes shades coloured by the SGB, they are shown
+    /// in grey.
+    fn dmg_colors(&self, reg: u16) -> [Rgb; 4] {
+        let colors = match reg {
+            OBP0 => &self.dmg_palettes.obj0,
+            OBP1 => &self.dmg_palettes.obj1,
+            _ => &self.dmg_palettes.bg,
+        };
+        [0, 1, 2, 3].map(|id| {
+            let shade = id2color(self.reg(reg), id);
+            if *colors == sgb::SHADES {
+                shade.rgb()
+            } else {
+                colors[shade as usize]
+            }
+        })
+    }
+    /// Colours of the color IDs 0-3 with `palette`
+    fn view_colors(&self, palette: ViewPalette) -> [Rgb; 4] {
+        match (palette, &self.cgb) {
+            (ViewPalette::Bgp, _) => self.dmg_colors(BGP),
+            (ViewPalette::Obp0, _) => self.dmg_colors(OBP0),
+            (ViewPalette::Obp1, _) => self.dmg_colors(OBP1),
+            (ViewPalette::CgbBg(n), Some(cgb)) => {
+                [0, 1, 2, 3].map(|id| Cgb::color(&cgb.bg_palettes, n, id))
+            }
+            (ViewPalette::CgbObj(n), Some(cgb)) => {
+                [0, 1, 2, 3].map(|id| Cgb::color(&cgb.obj_palettes, n, id))
+            }
+            _ => [0, 1, 2, 3].map(|id| id2color(0xe4, id).rgb()),
+        }
+    }
+    /// Colours of the palettes as displayed: BGP, OBP0 and OBP1 (in grey in
+    /// SGB mode, see `dmg_colors`), and the 8 BG and 8 OBJ palettes in CGB
+    /// mode
+    pub fn palette_swatches(&self) -> Vec<(String, [Rgb; 4])> {
+        match &self.cgb {
+            Some(cgb) => (0..16)
+                .map(|i| {
+                    let (name, palettes) = if i < 8 {
+                        ("BG", &cgb.bg_palettes)
+                    } else {
+                        ("OBJ", &cgb.obj_palettes)
+                    };
+                    let n = i % 8;
+                    let colors = [0, 1, 2, 3].map(|id| Cgb::color(palettes, n, id));
+                    (format!("{}{}", name, n), colors)
+                })
+                .collect(),
+            None => [("BGP", BGP), ("OBP0", OBP0), ("OBP1", OBP1)]
+                .iter()
+                .map(|(name, reg)| (name.to_string(), self.dmg_colors(*reg)))
+                .collect(),
+        }
+    }
+    /// VRAM banks: 2 in CGB mode
+    pub fn view_banks(&self) -> usize {
+        if self.cgb.is_some() {
+            2
+        } else {
+            1
+        }
+    }
+    /// Size in pixels of the tile sheet
+    pub fn tile_sheet_size(&self) -> (usize, usize) {
+        (
+            SHEET_TILES_X * 8 * self.view_banks(),
+            TILES / SHEET_TILES_X * 8,
+        )
+    }
+    /// All the tiles of VRAM, 16 per row, bank 1 right of bank 0
+    pub fn tile_sheet(&self, palette: ViewPalette) -> Vec<Rgb> {
+        let colors = self.view_colors(palette);
+        let (width, height) = self.tile_sheet_size();
+        let mut pixels = vec![colors[0]; width * height];
+        for bank in 0..self.view_banks() {
+            for index in 0..TILES {
+                let data = &self.vram[bank * VRAM_BANK_SIZE + index * 16..][..16];
+                let x0 = (bank * SHEET_TILES_X + index % SHEET_TILES_X) * 8;
+                let y0 = index / SHEET_TILES_X * 8;
+                for y in 0..8 {
+                    for x in 0..8 {
+                        let id = super::get_a_color_id(x as u16, y as u16, data);
+                        pixels[(y0 + y) * width + x0 + x] = colors[id as usize];
+                    }
+                }
+            }
+        }
+        pixels
+    }
+    /// Both tile maps with the current tile data addressing and palettes,
+    /// the visible BG outlined on the BG map and the window on its map
+    pub fn tile_maps(&self) -> Vec<Rgb> {
+        let (width, height) = MAPS_SIZE;
+        let lcdc = self.reg(LCDC);
+        let mut pixels = vec![Rgb(0, 0, 0); width * height];
+        let bgp = self.dmg_colors(BGP);
+        for y in 0..height {
+            for x in 0..width {
+                let map_index = 0x1800 + (x / MAP_SIZE) * 0x400 + (y / 8) * 32 + x % MAP_SIZE / 8;
+                let pixel = self.bg_pixel(lcdc, map_index, x % 8, y);
+                pixels[y * width + x] = match &self.cgb {
+                    Some(cgb) => Cgb::color(&cgb.bg_palettes, pixel.attr, pixel.color_id),
+                    None => bgp[pixel.color_id as usize],
+                };
+            }
+        }
+        let mut plot = |map: u8, x: usize, y: usize, color: Rgb| {
+            let x = map as usize * MAP_SIZE + x % MAP_SIZE;
+            pixels[y % MAP_SIZE * width + x] = color;
+        };
+        // the visible BG wraps around its map
+        let bg_map = lcdc >> 3 & 0x01;
+        let (scx, scy) = (self.reg(SCX) as usize, self.reg(SCY) as usize);
+        for x in 0..GFX_SIZE_X {
+            plot(bg_map, scx + x, scy, VIEWPORT);
+            plot(bg_map, scx + x, scy + GFX_SIZE_Y - 1, VIEWPORT);
+        }
+        for y in 0..GFX_SIZE_Y {
+            plot(bg_map, scx, scy + y, VIEWPORT);
+            plot(bg_map, scx + GFX_SIZE_X - 1, scy + y, VIEWPORT);
+        }
+        // the window starts at the top left of its map, WX - 7 and WY on
+        // the screen
+        let (wx, wy) = (self.reg(WX) as usize, self.reg(WY) as usize);
+        if lcdc & 0x20 != 0 && wx < GFX_SIZE_X + 7 && wy < GFX_SIZE_Y {
+            let win_map = lcdc >> 6 & 0x01;
+            let (x0, x1) = (7usize.saturating_sub(wx), GFX_SIZE_X + 7 - wx);
+            let (y0, y1) = (0, GFX_SIZE_Y - wy);
+            for x in x0..x1 {
+                plot(win_map, x, y0, WINDOW);
+                plot(win_map, x, y1 - 1, WINDOW);
+            }
+            for y in y0..y1 {
+                plot(win_map, x0, y, WINDOW);
+                plot(win_map, x1 - 1, y, WINDOW);
+            }
+        }
+        pixels
+    }
+    /// Tile map entry at pixel (x, y) of `tile_maps`
+    pub fn map_tile_at(&self, x: usize, y: usize) -> Option<MapTileInfo> {
+        let (width, height) = MAPS_SIZE;
+        if x >= width || y >= height {
+            return None;
+        }
+        let map_index = 0x1800 + (x / MAP_SIZE) * 0x400 + (y / 8) * 32 + x % MAP_SIZE / 8;
+        let cgb = self.cgb.is_some();
+        let attr = bg_attr(&self.vram, cgb, map_index);
+        // row 0 without the Y flip, and without the bank
+        let data = bg_row_index(self.reg(LCDC), self.vram[map_index], 0, 0);
+        Some(MapTileInfo {
+            map: VRAM + (map_index & !0x3ff) as u16,
+            x: x % MAP_SIZE / 8,
+            y: y / 8,
+            tile_id: self.vram[map_index],
+            data: VRAM + data as u16,
+            attr: cgb.then_some(attr),
+        })
+    }
+    /// The 40 objects of OAM. Those on the current line past the limit of
+    /// objects per line are flagged as dropped.
+    pub fn objs(&self) -> Vec<ObjInfo> {
+        let size = self.reg(LCDC) & 0x04 != 0;
+        let objs = Obj::set_objs(size, &self.oam);
+        let shown: Vec<usize> = Obj::filter_objs(
+            Obj::set_objs(size, &self.oam),
+            self.line,
+            size,
+            self.obj_limit(),
+        )
+        .iter()
+        .map(|obj| obj.index)
+        .collect();
+        objs.iter()
+            .map(|obj| {
+                let lines = obj.lines(size);
+                let screen = 0..GFX_SIZE_Y as isize;
+                ObjInfo {
+                    index: obj.index,
+                    y: obj.y,
+                    x: obj.x,
+                    tile: self.oam[obj.index * 4 + 2],
+                    attr: obj.attr,
+                    cgb: self.cgb.is_some(),
+                    lines: lines.start.clamp(0, screen.end) as usize
+                        ..lines.end.clamp(0, screen.end) as usize,
+                    on_line: obj
+                        .on_line(self.line, size)
+                        .then(|| shown.contains(&obj.index)),
+                }
+            })
+            .collect()
+    }
+    /// Previews of the 40 objects with their palette and flips, framed when
+    /// they are on the current line
+    pub fn obj_sheet(&self) -> Vec<Rgb> {
+        let (width, height) = OBJS_SIZE;
+        let tall = self.reg(LCDC) & 0x04 != 0;
+        let obj_height = if tall { 16 } else { 8 };
+        let mut pixels = vec![OBJ_BACKDROP; width * height];
+        for obj in self.objs() {
+            let x0 = obj.index % OBJ_CELLS_X * OBJ_CELL.0;
+            let y0 = obj.index / OBJ_CELLS_X * OBJ_CELL.1;
+            let frame = match obj.on_line {
+                Some(true) => ON_LINE,
+                Some(false) => DROPPED,
+                None => OBJ_BACKDROP,
+            };
+            for y in 0..OBJ_CELL.1 {
+                for x in 0..OBJ_CELL.0 {
+                    if x < 2 || y < 2 || x >= OBJ_CELL.0 - 2 || y >= OBJ_CELL.1 - 2 {
+                        pixels[(y0 + y) * width + x0 + x] = frame;
+                    }
+                }
+            }
+            let colors: [Rgb; 4] = match &self.cgb {
+                Some(cgb) => [0, 1, 2, 3].map(|id| Cgb::color(&cgb.obj_palettes, obj.attr, id)),
+                None if obj.attr & 0x10 != 0 => self.dmg_colors(OBP1),
+                None => self.dmg_colors(OBP0),
+            };
+            let tile = if tall { obj.tile & 0xfe } else { obj.tile } as usize;
+            let bank = obj_bank(obj.attr, self.cgb.is_some());
+            for y in 0..obj_height {
+                let row = if obj.attr & 0x40 != 0 {
+                    obj_height - 1 - y
+                } else {
+                    y
+                };
+                let data = &self.vram[bank + (tile + row / 8) * 16..][..16];
+                for x in 0..8 {
+                    let col = if obj.attr & 0x20 != 0 { 7 - x } else { x };
+                    let id = super::get_a_color_id(col as u16, (row % 8) as u16, data);
+                    pixels[(y0 + 2 + y) * width + x0 + 2 + x] = if id == 0 {
+                        TRANSPARENT
+                    } else {
+                        colors[id as usize]
+                    };
+                }
+            }
+        }
+        pixels
+    }
+    /// Object at pixel (x, y) of `obj_sheet`
+    pub fn obj_at(&self, x: usize, y: usize) -> Option<ObjInfo> {
+        let (width, height) = OBJS_SIZE;
+        if x >= width || y >= height {
+            return None;
+        }
+        let index = y / OBJ_CELL.1 * OBJ_CELLS_X + x / OBJ_CELL.0;
+        self.objs().into_iter().nth(index)
+    }
+    /// Tile at pixel (x, y) of the tile sheet
+    pub fn tile_at(&self, x: usize, y: usize) -> Option<TileInfo> {
+        let (width, height) = self.tile_sheet_size();
+        if x >= width || y >= height {
+            return None;
+        }
+        let tile_x = x / 8;
+        Some(TileInfo {
+            bank: tile_x / SHEET_TILES_X,
+            index: (y / 8) * SHEET_TILES_X + tile_x % SHEET_TILES_X,
+            bg_8000: self.reg(LCDC) & 0x10 != 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::GfxColor;
+    use crate::palette::LayerPalettes;
+
+    #[test]
+    fn tile_sheet() {
+        let mut ppu = Ppu::new();
+        ppu.lcd_regs[(BGP - LCDC) as usize] = 0x1b;
+        // tile 0x11: color 1 on the top left pixel
+        ppu.vram[0x110] = 0x80;
+        assert_eq!((128, 192), ppu.tile_sheet_size());
+        let sheet = ppu.tile_sheet(ViewPalette::Shades);
+        assert_eq!(GfxColor::LG.rgb(), sheet[8 * 128 + 8]);
+        assert_eq!(GfxColor::W.rgb(), sheet[8 * 128 + 9]);
+        let sheet = ppu.tile_sheet(ViewPalette::Bgp);
+        assert_eq!(GfxColor::DG.rgb(), sheet[8 * 128 + 8]);
+
+        ppu.set_cgb(true);
+        assert_eq!((256, 192), ppu.tile_sheet_size());
+        let tile = ppu.tile_at(128 + 8, 8 * 23).unwrap();
+        assert_eq!(
+            "tile 0x171 bank 1 at 0x9710: 0x8800 ID 0x71 (BG)",
+            tile.to_string()
+        );
+        let tile = ppu.tile_at(8, 8 * 8 + 7).unwrap();
+        assert_eq!(
+            "tile 0x081 bank 0 at 0x8810: 0x8000 ID 0x81 (OBJ), 0x8800 ID 0x81 (BG)",
+            tile.to_string()
+        );
+        assert_eq!(None, ppu.tile_at(256, 0));
+        assert_eq!(ViewPalette::CgbBg(0), ViewPalette::Obp1.next(true));
+        assert_eq!(ViewPalette::Shades, ViewPalette::Obp1.next(false));
+        assert_eq!(ViewPalette::Shades, ViewPalette::CgbObj(7).next(true));
+    }
+
+    #[test]
+    fn tile_maps() {
+        let mut ppu = Ppu::new();
+        // BG on the 0x9c00 map, scrolled to wrap on both axes
+        ppu.lcd_regs[(LCDC - LCDC) as usize] = 0x88;
+        ppu.lcd_regs[(SCX - LCDC) as usize] = 200;
+        ppu.lcd_regs[(SCY - LCDC) as usize] = 250;
+        ppu.lcd_regs[(BGP - LCDC) as usize] = 0xe4;
+        // tile 0x81 at (1, 0) of the 0x9800 map, colour 3 on its top left
+        ppu.vram[0x1801] = 0x81;
+        ppu.vram[0x0810] = 0x80;
+        ppu.vram[0x0811] = 0x80;
+        let maps = ppu.tile_maps();
+        let (width, _) = MAPS_SIZE;
+        assert_eq!(GfxColor::B.rgb(), maps[8]);
+        assert_eq!(GfxColor::W.rgb(), maps[9]);
+        // the outline of the visible BG on the right map
+        assert_eq!(VIEWPORT, maps[250 * width + 256 + 200]);
+        assert_eq!(VIEWPORT, maps[250 * width + 256 + (200 + 159) % 256]);
+        assert_eq!(VIEWPORT, maps[(250 + 143) % 256 * width + 256 + 210]);
+        assert_eq!(GfxColor::W.rgb(), maps[251 * width + 256 + 210]);
+
+        let tile = ppu.map_tile_at(8, 7).unwrap();
+        assert_eq!(
+            "map 0x9800 (1, 0) at 0x9801: ID 0x81 data 0x8810",
+            tile.to_string()
+        );
+        ppu.set_cgb(true);
+        ppu.vram[0x2000 + 0x1c21] = 0x29;
+        let tile = ppu.map_tile_at(256 + 8, 8).unwrap();
+        assert_eq!(
+            "map 0x9c00 (1, 1) at 0x9c21: ID 0x00 data 0x9000, attr 0x29 (palette 1, bank 1, X flip)",
+            tile.to_string()
+        );
+        assert_eq!(None, ppu.map_tile_at(512, 0));
+    }
+
+    #[test]
+    fn sgb_shades() {
+        let mut ppu = Ppu::new();
+        ppu.set_dmg_palettes(LayerPalettes::uniform(sgb::SHADES));
+        ppu.lcd_regs[(LCDC - LCDC) as usize] = 0x91;
+        ppu.lcd_regs[(BGP - LCDC) as usize] = 0xe4;
+        // tile 0 in colour 3, on both maps
+        ppu.vram[0x00..0x10].fill(0xff);
+        let maps = ppu.tile_maps();
+        let (width, _) = MAPS_SIZE;
+        assert_eq!(GfxColor::B.rgb(), maps[4 * width + 4]);
+        let sheet = ppu.tile_sheet(ViewPalette::Bgp);
+        assert_eq!(GfxColor::B.rgb(), sheet[0]);
+        // object 0 with OBP0
+        ppu.lcd_regs[(OBP0 - LCDC) as usize] = 0xe4;
+        let sheet = ppu.obj_sheet();
+        assert_eq!(GfxColor::B.rgb(), sheet[2 * OBJS_SIZE.0 + 2]);
+        let swatches = ppu.palette_swatches();
+        assert_eq!(GfxColor::B.rgb(), swatches[0].1[3]);
+        assert_eq!(GfxColor::W.rgb(), swatches[1].1[0]);
+    }
+
+    #[test]
+    fn objs() {
+        let mut ppu = Ppu::new();
+        ppu.lcd_regs[(OBP0 - LCDC) as usize] = 0xe4;
+        ppu.lcd_regs[(OBP1 - LCDC) as usize] = 0x1b;
+        // 11 objects on lines 16-23, the last one with OBP1 and an X flip
+        for i in 0..11 {
+            ppu.oam[i * 4..i * 4 + 4].copy_from_slice(&[32, 8, 1, 0]);
+        }
+        ppu.oam[43] = 0x30;
+        // tile 1: colour 3 on its top left pixel
+        ppu.vram[0x10] = 0x80;
+        ppu.vram[0x11] = 0x80;
+        ppu.line = 20;
+        let objs = ppu.objs();
+        assert_eq!(
+            "OBJ 0: Y 0x20 X 0x08 tile 0x01 attr 0x00 (OBP0), lines 16-23, on line",
+            objs[0].to_string()
+        );
+        assert_eq!(
+            "OBJ 10: Y 0x20 X 0x08 tile 0x01 attr 0x30 (OBP1, X flip), lines 16-23, dropped",
+            objs[10].to_string()
+        );
+        assert_eq!(
+            "OBJ 11: Y 0x00 X 0x00 tile 0x00 attr 0x00 (OBP0), off screen",
+            objs[11].to_string()
+        );
+        let sheet = ppu.obj_sheet();
+        let (width, _) = OBJS_SIZE;
+        assert_eq!(ON_LINE, sheet[0]);
+        assert_eq!(GfxColor::B.rgb(), sheet[2 * width + 2]);
+        assert_eq!(TRANSPARENT, sheet[2 * width + 3]);
+        // object 10, flipped, is the third of the second row
+        let (x0, y0) = (2 * OBJ_CELL.0, OBJ_CELL.1);
+        assert_eq!(DROPPED, sheet[y0 * width + x0]);
+        assert_eq!(GfxColor::W.rgb(), sheet[(y0 + 2) * width + x0 + 9]);
+        assert_eq!(Some(objs[10].clone()), ppu.obj_at(x0 + 5, y0 + 5));
+
+        ppu.set_unlimited_objs(true);
+        assert_eq!(Some(true), ppu.objs()[10].on_line);
+    }
+
+    #[test]
+    fn palette_swatches() {
+        let mut ppu = Ppu::new();
+        ppu.lcd_regs[(OBP1 - LCDC) as usize] = 0x1b;
+        let swatches = ppu.palette_swatches();
+        assert_eq!(3, swatches.len());
+        assert_eq!("OBP1", swatches[2].0);
+        assert_eq!(GfxColor::B.rgb(), swatches[2].1[0]);
+        ppu.set_cgb(true);
+        let swatches = ppu.palette_swatches();
+        assert_eq!(16, swatches.len());
+        assert_eq!("OBJ7", swatches[15].0);
+    }
+}
@@ -0,0 +1,43 @@
+pub struct Serial {
+    sb: u8,
+    sc: u8,
+    output: Vec<u8>,
+}
+
+impl Serial {
+    pub fn new() -> Serial {
+        Serial {
+            sb: 0,
+            sc: 0,
+            output: Vec::new(),
+        }
+    }
+    pub fn read_serial_reg(&self, index: usize) -> u8 {
+        match index {
+            0 /* SB - SB */ => self.sb,
+            _ /* SC - SB */ => self.sc | 0x7e,
+        }
+    }
+    pub fn write_serial_reg(&mut self, index: usize, val: u8, i_flg: &mut u8) {
+        match index {
+            0 /* SB - SB */ => self.sb = val,
+            _ /* SC - SB */ => {
+                self.sc = val;
+                if val & 0x81 == 0x81 {
+                    // Transfer with the internal clock. No link partner is
+                    // connected, so the transfer completes at once and 0xff
+                    // is shifted in.
+                    self.output.push(self.sb);
+                    self.sb = 0xff;
+                    self.sc &= !0x80;
+                    // Serial interrupt
+                    *i_flg |= 0x08;
+                }
+            }
+        }
+    }
+    /// Bytes sent out through the serial port so far.
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+}
@@ -0,0 +1,623 @@
+//! Super Game Boy: command packets sent through JOYP, screen colouring, the
+//! border and the multiplayer joypads.
+//!
+//! A packet of 16 bytes is sent as pulses on P14 and P15: a reset (both
+//! low), 128 bits LSB first (P14 low for 0, P15 low for 1, both high between
+//! the bits) and a 0 stop bit. The `*_TRN` commands take 4 KiB from the next
+//! frame: the first 256 tiles of the screen, 20 per row, read back as 2bpp
+//! tile data.
+//!
+//! In SGB mode the PPU writes the shade of each pixel (see `SHADES`), which
+//! is coloured here with the palette of its 8x8 cell. The lines drawn with
+//! the layer debug views are kept apart, and only shown: the transfers read
+//! the clean shades.
+
+use crate::io::{LcdIF, Rgb, GFX_SIZE_X, GFX_SIZE_Y};
+use crate::palette::Palette;
+use crate::ppu::Mark;
+
+pub const SGB_SIZE_X: usize = 256;
+pub const SGB_SIZE_Y: usize = 224;
+/// Position of the Game Boy screen in the border
+pub const SCREEN_X: usize = 48;
+pub const SCREEN_Y: usize = 40;
+/// Colours of the shades written by the PPU in SGB mode: the shade is the
+/// red channel.
+pub const SHADES: Palette = [Rgb(0, 0, 0), Rgb(1, 1, 1), Rgb(2, 2, 2), Rgb(3, 3, 3)];
+const CELLS_X: usize = GFX_SIZE_X / 8;
+const CELLS_Y: usize = GFX_SIZE_Y / 8;
+const TRANSFER_SIZE: usize = 0x1000;
+// attribute files sent by ATTR_TRN, 2 bits per cell
+const ATTR_FILES: usize = 45;
+const ATTR_FILE_SIZE: usize = CELLS_X * CELLS_Y / 4;
+// border map of 32x28 tiles, then the palettes 4-7
+const BORDER_MAP_SIZE: usize = 32 * 28 * 2;
+const BORDER_PALETTES: usize = 0x0800;
+// default palettes: SGB palette 1-A
+const DEFAULT_PALETTE: [u16; 4] = [0x639e, 0x263a, 0x10d4, 0x2866];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Transfer {
+    // tiles 0x00-0x7f or 0x80-0xff of the border
+    Chr(usize),
+    Pct,
+    Pal,
+    Attr,
+}
+
+/// MASK_EN
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mask {
+    Off,
+    Freeze,
+    Black,
+    Color0,
+}
+
+pub struct Sgb {
+    // P14 and P15 last written
+    lines: u8,
+    // bits received of the current packet, from a reset to the stop bit
+    bit: Option<usize>,
+    packet: [u8; 16],
+    // packets of the current command
+    command: Vec<u8>,
+    packets_left: usize,
+    // RGB555, colour 0 is shared by the 4 palettes
+    palettes: [[u16; 4]; 4],
+    // palette of each 8x8 cell
+    attrs: [u8; CELLS_X * CELLS_Y],
+    // 512 palettes of PAL_TRN
+    system_palettes: Vec<u16>,
+    attr_files: Vec<u8>,
+    // 256 tiles, 4bpp in the SNES format
+    border_tiles: Vec<u8>,
+    border_map: Vec<u8>,
+    border_palettes: [[u16; 16]; 4],
+    mask: Mask,
+    transfer: Option<Transfer>,
+    players: usize,
+    player: usize,
+    // shades written by the PPU
+    screen: Box<[Rgb; GFX_SIZE_X * GFX_SIZE_Y]>,
+    // shades and overlays of the layer debug views, on the lines flagged
+    debug_screen: Box<[(Rgb, Mark); GFX_SIZE_X * GFX_SIZE_Y]>,
+    debug_lines: [bool; GFX_SIZE_Y],
+    frame: Box<[Rgb; SGB_SIZE_X * SGB_SIZE_Y]>,
+}
+
+impl Sgb {
+    pub fn new() -> Sgb {
+        Sgb {
+            lines: 0x00,
+            bit: None,
+            packet: [0; 16],
+            command: Vec::new(),
+            packets_left: 0,
+            palettes: [DEFAULT_PALETTE; 4],
+            attrs: [0; CELLS_X * CELLS_Y],
+            system_palettes: vec![0; 512 * 4],
+            attr_files: vec![0; ATTR_FILES * ATTR_FILE_SIZE],
+            border_tiles: vec![0; 256 * 32],
+            border_map: vec![0; BORDER_MAP_SIZE],
+            border_palettes: [[0; 16]; 4],
+            mask: Mask::Off,
+            transfer: None,
+            players: 1,
+            player: 0,
+            screen: Box::new([SHADES[0]; GFX_SIZE_X * GFX_SIZE_Y]),
+            debug_screen: Box::new([(SHADES[0], Mark::None); GFX_SIZE_X * GFX_SIZE_Y]),
+            debug_lines: [false; GFX_SIZE_Y],
+            frame: Box::new([Rgb(0, 0, 0); SGB_SIZE_X * SGB_SIZE_Y]),
+        }
+    }
+    /// Joypad read through JOYP (MLT_REQ)
+    pub fn player(&self) -> usize {
+        self.player
+    }
+    /// Low nibble of JOYP when P14 and P15 are high: 0xf - player
+    pub fn joypad_id(&self) -> u8 {
+        0x0f - self.player as u8
+    }
+    /// Called on each write to JOYP.
+    pub fn write_joyp(&mut self, val: u8) {
+        let lines = val & 0x30;
+        let prev = std::mem::replace(&mut self.lines, lines);
+        match (prev, lines) {
+            (0x30, 0x00) => {
+                self.bit = Some(0);
+                self.packet = [0; 16];
+            }
+            (0x30, 0x10 | 0x20) => {
+                let one = lines == 0x10;
+                match self.bit {
+                    Some(128) => {
+                        self.bit = None;
+                        if !one {
+                            self.packet_received();
+                        }
+                    }
+                    Some(bit) => {
+                        self.packet[bit / 8] |= (one as u8) << (bit % 8);
+                        self.bit = Some(bit + 1);
+                    }
+                    None => (),
+                }
+            }
+            // both high after the buttons were read: next joypad
+            (0x10, 0x30) if self.bit.is_none() => self.player = (self.player + 1) % self.players,
+            _ => (),
+        }
+    }
+    fn packet_received(&mut self) {
+        if self.packets_left == 0 {
+            self.packets_left = (self.packet[0] & 0x07) as usize;
+            self.command.clear();
+        }
+        if self.packets_left == 0 {
+            return;
+        }
+        self.command.extend_from_slice(&self.packet);
+        self.packets_left -= 1;
+        if self.packets_left == 0 {
+            let command = std::mem::take(&mut self.command);
+            self.run_command(&command);
+            self.command = command;
+        }
+    }
+    fn run_command(&mut self, c: &[u8]) {
+        match c[0] >> 3 {
+            // PAL01, PAL23, PAL03, PAL12
+            0x00 => self.set_palettes(0, 1, c),
+            0x01 => self.set_palettes(2, 3, c),
+            0x02 => self.set_palettes(0, 3, c),
+            0x03 => self.set_palettes(1, 2, c),
+            0x04 => self.attr_blk(c),
+            0x05 => self.attr_lin(c),
+            0x06 => self.attr_div(c),
+            0x07 => self.attr_chr(c),
+            0x0a => self.pal_set(c),
+            0x0b => self.transfer = Some(Transfer::Pal),
+            // MLT_REQ
+            0x11 => {
+                self.players = match c[1] & 0x03 {
+                    1 => 2,
+                    3 => 4,
+                    _ => 1,
+                };
+                self.player = 0;
+            }
+            0x13 => self.transfer = Some(Transfer::Chr((c[1] & 0x01) as usize)),
+            0x14 => self.transfer = Some(Transfer::Pct),
+            0x15 => self.transfer = Some(Transfer::Attr),
+            // ATTR_SET
+            0x16 => {
+                self.apply_attr_file(c[1] & 0x3f);
+                if c[1] & 0x40 != 0 {
+                    self.mask = Mask::Off;
+                }
+            }
+            // MASK_EN
+            0x17 => {
+                self.mask = match c[1] & 0x03 {
+                    0 => Mask::Off,
+                    1 => Mask::Freeze,
+                    2 => Mask::Black,
+                    _ => Mask::Color0,
+                }
+            }
+            // sound, SNES code and the other system commands
+            _ => (),
+        }
+    }
+    fn set_palettes(&mut self, a: usize, b: usize, c: &[u8]) {
+        for palette in self.palettes.iter_mut() {
+            palette[0] = color(c, 1);
+        }
+        for i in 0..3 {
+            self.palettes[a][i + 1] = color(c, 3 + i * 2);
+            self.palettes[b][i + 1] = color(c, 9 + i * 2);
+        }
+    }
+    fn attr_blk(&mut self, c: &[u8]) {
+        for set in c[2..].chunks_exact(6).take(c[1] as usize) {
+            let ctrl = set[0] & 0x07;
+            let palette = |shift: u8| (set[1] >> shift) & 0x03;
+            // only the inside or the outside: the border is changed too
+            let border = match ctrl {
+                1 => Some(palette(0)),
+                4 => Some(palette(4)),
+                _ => (ctrl & 0x02 != 0).then_some(palette(2)),
+            };
+            let [x1, y1, x2, y2] = [set[2], set[3], set[4], set[5]].map(|v| (v & 0x1f) as usize);
+            for y in 0..CELLS_Y {
+                for x in 0..CELLS_X {
+                    let palette = if x1 < x && x < x2 && y1 < y && y < y2 {
+                        (ctrl & 0x01 != 0).then_some(palette(0))
+                    } else if (x1..=x2).contains(&x) && (y1..=y2).contains(&y) {
+                        border
+                    } else {
+                        (ctrl & 0x04 != 0).then_some(palette(4))
+                    };
+                    if let Some(palette) = palette {
+                        self.attrs[y * CELLS_X + x] = palette;
+                    }
+                }
+            }
+        }
+    }
+    fn attr_lin(&mut self, c: &[u8]) {
+        for &line in c[2..].iter().take(c[1] as usize) {
+            let (n, palette) = ((line & 0x1f) as usize, (line >> 5) & 0x03);
+            if line & 0x80 != 0 {
+                // horizontal: a row of cells
+                if n < CELLS_Y {
+                    self.attrs[n * CELLS_X..][..CELLS_X].fill(palette);
+                }
+            } else if n < CELLS_X {
+                for y in 0..CELLS_Y {
+                    self.attrs[y * CELLS_X + n] = palette;
+                }
+            }
+        }
+    }
+    fn attr_div(&mut self, c: &[u8]) {
+        let palette = |shift: u8| (c[1] >> shift) & 0x03;
+        let n = c[2] as usize;
+        for y in 0..CELLS_Y {
+            for x in 0..CELLS_X {
+                // horizontal line at Y = n, or vertical line at X = n
+                let pos = if c[1] & 0x40 != 0 { y } else { x };
+                self.attrs[y * CELLS_X + x] = match pos.cmp(&n) {
+                    std::cmp::Ordering::Less => palette(2),
+                    std::cmp::Ordering::Equal => palette(4),
+                    std::cmp::Ordering::Greater => palette(0),
+                };
+            }
+        }
+    }
+    fn attr_chr(&mut self, c: &[u8]) {
+        let (mut x, mut y) = (c[1] as usize, c[2] as usize);
+        let count = u16::from_le_bytes([c[3], c[4]]).min(360) as usize;
+        let vertical = c[5] & 0x01 != 0;
+        for i in 0..count {
+            let Some(&b) = c.get(6 + i / 4) else {
+                break;
+            };
+            if x < CELLS_X && y < CELLS_Y {
+                self.attrs[y * CELLS_X + x] = (b >> (6 - 2 * (i % 4))) & 0x03;
+            }
+            if vertical {
+                y += 1;
+                if y >= CELLS_Y {
+                    (x, y) = (x + 1, 0);
+                }
+            } else {
+                x += 1;
+                if x >= CELLS_X {
+                    (x, y) = (0, y + 1);
+                }
+            }
+        }
+    }
+    fn pal_set(&mut self, c: &[u8]) {
+        for i in 0..4 {
+            let n = (u16::from_le_bytes([c[1 + i * 2], c[2 + i * 2]]) & 0x01ff) as usize;
+            self.palettes[i].copy_from_slice(&self.system_palettes[n * 4..][..4]);
+        }
+        if c[9] & 0x80 != 0 {
+            self.apply_attr_file(c[9] & 0x3f);
+        }
+        if c[9] & 0x40 != 0 {
+            self.mask = Mask::Off;
+        }
+    }
+    fn apply_attr_file(&mut self, n: u8) {
+        let Some(file) = self.attr_files.chunks_exact(ATTR_FILE_SIZE).nth(n as usize) else {
+            return;
+        };
+        for (i, attr) in self.attrs.iter_mut().enumerate() {
+            *attr = (file[i / 4] >> (6 - 2 * (i % 4))) & 0x03;
+        }
+    }
+    /// Tile data of the first 256 tiles on the screen
+    fn screen_data(&self) -> Vec<u8> {
+        let mut data = vec![0; TRANSFER_SIZE];
+        for (i, row) in data.chunks_exact_mut(2).enumerate() {
+            let (tile, y) = (i / 8, i % 8);
+            let y = (tile / CELLS_X) * 8 + y;
+            let x = (tile % CELLS_X) * 8;
+            for px in 0..8 {
+                let shade = self.screen[y * GFX_SIZE_X + x + px].0;
+                row[0] |= (shade & 0x01) << (7 - px);
+                row[1] |= ((shade >> 1) & 0x01) << (7 - px);
+            }
+        }
+        data
+    }
+    fn run_transfer(&mut self, transfer: Transfer) {
+        let data = self.screen_data();
+        let words = |data: &[u8]| -> Vec<u16> {
+            data.chunks_exact(2)
+                .map(|w| u16::from_le_bytes([w[0], w[1]]))
+                .collect()
+        };
+        match transfer {
+            Transfer::Chr(half) => {
+                self.border_tiles[half * TRANSFER_SIZE..][..TRANSFER_SIZE].copy_from_slice(&data)
+            }
+            Transfer::Pct => {
+                self.border_map.copy_from_slice(&data[..BORDER_MAP_SIZE]);
+                let colors = words(&data[BORDER_PALETTES..][..4 * 16 * 2]);
+                for (palette, colors) in self.border_palettes.iter_mut().zip(colors.chunks(16)) {
+                    palette.copy_from_slice(colors);
+                }
+            }
+            Transfer::Pal => self.system_palettes = words(&data),
+            Transfer::Attr => {
+                self.attr_files
+                    .copy_from_slice(&data[..ATTR_FILES * ATTR_FILE_SIZE]);
+            }
+        }
+    }
+    /// Draws the border, then the coloured screen over it.
+    fn render(&mut self) {
+        if let Some(transfer) = self.transfer.take() {
+            self.run_transfer(transfer);
+        }
+        let backdrop = self.palettes[0][0];
+        for (i, map) in self.border_map.chunks_exact(2).enumerate() {
+            let entry = u16::from_le_bytes([map[0], map[1]]);
+            let tile = &self.border_tiles[(entry & 0xff) as usize * 32..][..32];
+            let palette = &self.border_palettes[((entry >> 10) & 0x03) as usize];
+            for row in 0..8 {
+                let ty = if entry & 0x8000 != 0 { 7 - row } else { row };
+                let planes = [
+                    tile[ty * 2],
+                    tile[ty * 2 + 1],
+                    tile[16 + ty * 2],
+                    tile[17 + ty * 2],
+                ];
+                for col in 0..8 {
+                    let bit = if entry & 0x4000 != 0 { col } else { 7 - col };
+                    let index = planes
+                        .iter()
+                        .enumerate()
+                        .fold(0, |index, (n, plane)| index | ((plane >> bit) & 0x01) << n);
+                    let color = if index == 0 {
+                        backdrop
+                    } else {
+                        palette[index as usize]
+                    };
+                    let (x, y) = ((i % 32) * 8 + col, (i / 32) * 8 + row);
+                    self.frame[y * SGB_SIZE_X + x] = Rgb::from_rgb555(color);
+                }
+            }
+        }
+        for y in 0..GFX_SIZE_Y {
+            let debug = std::mem::take(&mut self.debug_lines[y]);
+            for x in 0..GFX_SIZE_X {
+                let (pixel, mark) = if debug {
+                    self.debug_screen[y * GFX_SIZE_X + x]
+                } else {
+                    (self.screen[y * GFX_SIZE_X + x], Mark::None)
+                };
+                let shade = (pixel.0 & 0x03) as usize;
+                let color = match self.mask {
+                    Mask::Freeze => continue,
+                    Mask::Black => 0,
+                    Mask::Color0 => backdrop,
+                    Mask::Off if shade == 0 => backdrop,
+                    Mask::Off => {
+                        self.palettes[self.attrs[(y / 8) * CELLS_X + x / 8] as usize][shade]
+                    }
+                };
+                let (fx, fy) = (SCREEN_X + x, SCREEN_Y + y);
+                self.frame[fy * SGB_SIZE_X + fx] = mark.apply(Rgb::from_rgb555(color));
+            }
+        }
+    }
+    /// The LCD of the PPU: `lcd` gets the frames with the border.
+    pub fn lcd<'a, L: LcdIF>(&'a mut self, lcd: &'a mut L) -> SgbLcd<'a, L> {
+        SgbLcd { sgb: self, lcd }
+    }
+}
+
+fn color(c: &[u8], i: usize) -> u16 {
+    u16::from_le_bytes([c[i], c[i + 1]]) & 0x7fff
+}
+
+pub struct SgbLcd<'a, L: LcdIF> {
+    sgb: &'a mut Sgb,
+    lcd: &'a mut L,
+}
+
+impl<L: LcdIF> LcdIF for SgbLcd<'_, L> {
+    fn gfx(&mut self) -> &mut [Rgb; GFX_SIZE_X * GFX_SIZE_Y] {
+        &mut self.sgb.screen
+    }
+    fn present(&mut self) {
+        self.sgb.render();
+        self.lcd.present_sgb(&self.sgb.frame);
+    }
+    fn debug_line(&mut self, ly: usize, line: &[Rgb; GFX_SIZE_X], marks: &[Mark; GFX_SIZE_X]) {
+        let debug = &mut self.sgb.debug_screen[ly * GFX_SIZE_X..][..GFX_SIZE_X];
+        for ((pixel, color), mark) in debug.iter_mut().zip(line).zip(marks) {
+            *pixel = (*color, *mark);
+        }
+        self.sgb.debug_lines[ly] = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::Screen;
+    use crate::palette::LayerPalettes;
+    use crate::ppu::{DebugToggle, LayerDebug, Ppu};
+
+    fn send(sgb: &mut Sgb, command: u8, data: &[u8]) {
+        let mut bytes = vec![command << 3 | (data.len() as u8 / 16 + 1)];
+        bytes.extend_from_slice(data);
+        bytes.resize(bytes.len().div_ceil(16) * 16, 0);
+        for packet in bytes.chunks(16) {
+            sgb.write_joyp(0x30);
+            sgb.write_joyp(0x00);
+            for bit in 0..=128 {
+                let one = bit < 128 && (packet[bit / 8] >> (bit % 8)) & 0x01 != 0;
+                sgb.write_joyp(0x30);
+                sgb.write_joyp(if one { 0x10 } else { 0x20 });
+            }
+            sgb.write_joyp(0x30);
+        }
+    }
+    fn shade(screen: &mut Screen, x: usize, y: usize, shade: u8) {
+        screen.gfx[y * GFX_SIZE_X + x] = SHADES[shade as usize];
+    }
+    fn frame(sgb: &mut Sgb) -> Screen {
+        let mut screen = Screen::new();
+        sgb.lcd(&mut screen).present();
+        screen
+    }
+    #[test]
+    fn palettes_and_attributes() {
+        let mut sgb = Sgb::new();
+        // PAL01: colour 0 white, palette 0 red, palette 1 green
+        let mut pal01 = vec![0xff, 0x7f];
+        pal01.extend_from_slice(&[0x1f, 0x00, 0x1f, 0x00, 0x1f, 0x00]);
+        pal01.extend_from_slice(&[0xe0, 0x03, 0xe0, 0x03, 0xe0, 0x03]);
+        send(&mut sgb, 0x00, &pal01);
+        // ATTR_BLK: palette 1 inside and on cells (1, 1)-(3, 3)
+        send(&mut sgb, 0x04, &[1, 0x01, 0x01, 1, 1, 3, 3]);
+        // ATTR_LIN: palette 1 on row 17
+        send(&mut sgb, 0x05, &[1, 0x80 | 0x20 | 17]);
+        let mut screen = Screen::new();
+        let mut lcd = sgb.lcd(&mut screen);
+        for (x, y) in [(0, 0), (8, 8), (24, 24), (32, 32), (0, 143)] {
+            lcd.gfx()[y * GFX_SIZE_X + x] = SHADES[3];
+        }
+        lcd.present();
+        let (red, green) = (Rgb(0xff, 0, 0), Rgb(0, 0xff, 0));
+        let at = |x: usize, y: usize| screen.gfx[y * GFX_SIZE_X + x];
+        assert_eq!(Rgb(0xff, 0xff, 0xff), at(1, 0));
+        assert_eq!((red, green, green), (at(0, 0), at(8, 8), at(24, 24)));
+        assert_eq!((red, green), (at(32, 32), at(0, 143)));
+        // MASK_EN: black
+        send(&mut sgb, 0x17, &[2]);
+        assert_eq!(Rgb(0, 0, 0), frame(&mut sgb).gfx[1]);
+    }
+    #[test]
+    fn transfers() {
+        let mut sgb = Sgb::new();
+        // PAL_TRN: system palette 1 is 0x001f (red) in all the colours
+        let mut data = vec![0u8; TRANSFER_SIZE];
+        for c in data[8..16].chunks_exact_mut(2) {
+            c.copy_from_slice(&[0x1f, 0x00]);
+        }
+        send(&mut sgb, 0x0b, &[]);
+        let mut screen = Screen::new();
+        for (i, row) in data.chunks_exact(2).enumerate() {
+            let (tile, y) = (i / 8, i % 8);
+            for px in 0..8 {
+                let shade = (row[0] >> (7 - px)) & 0x01 | ((row[1] >> (7 - px)) & 0x01) << 1;
+                let x = (tile % CELLS_X) * 8 + px;
+                self::shade(&mut screen, x, (tile / CELLS_X) * 8 + y, shade);
+            }
+        }
+        sgb.screen.copy_from_slice(&screen.gfx);
+        frame(&mut sgb);
+        assert_eq!(0x001f, sgb.system_palettes[4]);
+        // PAL_SET: palette 1 for all
+        send(&mut sgb, 0x0a, &[1, 0, 1, 0, 1, 0, 1, 0, 0]);
+        assert_eq!([0x001f; 4], sgb.palettes[3]);
+    }
+    #[test]
+    fn debug_views() {
+        let toggles = [
+            DebugToggle::Bg,
+            DebugToggle::Window,
+            DebugToggle::Obj,
+            DebugToggle::Highlight,
+            DebugToggle::ObjBoxes,
+            DebugToggle::Priority,
+        ];
+        // CHR_TRN of a frame with the BG, the window and 2 objects
+        let transfer = |debug: LayerDebug| {
+            let mut sgb = Sgb::new();
+            let mut ppu = Ppu::new();
+            ppu.set_dmg_palettes(LayerPalettes::uniform(SHADES));
+            *ppu.layer_debug_mut() = debug;
+            let mut i_flg = 0;
+            ppu.write_lcd_reg(0, 0x00, &mut i_flg);
+            ppu.write_vram(0x00, 0xff);
+            ppu.write_vram(0x11, 0xff);
+            for addr in 0x20..0x22 {
+                ppu.write_vram(addr, 0xff);
+            }
+            for addr in 0x1c00..0x1c20 {
+                ppu.write_vram(addr, 2);
+            }
+            for (i, val) in [16, 24, 1, 0x80, 16, 40, 1, 0x00].into_iter().enumerate() {
+                ppu.write_oam(i, val);
+            }
+            // LCDC, BGP, OBP0, WX
+            for (index, val) in [(0, 0xf3), (7, 0xe4), (8, 0xe4), (0xb, 87)] {
+                ppu.write_lcd_reg(index, val, &mut i_flg);
+            }
+            // the first frame after the LCD is turned on is blank
+            let mut screen = Screen::new();
+            for frames in 1..=2 {
+                if frames == 2 {
+                    send(&mut sgb, 0x13, &[0]);
+                }
+                while screen.frames < frames {
+                    ppu.run(&mut sgb.lcd(&mut screen), &mut i_flg).unwrap();
+                }
+            }
+            (sgb.border_tiles[..TRANSFER_SIZE].to_vec(), screen.gfx)
+        };
+        let (tiles, gfx) = transfer(LayerDebug::default());
+        assert!(tiles.iter().any(|&b| b != 0));
+        for toggle in toggles {
+            let mut debug = LayerDebug::default();
+            debug.toggle(toggle);
+            let (debug_tiles, debug_gfx) = transfer(debug);
+            assert_eq!(tiles, debug_tiles, "{:?}", toggle);
+            // still shown
+            assert_ne!(gfx, debug_gfx, "{:?}", toggle);
+        }
+    }
+    #[test]
+    fn border() {
+        let mut sgb = Sgb::new();
+        // tile 1: colour 1 on the left column, map entry 0: tile 1, palette
+        // 5, X flip
+        sgb.border_tiles[32..48].copy_from_slice(&[[0x80, 0x00]; 8].concat());
+        sgb.border_map[..2].copy_from_slice(&[0x01, 0x54]);
+        sgb.border_palettes[1][1] = 0x7c00;
+        sgb.screen.fill(SHADES[0]);
+        let mut screen = Screen::new();
+        sgb.lcd(&mut screen).present();
+        let blue = Rgb(0, 0, 0xff);
+        assert_eq!(blue, Rgb::from_rgb555(0x7c00));
+        assert_eq!(blue, sgb.frame[7]);
+        assert_eq!(Rgb::from_rgb555(DEFAULT_PALETTE[0]), sgb.frame[0]);
+        assert_eq!(1, screen.frames);
+    }
+    #[test]
+    fn multiplayer() {
+        let mut sgb = Sgb::new();
+        send(&mut sgb, 0x11, &[0x03]);
+        let mut ids = Vec::new();
+        for _ in 0..5 {
+            ids.push(sgb.joypad_id());
+            for lines in [0x20, 0x10, 0x30] {
+                sgb.write_joyp(lines);
+            }
+        }
+        assert_eq!(vec![0x0f, 0x0e, 0x0d, 0x0c, 0x0f], ids);
+        // back to one player
+        send(&mut sgb, 0x11, &[0x00]);
+        assert_eq!(1, sgb.players);
+    }
+}
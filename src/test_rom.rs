@@ -0,0 +1,310 @@
+//! Headless runner for the Blargg and Mooneye test ROMs.
+//!
+//! Blargg ROMs report through the serial port ("Passed" / "Failed") or
+//! through the signature area at 0xa000 (0xa001..=0xa003 = de b0 61,
+//! 0xa000 = result code, text from 0xa004).
+//! Mooneye ROMs execute `LD B,B` with B/C/D/E/H/L = 3/5/8/13/21/34 on success
+//! and 0x42 in all of them on failure.
+
+use crate::cpu::Cpu;
+use crate::io::Screen;
+use crate::memory::{MemoryIF, Model};
+use crate::mmu::Mmu;
+use std::fmt;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+const CLOCK_M_PER_SEC: usize = 1 << 20;
+// Check the 0xa000 signature area about once a frame
+const SIGNATURE_CHECK_M: usize = 17556;
+const DEFAULT_TIMEOUT_SEC: usize = 30;
+
+const MOONEYE_PASS: [u8; 6] = [3, 5, 8, 13, 21, 34];
+const MOONEYE_FAIL: [u8; 6] = [0x42; 6];
+
+pub enum TestResult {
+    Pass,
+    Fail(String),
+    Timeout,
+    Crash(String),
+}
+
+/// Emulator settings for a test run
+#[derive(Clone, Copy, Default)]
+pub struct RunOptions {
+    pub block_cache: bool,
+    pub fast_ppu: bool,
+}
+
+impl TestResult {
+    fn is_pass(&self) -> bool {
+        matches!(self, TestResult::Pass)
+    }
+}
+
+impl fmt::Display for TestResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TestResult::Pass => write!(f, "pass"),
+            TestResult::Fail(detail) => write!(f, "FAIL     {}", detail),
+            TestResult::Timeout => write!(f, "TIMEOUT"),
+            TestResult::Crash(detail) => write!(f, "CRASH    {}", detail),
+        }
+    }
+}
+
+/// Runs every `.gb` file under `args[0]` (a directory or a single ROM) and
+/// prints a result table. `args[1]` is the timeout in emulated seconds.
+/// Returns the process exit code: 0 when every ROM passed.
+pub fn main(args: &[String]) -> i32 {
+    let mut options = RunOptions::default();
+    let args: Vec<&String> = args
+        .iter()
+        .filter(|a| match a.as_str() {
+            "--block-cache" => {
+                options.block_cache = true;
+                false
+            }
+            "--fast-ppu" => {
+                options.fast_ppu = true;
+                false
+            }
+            _ => true,
+        })
+        .collect();
+    let path = match args.first() {
+        Some(path) => Path::new(path),
+        None => {
+            println!("usage: rs-gb test-roms <dir|rom> [timeout_sec] [--block-cache] [--fast-ppu]");
+            return 2;
+        }
+    };
+    let timeout_sec = match args.get(1) {
+        Some(s) => s
+            .trim()
+            .parse()
+            .expect("timeout_sec: cannot parse to usize"),
+        None => DEFAULT_TIMEOUT_SEC,
+    };
+
+    let mut roms = Vec::new();
+    collect_roms(path, &mut roms);
+    roms.sort();
+
+    // panics are reported in the table
+    panic::set_hook(Box::new(|_| {}));
+    let mut passed = 0;
+    for rom in &roms {
+        let name = rom.strip_prefix(path).unwrap_or(rom);
+        let name = if name.as_os_str().is_empty() {
+            rom
+        } else {
+            name
+        };
+        let result = run_rom(rom, timeout_sec, options);
+        if result.is_pass() {
+            passed += 1;
+        }
+        println!("{:<48} {}", name.display(), result);
+    }
+    let _ = panic::take_hook();
+    println!("{}/{} passed", passed, roms.len());
+
+    if passed == roms.len() {
+        0
+    } else {
+        1
+    }
+}
+
+fn collect_roms(path: &Path, roms: &mut Vec<PathBuf>) {
+    if path.is_dir() {
+        if let Ok(entries) = fs::read_dir(path) {
+            for entry in entries.flatten() {
+                collect_roms(&entry.path(), roms);
+            }
+        }
+    } else if path.extension().is_some_and(|ext| ext == "gb") {
+        roms.push(path.to_path_buf());
+    }
+}
+
+pub fn run_rom(rom: &Path, timeout_sec: usize, options: RunOptions) -> TestResult {
+    let mut mmu = Mmu::new();
+    if let Err(e) = mmu.load(&rom.to_string_lossy()) {
+        return TestResult::Crash(e.to_string());
+    }
+    // enhancements such as unlimited sprites stay off
+    mmu.set_fast_ppu(options.fast_ppu);
+    let mut cpu = Cpu::new();
+    if mmu.cgb_cart() {
+        mmu.set_model(Model::Cgb);
+        cpu.set_model(Model::Cgb);
+    }
+    if options.block_cache {
+        cpu.enable_block_cache();
+    }
+    run(cpu, mmu, timeout_sec)
+}
+
+/// Runs a ROM image, e.g. one built by `cpu::asm`.
+#[cfg(test)]
+pub fn run_image(rom: &[u8], timeout_sec: usize) -> TestResult {
+    let mut mmu = Mmu::new();
+    if let Err(e) = mmu.load_rom(rom) {
+        return TestResult::Crash(e.to_string());
+    }
+    run(Cpu::new(), mmu, timeout_sec)
+}
+
+fn run(mut cpu: Cpu, mut mmu: Mmu, timeout_sec: usize) -> TestResult {
+    let mut screen = Screen::new();
+    let mut serial_len = 0;
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        for clock_m in 0..timeout_sec * CLOCK_M_PER_SEC {
+            let (_, stop) = match cpu.run(&mut mmu, false) {
+                Ok(r) => r,
+                Err(e) => return TestResult::Crash(e),
+            };
+            if let Err(e) = mmu.run_ppu(&mut screen).and_then(|_| mmu.run_timer(stop)) {
+                return TestResult::Crash(e);
+            }
+
+            if cpu.at_breakpoint() {
+                match cpu.regs_bcdehl() {
+                    MOONEYE_PASS => return TestResult::Pass,
+                    MOONEYE_FAIL => return TestResult::Fail("mooneye".to_string()),
+                    _ => (),
+                }
+            }
+            let serial = mmu.serial_output();
+            if serial.len() != serial_len {
+                serial_len = serial.len();
+                if let Some(result) = check_serial(serial) {
+                    return result;
+                }
+            }
+            if clock_m % SIGNATURE_CHECK_M == 0 {
+                if let Some(result) = check_signature(&mmu) {
+                    return result;
+                }
+            }
+        }
+        TestResult::Timeout
+    }));
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            let msg = if let Some(s) = e.downcast_ref::<&str>() {
+                s.to_string()
+            } else if let Some(s) = e.downcast_ref::<String>() {
+                s.clone()
+            } else {
+                "panic".to_string()
+            };
+            TestResult::Crash(msg)
+        }
+    }
+}
+
+fn check_serial(output: &[u8]) -> Option<TestResult> {
+    let text = String::from_utf8_lossy(output);
+    // wait for the end of the line, "Failed" is followed by the details
+    let line_done = |i: usize| text[i..].contains('\n');
+    if let Some(i) = text.find("Passed") {
+        line_done(i).then_some(TestResult::Pass)
+    } else if let Some(i) = text.find("Failed") {
+        line_done(i).then(|| TestResult::Fail(summarize(&text)))
+    } else {
+        None
+    }
+}
+
+fn check_signature(memory: &impl MemoryIF) -> Option<TestResult> {
+    let signature = [
+        memory.read_byte(0xa001),
+        memory.read_byte(0xa002),
+        memory.read_byte(0xa003),
+    ];
+    if signature != [0xde, 0xb0, 0x61] {
+        return None;
+    }
+    match memory.read_byte(0xa000) {
+        0x80 => None, // still running
+        0x00 => Some(TestResult::Pass),
+        code => {
+            let mut text = Vec::new();
+            let mut addr = 0xa004;
+            while addr < 0xc000 {
+                let c = memory.read_byte(addr);
+                if c == 0 {
+                    break;
+                }
+                text.push(c);
+                addr += 1;
+            }
+            let text = String::from_utf8_lossy(&text);
+            Some(TestResult::Fail(format!(
+                "code {:#04x}: {}",
+                code,
+                summarize(&text)
+            )))
+        }
+    }
+}
+
+fn summarize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestMemory {
+        memory: [u8; 0x10000],
+    }
+    impl MemoryIF for TestMemory {
+        fn read_byte(&self, addr: u16) -> u8 {
+            self.memory[addr as usize]
+        }
+        fn write_byte(&mut self, addr: u16, val: u8) {
+            self.memory[addr as usize] = val;
+        }
+    }
+
+    #[test]
+    fn serial_result() {
+        assert!(check_serial(b"01-special\n\n").is_none());
+        assert!(check_serial(b"01-special\n\nPass").is_none());
+        assert!(check_serial(b"01-special\n\nPassed\n").unwrap().is_pass());
+        match check_serial(b"01-special\n\nFailed #3\n") {
+            Some(TestResult::Fail(s)) => assert_eq!("01-special Failed #3", s),
+            _ => panic!(),
+        }
+    }
+    #[test]
+    fn signature_result() {
+        let mut m = TestMemory {
+            memory: [0; 0x10000],
+        };
+        assert!(check_signature(&m).is_none());
+        m.write_byte(0xa001, 0xde);
+        m.write_byte(0xa002, 0xb0);
+        m.write_byte(0xa003, 0x61);
+        m.write_byte(0xa000, 0x80);
+        assert!(check_signature(&m).is_none());
+        m.write_byte(0xa000, 0x00);
+        assert!(check_signature(&m).unwrap().is_pass());
+        m.write_byte(0xa000, 0x01);
+        for (i, c) in b"Failed\n".iter().enumerate() {
+            m.write_byte(0xa004 + i as u16, *c);
+        }
+        match check_signature(&m) {
+            Some(TestResult::Fail(s)) => assert_eq!("code 0x01: Failed", s),
+            _ => panic!(),
+        }
+    }
+}
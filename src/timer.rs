@@ -1,62 +1,193 @@
 use crate::memory::{DIV, TAC, TIMA, TMA};
 
+// register indices
+const I_DIV: usize = 0;
+const I_TIMA: usize = (TIMA - DIV) as usize;
+const I_TMA: usize = (TMA - DIV) as usize;
+const I_TAC: usize = (TAC - DIV) as usize;
+
+/// DIV and TIMA are driven by a 16-bit counter incremented every T-cycle.
+/// DIV is its upper byte, and TIMA is incremented on a falling edge of the
+/// counter bit selected by TAC (ANDed with the enable bit), so writing DIV
+/// or TAC can increment TIMA too.
 pub struct Timer {
-    clock_div_m: usize,
-    clock_tima_m: usize,
-    timer_regs: [u8; 4],
+    counter: u16,
+    tima: u8,
+    tma: u8,
+    tac: u8,
+    overflow: Overflow,
+}
+
+/// TIMA reads 0 for one M-cycle after it overflows, then TMA is loaded and
+/// the interrupt is requested.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Overflow {
+    None,
+    // TIMA overflowed in the last cycle; writing TIMA cancels the reload
+    Pending,
+    // TMA was loaded in the last cycle; writing TIMA is ignored and writing
+    // TMA writes TIMA too
+    Reloaded,
 }
 
 impl Timer {
     pub fn new() -> Timer {
         Timer {
-            clock_div_m: 0,
-            clock_tima_m: 0,
-            timer_regs: [0; 4],
+            counter: 0,
+            tima: 0,
+            tma: 0,
+            tac: 0,
+            overflow: Overflow::None,
         }
     }
     pub fn read_timer_reg(&self, index: usize) -> u8 {
-        self.timer_regs[index]
+        match index {
+            I_DIV => (self.counter >> 8) as u8,
+            I_TIMA => self.tima,
+            I_TMA => self.tma,
+            I_TAC => self.tac | 0xf8,
+            _ => 0xff,
+        }
     }
     pub fn write_timer_reg(&mut self, index: usize, val: u8) {
-        let val = if index == 0 /*DIV - DIV*/ { 0 } else { val };
-        self.timer_regs[index] = val;
+        match index {
+            I_DIV => self.set_counter(0, self.tac),
+            I_TIMA => match self.overflow {
+                Overflow::Reloaded => (),
+                Overflow::Pending => {
+                    self.overflow = Overflow::None;
+                    self.tima = val;
+                }
+                Overflow::None => self.tima = val,
+            },
+            I_TMA => {
+                self.tma = val;
+                if self.overflow == Overflow::Reloaded {
+                    self.tima = val;
+                }
+            }
+            I_TAC => self.set_counter(self.counter, val & 0x07),
+            _ => (),
+        }
     }
+    /// Runs one M-cycle. The counter is held at 0 in STOP mode.
     pub fn run(&mut self, i_flg: &mut u8, stop: bool) -> Result<(), String> {
-        if stop {
-            self.clock_div_m = 0;
-            self.timer_regs[0 /*DIV - DIV*/] = 0;
-        } else {
-            self.clock_div_m += 1;
-            if self.clock_div_m >= 64 {
-                self.clock_div_m = 0;
-                let div = self.timer_regs[0 /*DIV - DIV*/];
-                self.timer_regs[0 /*DIV - DIV*/] = div.wrapping_add(1);
+        match self.overflow {
+            Overflow::Pending => {
+                self.tima = self.tma;
+                // Timer interrupt
+                *i_flg |= 0x04;
+                self.overflow = Overflow::Reloaded;
             }
+            Overflow::Reloaded => self.overflow = Overflow::None,
+            Overflow::None => (),
         }
-        let tac = self.timer_regs[(TAC - DIV) as usize];
-        let m = match tac & 0x03 {
-            0x00 => 256,
-            0x01 => 4,
-            0x02 => 16,
-            0x03 => 64,
-            _ => return Err("Timer::run: invalid tac value".to_string()),
+        let counter = if stop {
+            0
+        } else {
+            self.counter.wrapping_add(4)
         };
-        if tac & 0x04 != 0 {
-            self.clock_tima_m += 1;
-            if self.clock_tima_m >= m {
-                self.clock_tima_m = 0;
-                let tima = self.timer_regs[(TIMA - DIV) as usize];
-                if tima == u8::MAX {
-                    // overflow
-                    let tma = self.timer_regs[(TMA - DIV) as usize];
-                    self.timer_regs[(TIMA - DIV) as usize] = tma;
-                    // Timer interrupt
-                    *i_flg |= 0x04;
-                } else {
-                    self.timer_regs[(TIMA - DIV) as usize] = tima + 1;
-                }
-            }
-        }
+        self.set_counter(counter, self.tac);
         Ok(())
     }
+
+    /// Timer input: the counter bit selected by TAC, ANDed with the enable bit
+    fn signal(counter: u16, tac: u8) -> bool {
+        let bit = match tac & 0x03 {
+            0x00 => 9,
+            0x01 => 3,
+            0x02 => 5,
+            _ => 7,
+        };
+        tac & 0x04 != 0 && counter & (1 << bit) != 0
+    }
+    fn set_counter(&mut self, counter: u16, tac: u8) {
+        let falling = Timer::signal(self.counter, self.tac) && !Timer::signal(counter, tac);
+        self.counter = counter;
+        self.tac = tac;
+        if falling {
+            self.increment();
+        }
+    }
+    fn increment(&mut self) {
+        if self.tima == u8::MAX {
+            self.tima = 0;
+            self.overflow = Overflow::Pending;
+        } else {
+            self.tima += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(timer: &mut Timer, m: usize) -> u8 {
+        let mut i_flg = 0;
+        for _ in 0..m {
+            timer.run(&mut i_flg, false).unwrap();
+        }
+        i_flg
+    }
+
+    #[test]
+    fn div_and_tima() {
+        let mut timer = Timer::new();
+        run(&mut timer, 64);
+        assert_eq!(1, timer.read_timer_reg(I_DIV));
+        // 4 M-cycles per increment
+        timer.write_timer_reg(I_TAC, 0x05);
+        assert_eq!(0xfd, timer.read_timer_reg(I_TAC));
+        run(&mut timer, 40);
+        assert_eq!(10, timer.read_timer_reg(I_TIMA));
+        // writing DIV resets the counter
+        timer.write_timer_reg(I_DIV, 0x12);
+        assert_eq!(0, timer.read_timer_reg(I_DIV));
+    }
+    #[test]
+    fn falling_edge_glitches() {
+        let mut timer = Timer::new();
+        timer.write_timer_reg(I_TAC, 0x05);
+        // bit 3 set
+        run(&mut timer, 2);
+        assert_eq!(0, timer.read_timer_reg(I_TIMA));
+        timer.write_timer_reg(I_DIV, 0);
+        assert_eq!(1, timer.read_timer_reg(I_TIMA));
+        run(&mut timer, 2);
+        // disabling the timer while the bit is set
+        timer.write_timer_reg(I_TAC, 0x01);
+        assert_eq!(2, timer.read_timer_reg(I_TIMA));
+        // selecting a cleared bit
+        timer.write_timer_reg(I_TAC, 0x05);
+        timer.write_timer_reg(I_TAC, 0x06);
+        assert_eq!(3, timer.read_timer_reg(I_TIMA));
+    }
+    #[test]
+    fn overflow_delay() {
+        let mut timer = Timer::new();
+        timer.write_timer_reg(I_TMA, 0x80);
+        timer.write_timer_reg(I_TIMA, 0xff);
+        timer.write_timer_reg(I_TAC, 0x05);
+        assert_eq!(0, run(&mut timer, 4));
+        assert_eq!(0, timer.read_timer_reg(I_TIMA));
+        assert_eq!(0x04, run(&mut timer, 1));
+        assert_eq!(0x80, timer.read_timer_reg(I_TIMA));
+        // TIMA writes are ignored in the reload cycle, TMA writes go through
+        timer.write_timer_reg(I_TIMA, 0x10);
+        timer.write_timer_reg(I_TMA, 0x90);
+        assert_eq!(0x90, timer.read_timer_reg(I_TIMA));
+        run(&mut timer, 1);
+        timer.write_timer_reg(I_TMA, 0xa0);
+        assert_eq!(0x90, timer.read_timer_reg(I_TIMA));
+
+        // writing TIMA in the delay cycle cancels the reload
+        let mut timer = Timer::new();
+        timer.write_timer_reg(I_TIMA, 0xff);
+        timer.write_timer_reg(I_TAC, 0x05);
+        run(&mut timer, 4);
+        timer.write_timer_reg(I_TIMA, 0x10);
+        assert_eq!(0, run(&mut timer, 1));
+        assert_eq!(0x10, timer.read_timer_reg(I_TIMA));
+    }
 }
@@ -0,0 +1,317 @@
+//! Runs a ROM and compares its trace with a reference log, line by line.
+//!
+//! The reference is in the gameboy-doctor format or in the one written by
+//! `--trace`. Only the fields present in both lines are compared (`KEY:VAL`
+//! pairs before ` | `), so the cycle count, the bank and LY are compared only
+//! when the reference has them. The CPU starts from the post-boot state, as
+//! the reference logs do.
+
+use crate::cpu::disasm::parse_num;
+use crate::cpu::trace::{TraceOptions, Tracer};
+use crate::cpu::Cpu;
+use crate::io::Screen;
+use crate::mmu::{MemoryDump, Mmu};
+use std::collections::VecDeque;
+use std::fs;
+
+const CLOCK_M_PER_SEC: usize = 1 << 20;
+// give up when no instruction is executed for this long (HALT / STOP)
+const IDLE_TIMEOUT_SEC: usize = 10;
+const DEFAULT_CONTEXT: usize = 5;
+
+const USAGE: &str = "\
+usage: rs-gb trace-diff <rom> <reference> [options]
+  --skip=<field>,..   do not compare these fields (e.g. LY,CY)
+  --skip-until=<pc>   compare the skipped fields after PC first reaches pc
+  --context=<n>       lines printed before the mismatch (default 5)";
+
+pub struct DiffOptions {
+    pub skip: Vec<String>,
+    pub skip_until: Option<u16>,
+    pub context: usize,
+}
+
+pub enum DiffResult {
+    /// All the reference lines matched
+    Match(usize),
+    Mismatch {
+        // 1-based line number in the reference
+        line: usize,
+        expected: String,
+        actual: String,
+        fields: Vec<String>,
+        context: Vec<String>,
+        pc: u16,
+        // the memory before the instruction
+        memory: MemoryDump,
+    },
+    /// The emulator stopped producing lines
+    Stopped(usize, String),
+}
+
+/// `rs-gb trace-diff <rom> <reference> [options]`
+pub fn main(args: &[String]) -> i32 {
+    let (rom, reference) = match args {
+        [rom, reference, ..] => (rom, reference),
+        _ => {
+            println!("{}", USAGE);
+            return 2;
+        }
+    };
+    let mut options = DiffOptions {
+        skip: Vec::new(),
+        skip_until: None,
+        context: DEFAULT_CONTEXT,
+    };
+    for arg in &args[2..] {
+        let parsed = match arg.split_once('=') {
+            Some(("--skip", val)) => {
+                options.skip = val.split(',').map(|s| s.trim().to_uppercase()).collect();
+                Some(())
+            }
+            Some(("--skip-until", val)) => parse_num(val).map(|pc| {
+                options.skip_until = Some(pc as u16);
+            }),
+            Some(("--context", val)) => val.parse().ok().map(|n| options.context = n),
+            _ => None,
+        };
+        if parsed.is_none() {
+            println!("invalid argument {}\n{}", arg, USAGE);
+            return 2;
+        }
+    }
+
+    let mut mmu = Mmu::new();
+    if let Err(e) = mmu.load(rom) {
+        println!("error {}", e);
+        return 1;
+    }
+    let reference = match fs::read_to_string(reference) {
+        Ok(s) => s,
+        Err(e) => {
+            println!("error {}: {}", reference, e);
+            return 1;
+        }
+    };
+    let reference: Vec<&str> = reference.lines().filter(|l| !l.trim().is_empty()).collect();
+
+    match diff(&mut mmu, &reference, &options) {
+        DiffResult::Match(n) => {
+            println!("{} lines match", n);
+            0
+        }
+        DiffResult::Stopped(n, e) => {
+            println!("{} lines match, then the emulator stopped: {}", n, e);
+            1
+        }
+        DiffResult::Mismatch {
+            line,
+            expected,
+            actual,
+            fields,
+            context,
+            pc,
+            memory,
+        } => {
+            println!("mismatch at line {}", line);
+            for l in &context {
+                println!("           {}", l);
+            }
+            println!("expected:  {}", expected);
+            println!("actual:    {}", actual);
+            for f in &fields {
+                println!("  {}", f);
+            }
+            println!("memory before the instruction at 0x{:04x}:", pc);
+            memory.print();
+            1
+        }
+    }
+}
+
+pub fn diff(mmu: &mut Mmu, reference: &[&str], options: &DiffOptions) -> DiffResult {
+    let mut cpu = Cpu::new();
+    cpu.skip_boot_rom();
+    cpu.set_tracer(Tracer::buffered(TraceOptions {
+        cycles: true,
+        bank: true,
+        disasm: true,
+        ly: true,
+        ..TraceOptions::default()
+    }));
+    let mut screen = Screen::new();
+    let mut context = VecDeque::new();
+    let mut skipping = !options.skip.is_empty();
+    let mut n = 0;
+    let mut idle = 0;
+    let mut pc = 0x0100;
+    let mut memory = mmu.memory_dump(pc);
+
+    while n < reference.len() {
+        let stopped = |e: String| DiffResult::Stopped(n, e);
+        // the state compared with the reference, before the instruction
+        if cpu.at_instruction() {
+            memory = mmu.memory_dump(pc);
+        }
+        let stop = match cpu.run(mmu, false) {
+            Ok((next, stop)) => {
+                pc = next;
+                stop
+            }
+            Err(e) => return stopped(e),
+        };
+        if let Err(e) = mmu.run_ppu(&mut screen).and_then(|_| mmu.run_timer(stop)) {
+            return stopped(e);
+        }
+
+        let lines = cpu.tracer_mut().map(|t| t.take_lines()).unwrap_or_default();
+        if lines.is_empty() {
+            idle += 1;
+            if idle > IDLE_TIMEOUT_SEC * CLOCK_M_PER_SEC {
+                return stopped("no instruction executed".to_string());
+            }
+            continue;
+        }
+        idle = 0;
+        for actual in lines {
+            if n >= reference.len() {
+                break;
+            }
+            if skipping && options.skip_until.is_some() && pc_of(&actual) == options.skip_until {
+                skipping = false;
+            }
+            let skip: &[String] = if skipping { &options.skip } else { &[] };
+            let fields = compare(reference[n], &actual, skip);
+            if !fields.is_empty() {
+                return DiffResult::Mismatch {
+                    line: n + 1,
+                    expected: reference[n].to_string(),
+                    pc: pc_of(&actual).unwrap_or(0),
+                    actual,
+                    fields,
+                    context: context.into(),
+                    memory,
+                };
+            }
+            context.push_back(actual);
+            if context.len() > options.context {
+                context.pop_front();
+            }
+            n += 1;
+        }
+    }
+    DiffResult::Match(n)
+}
+
+/// `KEY:VAL` pairs, the disassembly after ` | ` is ignored.
+fn fields(line: &str) -> Vec<(&str, &str)> {
+    let line = line.split(" | ").next().unwrap_or("");
+    line.split_whitespace()
+        .filter_map(|f| f.split_once(':'))
+        .collect()
+}
+
+fn pc_of(line: &str) -> Option<u16> {
+    fields(line)
+        .iter()
+        .find(|(k, _)| *k == "PC")
+        .and_then(|(_, v)| u16::from_str_radix(v, 16).ok())
+}
+
+/// Returns the mismatching fields, e.g. `A: expected 01, actual 00`.
+fn compare(expected: &str, actual: &str, skip: &[String]) -> Vec<String> {
+    let actual = fields(actual);
+    let expected = fields(expected);
+    if expected.is_empty() {
+        return vec!["no fields in the reference line".to_string()];
+    }
+    expected
+        .iter()
+        .filter(|(k, _)| !skip.iter().any(|s| s.eq_ignore_ascii_case(k)))
+        .filter_map(|(k, e)| {
+            let (_, a) = actual.iter().find(|(ak, _)| ak == k)?;
+            (!e.eq_ignore_ascii_case(a)).then(|| format!("{}: expected {}, actual {}", k, e, a))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::asm::{assemble, build_rom};
+    use crate::memory::MemoryIF;
+
+    const LINE: &str = "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,C3,50,01";
+
+    #[test]
+    fn compare_fields() {
+        let actual = format!("{} LY:00 CY:0 BANK:00 | nop", LINE);
+        assert!(compare(LINE, &actual, &[]).is_empty());
+        let expected = LINE.replace("A:01", "A:02") + " LY:90";
+        assert_eq!(
+            vec!["A: expected 02, actual 01", "LY: expected 90, actual 00"],
+            compare(&expected, &actual, &[])
+        );
+        assert_eq!(
+            vec!["A: expected 02, actual 01"],
+            compare(&expected, &actual, &["LY".to_string()])
+        );
+        assert_eq!(Some(0x100), pc_of(&actual));
+    }
+    #[test]
+    fn diff_finds_first_mismatch() {
+        let src = "
+            SECTION \"main\", ROM0[$0150]
+            ld a, 1
+        .loop:
+            inc a
+            ldh [$80], a
+            jr .loop
+        ";
+        let rom = build_rom(&assemble(src).unwrap(), "DIFF").unwrap();
+        let mut reference = [
+            "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100".to_string(),
+            "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0101".to_string(),
+            "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0150 LY:90".to_string(),
+            "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0152".to_string(),
+            "A:02 F:10 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0153".to_string(),
+        ];
+        let options = DiffOptions {
+            skip: vec!["LY".to_string()],
+            skip_until: None,
+            context: 2,
+        };
+        let reference_str: Vec<&str> = reference.iter().map(|s| s.as_str()).collect();
+        let mut mmu = Mmu::new();
+        mmu.load_rom(&rom).unwrap();
+        assert!(matches!(
+            diff(&mut mmu, &reference_str, &options),
+            DiffResult::Match(5)
+        ));
+
+        reference[4] = reference[4].replace("A:02", "A:03");
+        let reference_str: Vec<&str> = reference.iter().map(|s| s.as_str()).collect();
+        let mut mmu = Mmu::new();
+        mmu.load_rom(&rom).unwrap();
+        match diff(&mut mmu, &reference_str, &options) {
+            DiffResult::Mismatch {
+                line,
+                fields,
+                context,
+                pc,
+                memory,
+                ..
+            } => {
+                assert_eq!(5, line);
+                assert_eq!(vec!["A: expected 03, actual 02"], fields);
+                assert_eq!(2, context.len());
+                assert_eq!(0x153, pc);
+                // before `ldh [$80], a` stores 2
+                assert_eq!(Some(0xe0), memory.read(0x153));
+                assert_eq!(Some(0x00), memory.read(0xff80));
+                assert_eq!(0x02, mmu.read_byte(0xff80));
+            }
+            _ => panic!(),
+        }
+    }
+}